@@ -1,7 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use intel_8080_emu_rust::emulator::cpu::emulate_8080_op;
 use intel_8080_emu_rust::emulator::data_types::State8080;
-use intel_8080_emu_rust::emulator::machine::SpaceInvadersMachine;
+use intel_8080_emu_rust::emulator::machine::{Engine, SpaceInvadersMachine};
 
 fn benchmark_emulate_8080_op(c: &mut Criterion) {
     let mut state = State8080::default();
@@ -11,8 +11,17 @@ fn benchmark_emulate_8080_op(c: &mut Criterion) {
     });
 }
 
+fn benchmark_emulate_8080_op_without_history(c: &mut Criterion) {
+    let mut state = State8080::default();
+    state.history.set_capacity(0);
+
+    c.bench_function("emulate_8080_op without history", |b| {
+        b.iter(|| emulate_8080_op(black_box(&mut state)))
+    });
+}
+
 fn benchmark_16_666_cycles(c: &mut Criterion) {
-    c.bench_function("emulate_8080_op", |b| {
+    c.bench_function("16_666 cycles", |b| {
         b.iter(|| {
             let mut state = State8080::default();
             let mut cycles: u32 = 0;
@@ -25,7 +34,17 @@ fn benchmark_16_666_cycles(c: &mut Criterion) {
 
 fn emulator_benchmark(c: &mut Criterion) {
     let mut machine = SpaceInvadersMachine::new();
-    c.bench_function("emulate_8080_op", |b| {
+    c.bench_function("space invaders frame", |b| {
+        b.iter(|| {
+            machine.do_cpu();
+        })
+    });
+}
+
+fn recompiler_benchmark(c: &mut Criterion) {
+    let mut machine = SpaceInvadersMachine::new();
+    machine.set_engine(Engine::Recompiler);
+    c.bench_function("space invaders frame (recompiler)", |b| {
         b.iter(|| {
             machine.do_cpu();
         })
@@ -35,7 +54,9 @@ fn emulator_benchmark(c: &mut Criterion) {
 criterion_group!(
     benches,
     benchmark_emulate_8080_op,
+    benchmark_emulate_8080_op_without_history,
     benchmark_16_666_cycles,
     emulator_benchmark,
+    recompiler_benchmark,
 );
 criterion_main!(benches);
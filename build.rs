@@ -9,7 +9,8 @@ fn main() {
     env::set_var("PATH", new_path);
     let target = std::env::var("TARGET").unwrap();
 
-    if !target.contains("wasm32") {
+    // Only the native window needs SDL2
+    if !target.contains("wasm32") && env::var_os("CARGO_FEATURE_SDL").is_some() {
         println!("cargo:rustc-link-lib=dylib=SDL2");
 
         println!("cargo:rustc-link-search=native=./libs");
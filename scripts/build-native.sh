@@ -10,6 +10,6 @@ set -euo pipefail
 ROOT="$(cd "$(dirname "$0")/.." && pwd)"
 
 echo "Building native emulator..."
-cargo build --release --bin i8080_emulator
+cargo build --release --features sdl --bin i8080_emulator
 
 echo "Done. Binary: $ROOT/target/release/i8080_emulator"
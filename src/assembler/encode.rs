@@ -0,0 +1,207 @@
+//! Intel 8080 instruction encoding, the inverse of `disassembler::decode`.
+
+use crate::disassembler::instruction::{ALU, ALU_IMMEDIATE, CALLS, JUMPS, RETURNS, ROTATES};
+
+const REGISTERS: [&str; 8] = ["B", "C", "D", "E", "H", "L", "M", "A"];
+const PAIRS: [&str; 4] = ["B", "D", "H", "SP"];
+const STACK_PAIRS: [&str; 4] = ["B", "D", "H", "PSW"];
+
+pub type Bytes = heapless::Vec<u8, 3>;
+
+/// Length in bytes of an instruction, or `None` if `mnemonic` is not an 8080 instruction.
+pub fn length(mnemonic: &str) -> Option<u16> {
+    let length = match mnemonic {
+        "LXI" | "LDA" | "STA" | "LHLD" | "SHLD" | "JMP" | "CALL" => 3,
+        m if JUMPS.contains(&m) || CALLS.contains(&m) => 3,
+        "MVI" | "IN" | "OUT" => 2,
+        m if ALU_IMMEDIATE.contains(&m) => 2,
+        "NOP" | "HLT" | "RET" | "XCHG" | "XTHL" | "SPHL" | "PCHL" | "DI" | "EI" => 1,
+        "MOV" | "INR" | "DCR" | "INX" | "DCX" | "DAD" | "LDAX" | "STAX" | "PUSH" | "POP"
+        | "RST" => 1,
+        m if ALU.contains(&m) || ROTATES.contains(&m) || RETURNS.contains(&m) => 1,
+        _ => return None,
+    };
+    Some(length)
+}
+
+/// Encodes one instruction. `mnemonic` must be upper case; `value` evaluates
+/// an operand expression.
+pub fn encode<F>(mnemonic: &str, operands: &[&str], value: F) -> Result<Bytes, String>
+where
+    F: Fn(&str) -> Result<i32, String>,
+{
+    let expect = |count: usize| {
+        if operands.len() == count {
+            Ok(())
+        } else {
+            Err(format!(
+                "{} takes {} operand{}",
+                mnemonic,
+                count,
+                if count == 1 { "" } else { "s" }
+            ))
+        }
+    };
+    let byte = |text: &str| -> Result<u8, String> {
+        let v = value(text)?;
+        if (-128..=255).contains(&v) {
+            Ok(v as u8)
+        } else {
+            Err(format!("Value {} does not fit in a byte", v))
+        }
+    };
+    let word = |text: &str| -> Result<[u8; 2], String> {
+        let v = value(text)?;
+        if (-32768..=65535).contains(&v) {
+            Ok((v as u16).to_le_bytes())
+        } else {
+            Err(format!("Value {} does not fit in a word", v))
+        }
+    };
+    let op = |index: usize| operands[index];
+
+    let with_word = |opcode: u8, text: &str| -> Result<Bytes, String> {
+        let [lo, hi] = word(text)?;
+        Ok(bytes(&[opcode, lo, hi]))
+    };
+
+    if let Some(i) = position(&ROTATES, mnemonic) {
+        expect(0)?;
+        return Ok(bytes(&[0x07 | (i << 3)]));
+    }
+    if let Some(i) = position(&RETURNS, mnemonic) {
+        expect(0)?;
+        return Ok(bytes(&[0xC0 | (i << 3)]));
+    }
+    if let Some(i) = position(&JUMPS, mnemonic) {
+        expect(1)?;
+        return with_word(0xC2 | (i << 3), op(0));
+    }
+    if let Some(i) = position(&CALLS, mnemonic) {
+        expect(1)?;
+        return with_word(0xC4 | (i << 3), op(0));
+    }
+    if let Some(i) = position(&ALU, mnemonic) {
+        expect(1)?;
+        return Ok(bytes(&[0x80 | (i << 3) | register(op(0))?]));
+    }
+    if let Some(i) = position(&ALU_IMMEDIATE, mnemonic) {
+        expect(1)?;
+        return Ok(bytes(&[0xC6 | (i << 3), byte(op(0))?]));
+    }
+
+    let encoded = match mnemonic {
+        "NOP" | "HLT" | "RET" | "XCHG" | "XTHL" | "SPHL" | "PCHL" | "DI" | "EI" => {
+            expect(0)?;
+            let opcode = match mnemonic {
+                "NOP" => 0x00,
+                "HLT" => 0x76,
+                "RET" => 0xC9,
+                "XCHG" => 0xEB,
+                "XTHL" => 0xE3,
+                "SPHL" => 0xF9,
+                "PCHL" => 0xE9,
+                "DI" => 0xF3,
+                _ => 0xFB,
+            };
+            bytes(&[opcode])
+        }
+        "MOV" => {
+            expect(2)?;
+            let (dst, src) = (register(op(0))?, register(op(1))?);
+            if dst == 6 && src == 6 {
+                return Err("MOV M,M is not an instruction (it encodes HLT)".into());
+            }
+            bytes(&[0x40 | (dst << 3) | src])
+        }
+        "MVI" => {
+            expect(2)?;
+            bytes(&[0x06 | (register(op(0))? << 3), byte(op(1))?])
+        }
+        "INR" | "DCR" => {
+            expect(1)?;
+            let base = if mnemonic == "INR" { 0x04 } else { 0x05 };
+            bytes(&[base | (register(op(0))? << 3)])
+        }
+        "LXI" => {
+            expect(2)?;
+            with_word(0x01 | (pair(op(0), &PAIRS)? << 4), op(1))?
+        }
+        "INX" | "DCX" | "DAD" => {
+            expect(1)?;
+            let base = match mnemonic {
+                "INX" => 0x03,
+                "DCX" => 0x0B,
+                _ => 0x09,
+            };
+            bytes(&[base | (pair(op(0), &PAIRS)? << 4)])
+        }
+        "LDAX" | "STAX" => {
+            expect(1)?;
+            let index = pair(op(0), &PAIRS)?;
+            if index > 1 {
+                return Err(format!("{} takes B or D", mnemonic));
+            }
+            let base = if mnemonic == "LDAX" { 0x0A } else { 0x02 };
+            bytes(&[base | (index << 4)])
+        }
+        "PUSH" | "POP" => {
+            expect(1)?;
+            let base = if mnemonic == "PUSH" { 0xC5 } else { 0xC1 };
+            bytes(&[base | (pair(op(0), &STACK_PAIRS)? << 4)])
+        }
+        "LDA" | "STA" | "LHLD" | "SHLD" | "JMP" | "CALL" => {
+            expect(1)?;
+            let opcode = match mnemonic {
+                "LDA" => 0x3A,
+                "STA" => 0x32,
+                "LHLD" => 0x2A,
+                "SHLD" => 0x22,
+                "JMP" => 0xC3,
+                _ => 0xCD,
+            };
+            with_word(opcode, op(0))?
+        }
+        "IN" | "OUT" => {
+            expect(1)?;
+            let opcode = if mnemonic == "IN" { 0xDB } else { 0xD3 };
+            bytes(&[opcode, byte(op(0))?])
+        }
+        "RST" => {
+            expect(1)?;
+            let n = value(op(0))?;
+            if !(0..8).contains(&n) {
+                return Err(format!("RST {} is out of range 0-7", n));
+            }
+            bytes(&[0xC7 | ((n as u8) << 3)])
+        }
+        _ => return Err(format!("Unknown instruction '{}'", mnemonic)),
+    };
+    Ok(encoded)
+}
+
+fn bytes(values: &[u8]) -> Bytes {
+    values.iter().copied().collect()
+}
+
+fn position(table: &[&str; 8], mnemonic: &str) -> Option<u8> {
+    table.iter().position(|&m| m == mnemonic).map(|i| i as u8)
+}
+
+fn register(text: &str) -> Result<u8, String> {
+    let upper = text.trim().to_ascii_uppercase();
+    REGISTERS
+        .iter()
+        .position(|&r| r == upper)
+        .map(|i| i as u8)
+        .ok_or_else(|| format!("Expected a register, found '{}'", text))
+}
+
+fn pair(text: &str, names: &[&str; 4]) -> Result<u8, String> {
+    let upper = text.trim().to_ascii_uppercase();
+    names
+        .iter()
+        .position(|&p| p == upper)
+        .map(|i| i as u8)
+        .ok_or_else(|| format!("Expected {}, found '{}'", names.join("/"), text))
+}
@@ -0,0 +1,258 @@
+//! Operand expressions: numbers in the usual Intel spellings, symbols, `$`
+//! for the current address, character constants and the arithmetic, logical
+//! and `HIGH`/`LOW` operators.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExprError {
+    /// A symbol that has not been defined (yet).
+    Undefined(String),
+    Invalid(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Number(i32),
+    Symbol(String),
+    Here,
+    Op(&'static str),
+}
+
+const SYMBOLIC_OPERATORS: [&str; 13] = [
+    "<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "(", ")",
+];
+const WORD_OPERATORS: [&str; 9] = [
+    "MOD", "AND", "OR", "XOR", "NOT", "SHL", "SHR", "HIGH", "LOW",
+];
+
+/// Evaluates `text` with `$` standing for `here`, looking symbols up with `lookup`.
+pub fn evaluate<F: Fn(&str) -> Option<i32>>(
+    text: &str,
+    here: u16,
+    lookup: F,
+) -> Result<i32, ExprError> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        here,
+        lookup: &lookup,
+    };
+    let value = parser.or()?;
+    match parser.tokens.get(parser.pos) {
+        None => Ok(value),
+        Some(_) => Err(ExprError::Invalid(format!("Unexpected text in '{}'", text))),
+    }
+}
+
+pub fn is_symbol_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '_' | '?' | '@' | '.')
+}
+
+pub fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '?' | '@' | '.' | '$')
+}
+
+/// Parses a numeric literal: `42`, `42D`, `2AH`, `0x2A`, `$2A`, `101010B`, `52O`/`52Q`.
+pub fn parse_number(text: &str) -> Option<i32> {
+    let upper = text.to_ascii_uppercase();
+    let (digits, radix) = if let Some(hex) = upper.strip_prefix("0X") {
+        (hex, 16)
+    } else if let Some(hex) = upper.strip_prefix('$') {
+        (hex, 16)
+    } else if let Some(hex) = upper.strip_suffix('H') {
+        (hex, 16)
+    } else if let Some(binary) = upper.strip_suffix('B') {
+        (binary, 2)
+    } else if let Some(octal) = upper.strip_suffix(['O', 'Q']) {
+        (octal, 8)
+    } else if let Some(decimal) = upper.strip_suffix('D') {
+        (decimal, 10)
+    } else {
+        (upper.as_str(), 10)
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    i64::from_str_radix(digits, radix)
+        .ok()
+        .filter(|&value| value <= u16::MAX as i64)
+        .map(|value| value as i32)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, ExprError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '$' && chars.get(i + 1).is_some_and(char::is_ascii_hexdigit))
+        {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let literal: String = chars[start..i].iter().collect();
+            let value = parse_number(&literal)
+                .ok_or_else(|| ExprError::Invalid(format!("Bad number '{}'", literal)))?;
+            tokens.push(Token::Number(value));
+        } else if c == '$' {
+            tokens.push(Token::Here);
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            let (value, end) = char_constant(&chars, i)?;
+            tokens.push(Token::Number(value));
+            i = end;
+        } else if is_symbol_start(c) {
+            let start = i;
+            while i < chars.len() && is_symbol_char(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let upper = word.to_ascii_uppercase();
+            match WORD_OPERATORS.iter().find(|&&op| op == upper) {
+                Some(op) => tokens.push(Token::Op(op)),
+                None => tokens.push(Token::Symbol(word)),
+            }
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = SYMBOLIC_OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(*op))
+                .ok_or_else(|| ExprError::Invalid(format!("Unexpected '{}'", c)))?;
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+/// One- or two-character constant such as `'A'`; a doubled quote stands for itself.
+fn char_constant(chars: &[char], start: usize) -> Result<(i32, usize), ExprError> {
+    let quote = chars[start];
+    let mut value = 0i32;
+    let mut count = 0;
+    let mut i = start + 1;
+    loop {
+        match chars.get(i) {
+            None => return Err(ExprError::Invalid("Unterminated character constant".into())),
+            Some(&c) if c == quote && chars.get(i + 1) != Some(&quote) => break,
+            Some(&c) => {
+                value = (value << 8) | (c as u32 as i32 & 0xFF);
+                count += 1;
+                i += if c == quote { 2 } else { 1 };
+            }
+        }
+    }
+    if count == 0 || count > 2 {
+        return Err(ExprError::Invalid(
+            "Character constants hold one or two characters".into(),
+        ));
+    }
+    Ok((value, i + 1))
+}
+
+struct Parser<'a, F> {
+    tokens: &'a [Token],
+    pos: usize,
+    here: u16,
+    lookup: &'a F,
+}
+
+impl<F: Fn(&str) -> Option<i32>> Parser<'_, F> {
+    fn accept(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn or(&mut self) -> Result<i32, ExprError> {
+        let mut value = self.and()?;
+        while let Some(op) = self.accept(&["OR", "|", "XOR", "^"]) {
+            let rhs = self.and()?;
+            value = if matches!(op, "OR" | "|") {
+                value | rhs
+            } else {
+                value ^ rhs
+            };
+        }
+        Ok(value)
+    }
+
+    fn and(&mut self) -> Result<i32, ExprError> {
+        let mut value = self.sum()?;
+        while self.accept(&["AND", "&"]).is_some() {
+            value &= self.sum()?;
+        }
+        Ok(value)
+    }
+
+    fn sum(&mut self) -> Result<i32, ExprError> {
+        let mut value = self.product()?;
+        while let Some(op) = self.accept(&["+", "-"]) {
+            let rhs = self.product()?;
+            value = if op == "+" {
+                value.wrapping_add(rhs)
+            } else {
+                value.wrapping_sub(rhs)
+            };
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<i32, ExprError> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.accept(&["*", "/", "%", "MOD", "<<", "SHL", ">>", "SHR"]) {
+            let rhs = self.unary()?;
+            value = match op {
+                "*" => value.wrapping_mul(rhs),
+                "<<" | "SHL" => value.wrapping_shl(rhs as u32),
+                ">>" | "SHR" => ((value as u32 & 0xFFFF) >> (rhs as u32).min(31)) as i32,
+                _ if rhs == 0 => return Err(ExprError::Invalid("Division by zero".into())),
+                "/" => value.wrapping_div(rhs),
+                _ => value.wrapping_rem(rhs),
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i32, ExprError> {
+        match self.accept(&["-", "+", "NOT", "~", "HIGH", "LOW"]) {
+            Some("-") => Ok(self.unary()?.wrapping_neg()),
+            Some("+") => self.unary(),
+            Some("HIGH") => Ok((self.unary()? >> 8) & 0xFF),
+            Some("LOW") => Ok(self.unary()? & 0xFF),
+            Some(_) => Ok(!self.unary()?),
+            None => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<i32, ExprError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .ok_or_else(|| ExprError::Invalid("Missing operand".into()))?;
+        self.pos += 1;
+        match token {
+            Token::Number(value) => Ok(*value),
+            Token::Here => Ok(self.here as i32),
+            Token::Symbol(name) => {
+                (self.lookup)(name).ok_or_else(|| ExprError::Undefined(name.clone()))
+            }
+            Token::Op("(") => {
+                let value = self.or()?;
+                self.accept(&[")"])
+                    .ok_or_else(|| ExprError::Invalid("Missing ')'".into()))?;
+                Ok(value)
+            }
+            Token::Op(op) => Err(ExprError::Invalid(format!("Unexpected '{}'", op))),
+        }
+    }
+}
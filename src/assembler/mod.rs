@@ -0,0 +1,606 @@
+//! Two-pass Intel 8080 assembler.
+//!
+//! Accepts the syntax `disassembler::RecursiveDisassembler::listing` writes:
+//! `label:` definitions (the colon is optional in column one), Intel
+//! mnemonics, expressions, and the `ORG`, `DB`, `DW`, `DS`, `EQU`, `SET` and
+//! `END` directives. A `SET` symbol may be set again further down; each use
+//! takes the value of the last `SET` before it.
+
+pub mod encode;
+pub mod expression;
+
+use crate::symbols::SymbolTable;
+use expression::{evaluate, is_symbol_char, is_symbol_start, ExprError};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::fmt;
+use std::fmt::Write;
+
+const LISTING_BYTES_PER_LINE: usize = 4;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssemblyError {
+    /// 1-based source line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+/// A contiguous run of assembled bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub origin: u16,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+struct ListingLine {
+    address: Option<u16>,
+    /// Value shown for `EQU` and `SET` lines instead of bytes.
+    value: Option<u16>,
+    bytes: Vec<u8>,
+    source: String,
+}
+
+/// The output of a successful assembly.
+#[derive(Clone, Debug)]
+pub struct Assembly {
+    segments: Vec<Segment>,
+    /// Every label and `EQU`, ready for the disassembler or debugger.
+    pub symbols: SymbolTable,
+    /// Operand of `END`, if given.
+    pub entry: Option<u16>,
+    listing: Vec<ListingLine>,
+}
+
+impl Assembly {
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Lowest address written to.
+    pub fn origin(&self) -> u16 {
+        self.segments.iter().map(|s| s.origin).min().unwrap_or(0)
+    }
+
+    /// All segments flattened into one image starting at `origin()`, with
+    /// gaps (from `DS` or `ORG`) zero-filled.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let origin = self.origin() as usize;
+        let end = self
+            .segments
+            .iter()
+            .map(|s| s.origin as usize + s.bytes.len())
+            .max()
+            .unwrap_or(origin);
+        let mut image = vec![0; end - origin];
+        for segment in &self.segments {
+            let start = segment.origin as usize - origin;
+            image[start..start + segment.bytes.len()].copy_from_slice(&segment.bytes);
+        }
+        image
+    }
+
+    /// Copies each segment to its address in `memory`, leaving gaps untouched.
+    /// Segments never run past 0xFFFF, so a 64K `memory` always fits them.
+    pub fn load_into(&self, memory: &mut [u8]) {
+        for segment in &self.segments {
+            let start = segment.origin as usize;
+            memory[start..start + segment.bytes.len()].copy_from_slice(&segment.bytes);
+        }
+    }
+
+    /// Address, bytes and source for every line, followed by the symbol table.
+    pub fn listing(&self) -> String {
+        let mut output = String::new();
+        for line in &self.listing {
+            let address = match (line.value, line.address) {
+                (Some(value), _) => format!("{:04X}  =", value),
+                (None, Some(address)) => format!("{:04X}", address),
+                (None, None) => String::new(),
+            };
+            let mut chunks = line.bytes.chunks(LISTING_BYTES_PER_LINE);
+            let first: Vec<String> = chunks
+                .next()
+                .unwrap_or_default()
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect();
+            let text = format!("{:<7} {:<12}{}", address, first.join(" "), line.source);
+            let _ = writeln!(output, "{}", text.trim_end());
+
+            // Long DB/DW lines continue below without repeating the source
+            let mut address = line.address.unwrap_or(0);
+            for chunk in chunks {
+                address = address.wrapping_add(LISTING_BYTES_PER_LINE as u16);
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+                let _ = writeln!(output, "{:04X}    {}", address, hex.join(" "));
+            }
+        }
+
+        if !self.symbols.is_empty() {
+            let _ = writeln!(output, "\nSymbols:");
+            for (addr, name) in self.symbols.iter() {
+                let _ = writeln!(output, "    {:04X}  {}", addr, name);
+            }
+        }
+        output
+    }
+}
+
+/// Assembles `source` with no predefined symbols.
+pub fn assemble(source: &str) -> Result<Assembly, AssemblyError> {
+    Assembler::new().assemble(source)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Assembler {
+    predefined: Vec<(String, u16)>,
+}
+
+/// One parsed source line.
+struct Statement<'a> {
+    line: usize,
+    source: &'a str,
+    label: Option<&'a str>,
+    /// Upper-cased mnemonic or directive.
+    operation: Option<String>,
+    operands: Vec<&'a str>,
+}
+
+#[derive(Default)]
+struct Symbols {
+    values: FxHashMap<String, i32>,
+    /// Names in definition order, with their original spelling.
+    order: Vec<String>,
+    /// Lowercased names defined with `SET`, which may be set again.
+    variables: FxHashSet<String>,
+}
+
+impl Symbols {
+    fn get(&self, name: &str) -> Option<i32> {
+        self.values.get(&name.to_ascii_lowercase()).copied()
+    }
+
+    fn define(&mut self, name: &str, value: i32) -> Result<(), String> {
+        let key = name.to_ascii_lowercase();
+        if self.values.insert(key, value).is_some() {
+            return Err(format!("'{}' is already defined", name));
+        }
+        self.order.push(name.to_string());
+        Ok(())
+    }
+
+    fn set(&mut self, name: &str, value: i32) -> Result<(), String> {
+        let key = name.to_ascii_lowercase();
+        if self.variables.contains(&key) {
+            self.values.insert(key, value);
+            return Ok(());
+        }
+        self.define(name, value)?;
+        self.variables.insert(key);
+        Ok(())
+    }
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `name` available to the program, e.g. a ROM routine being patched.
+    pub fn define(&mut self, name: &str, value: u16) -> &mut Self {
+        self.predefined.push((name.to_string(), value));
+        self
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Assembly, AssemblyError> {
+        let mut statements = Vec::new();
+        for (i, source) in source.lines().enumerate() {
+            let (label, operation, operands) =
+                parse_statement(source).map_err(|message| AssemblyError {
+                    line: i + 1,
+                    message,
+                })?;
+            statements.push(Statement {
+                line: i + 1,
+                source,
+                label,
+                operation,
+                operands,
+            });
+        }
+
+        let mut symbols = Symbols::default();
+        for (name, value) in &self.predefined {
+            symbols
+                .define(name, *value as i32)
+                .map_err(|message| AssemblyError { line: 0, message })?;
+        }
+        let predefined = symbols.order.len();
+
+        self.first_pass(&statements, &mut symbols)?;
+        let mut assembly = self.second_pass(&statements, &mut symbols)?;
+        for name in &symbols.order[predefined..] {
+            if let Some(value) = symbols.get(name) {
+                assembly.symbols.insert(value as u16, name);
+            }
+        }
+        Ok(assembly)
+    }
+
+    /// Assigns an address to every label and a value to every `EQU`. `SET`
+    /// symbols end up with their last value.
+    fn first_pass(
+        &self,
+        statements: &[Statement],
+        symbols: &mut Symbols,
+    ) -> Result<(), AssemblyError> {
+        let mut pc: u16 = 0;
+        // EQUs that refer to symbols defined further down
+        let mut pending: Vec<(&Statement, u16)> = Vec::new();
+
+        for statement in statements {
+            let error = |message: String| AssemblyError {
+                line: statement.line,
+                message,
+            };
+            let operation = statement.operation.as_deref();
+
+            if operation == Some("EQU") {
+                let name = statement
+                    .label
+                    .ok_or_else(|| error("EQU needs a name".into()))?;
+                let expr = single_operand(statement).map_err(error)?;
+                match evaluate(expr, pc, |s| symbols.get(s)) {
+                    Ok(value) => symbols.define(name, value).map_err(error)?,
+                    Err(ExprError::Undefined(_)) => pending.push((statement, pc)),
+                    Err(ExprError::Invalid(message)) => return Err(error(message)),
+                }
+                continue;
+            }
+            if operation == Some("SET") {
+                let name = statement
+                    .label
+                    .ok_or_else(|| error("SET needs a name".into()))?;
+                let value = known_value(statement, pc, symbols)?;
+                symbols.set(name, value).map_err(error)?;
+                continue;
+            }
+            if operation == Some("ORG") {
+                pc = known_value(statement, pc, symbols)? as u16;
+            }
+            if let Some(label) = statement.label {
+                symbols.define(label, pc as i32).map_err(error)?;
+            }
+
+            let size = match operation {
+                None | Some("ORG") => 0,
+                Some("END") => break,
+                Some("DB") => statement
+                    .operands
+                    .iter()
+                    .map(|item| string_literal(item).map_or(1, |s| s.len() as u16))
+                    .sum(),
+                Some("DW") => 2 * statement.operands.len() as u16,
+                Some("DS") => known_value(statement, pc, symbols)? as u16,
+                Some(mnemonic) => encode::length(mnemonic)
+                    .ok_or_else(|| error(format!("Unknown instruction '{}'", mnemonic)))?,
+            };
+            pc = pc.wrapping_add(size);
+        }
+
+        // Resolve forward-referencing EQUs until nothing changes
+        while !pending.is_empty() {
+            let before = pending.len();
+            let mut unresolved = Vec::new();
+            for (statement, here) in pending {
+                match evaluate(statement.operands[0], here, |s| symbols.get(s)) {
+                    Ok(value) => {
+                        symbols
+                            .define(statement.label.unwrap_or_default(), value)
+                            .map_err(|message| AssemblyError {
+                                line: statement.line,
+                                message,
+                            })?;
+                    }
+                    Err(_) => unresolved.push((statement, here)),
+                }
+            }
+            if unresolved.len() == before {
+                let (statement, _) = unresolved[0];
+                return Err(undefined(statement, statement.operands[0], symbols));
+            }
+            pending = unresolved;
+        }
+        Ok(())
+    }
+
+    /// Emits the bytes, setting `SET` symbols again as they come, so
+    /// references before the first `SET` of a name see its last value.
+    fn second_pass(
+        &self,
+        statements: &[Statement],
+        symbols: &mut Symbols,
+    ) -> Result<Assembly, AssemblyError> {
+        let mut assembly = Assembly {
+            segments: Vec::new(),
+            symbols: SymbolTable::new(),
+            entry: None,
+            listing: Vec::new(),
+        };
+        let mut pc: u16 = 0;
+        // Set when the next byte must start a new segment
+        let mut new_segment = true;
+
+        for statement in statements {
+            let error = |message: String| AssemblyError {
+                line: statement.line,
+                message,
+            };
+            if statement.operation.as_deref() == Some("SET") {
+                let value = known_value(statement, pc, symbols)?;
+                symbols
+                    .set(statement.label.unwrap_or_default(), value)
+                    .map_err(error)?;
+                assembly.listing.push(ListingLine {
+                    address: None,
+                    value: Some(value as u16),
+                    bytes: Vec::new(),
+                    source: statement.source.to_string(),
+                });
+                continue;
+            }
+            let symbols = &*symbols;
+            let here = pc;
+            let value = |text: &str| -> Result<i32, String> {
+                evaluate(text, here, |s| symbols.get(s)).map_err(|e| match e {
+                    ExprError::Undefined(name) => format!("Undefined symbol '{}'", name),
+                    ExprError::Invalid(message) => message,
+                })
+            };
+
+            let mut line = ListingLine {
+                address: None,
+                value: None,
+                bytes: Vec::new(),
+                source: statement.source.to_string(),
+            };
+            let mut bytes: Vec<u8> = Vec::new();
+            let mut end = false;
+
+            match statement.operation.as_deref() {
+                None => line.address = statement.label.map(|_| pc),
+                Some("EQU") => {
+                    let name = statement.label.unwrap_or_default();
+                    line.value = symbols.get(name).map(|v| v as u16);
+                }
+                Some("ORG") => {
+                    pc = value(statement.operands[0]).map_err(error)? as u16;
+                    new_segment = true;
+                    line.address = Some(pc);
+                }
+                Some("END") => {
+                    if let Some(entry) = statement.operands.first() {
+                        assembly.entry = Some(value(entry).map_err(error)? as u16);
+                    }
+                    end = true;
+                }
+                Some("DS") => {
+                    line.address = Some(pc);
+                    pc = pc.wrapping_add(value(statement.operands[0]).map_err(error)? as u16);
+                    new_segment = true;
+                }
+                Some("DB") => {
+                    line.address = Some(pc);
+                    for item in &statement.operands {
+                        match string_literal(item) {
+                            Some(text) => bytes.extend(text),
+                            None => {
+                                let v = value(item).map_err(error)?;
+                                if !(-128..=255).contains(&v) {
+                                    return Err(error(format!(
+                                        "Value {} does not fit in a byte",
+                                        v
+                                    )));
+                                }
+                                bytes.push(v as u8);
+                            }
+                        }
+                    }
+                }
+                Some("DW") => {
+                    line.address = Some(pc);
+                    for item in &statement.operands {
+                        let v = value(item).map_err(error)?;
+                        if !(-32768..=65535).contains(&v) {
+                            return Err(error(format!("Value {} does not fit in a word", v)));
+                        }
+                        bytes.extend((v as u16).to_le_bytes());
+                    }
+                }
+                Some(mnemonic) => {
+                    line.address = Some(pc);
+                    let encoded =
+                        encode::encode(mnemonic, &statement.operands, value).map_err(error)?;
+                    bytes.extend(encoded);
+                }
+            }
+
+            if !bytes.is_empty() {
+                if new_segment {
+                    assembly.segments.push(Segment {
+                        origin: pc,
+                        bytes: Vec::new(),
+                    });
+                    new_segment = false;
+                }
+                if let Some(segment) = assembly.segments.last_mut() {
+                    segment.bytes.extend(&bytes);
+                    if segment.origin as usize + segment.bytes.len() > 0x10000 {
+                        return Err(error("Code runs past 0FFFFH".into()));
+                    }
+                }
+                pc = pc.wrapping_add(bytes.len() as u16);
+                line.bytes = bytes;
+            }
+            assembly.listing.push(line);
+            if end {
+                break;
+            }
+        }
+        Ok(assembly)
+    }
+}
+
+fn single_operand<'a>(statement: &Statement<'a>) -> Result<&'a str, String> {
+    match statement.operands.as_slice() {
+        [operand] => Ok(operand),
+        _ => Err(format!(
+            "{} takes one operand",
+            statement.operation.as_deref().unwrap_or_default()
+        )),
+    }
+}
+
+/// Evaluates an `ORG`/`DS` operand, which must not depend on later labels.
+fn known_value(statement: &Statement, pc: u16, symbols: &Symbols) -> Result<i32, AssemblyError> {
+    let expr = single_operand(statement).map_err(|message| AssemblyError {
+        line: statement.line,
+        message,
+    })?;
+    evaluate(expr, pc, |s| symbols.get(s)).map_err(|e| match e {
+        ExprError::Undefined(_) => undefined(statement, expr, symbols),
+        ExprError::Invalid(message) => AssemblyError {
+            line: statement.line,
+            message,
+        },
+    })
+}
+
+fn undefined(statement: &Statement, expr: &str, symbols: &Symbols) -> AssemblyError {
+    let name = match evaluate(expr, 0, |s| symbols.get(s)) {
+        Err(ExprError::Undefined(name)) => name,
+        _ => expr.to_string(),
+    };
+    AssemblyError {
+        line: statement.line,
+        message: format!("'{}' must be defined before it is used here", name),
+    }
+}
+
+/// The bytes of a quoted string longer than one character, which `DB` emits
+/// as-is. Single characters are left to the expression evaluator.
+fn string_literal(item: &str) -> Option<Vec<u8>> {
+    let quote = item.chars().next().filter(|&c| c == '\'' || c == '"')?;
+    let inner = item.strip_prefix(quote)?.strip_suffix(quote)?;
+    let doubled = format!("{}{}", quote, quote);
+    let text = inner.replace(&doubled, &quote.to_string());
+    (text.len() != 1).then(|| text.into_bytes())
+}
+
+type ParsedLine<'a> = (Option<&'a str>, Option<String>, Vec<&'a str>);
+
+fn parse_statement(text: &str) -> Result<ParsedLine<'_>, String> {
+    let code = strip_comment(text);
+    let trimmed = code.trim_start();
+    if trimmed.trim().is_empty() {
+        return Ok((None, None, Vec::new()));
+    }
+    let in_first_column = trimmed.len() == code.len();
+
+    let (first, rest) = split_word(trimmed);
+    let (label, rest) = if let Some(name) = first.strip_suffix(':') {
+        (Some(name), rest)
+    } else {
+        let (second, _) = split_word(rest);
+        let second = second.to_ascii_uppercase();
+        let is_definition = matches!(second.as_str(), "EQU" | "=" | "SET");
+        if is_definition || (in_first_column && !is_keyword(first)) {
+            (Some(first), rest)
+        } else {
+            (None, trimmed)
+        }
+    };
+    if let Some(name) = label {
+        let mut chars = name.chars();
+        let valid = chars.next().is_some_and(is_symbol_start) && chars.all(is_symbol_char);
+        if !valid {
+            return Err(format!("Invalid label '{}'", name));
+        }
+    }
+
+    let (operation, operands) = split_word(rest.trim_start());
+    if operation.is_empty() {
+        return Ok((label, None, Vec::new()));
+    }
+    let operation = match operation.to_ascii_uppercase().as_str() {
+        "=" => "EQU".to_string(),
+        other => other.to_string(),
+    };
+    Ok((label, Some(operation), split_operands(operands)))
+}
+
+fn is_keyword(word: &str) -> bool {
+    let upper = word.to_ascii_uppercase();
+    encode::length(&upper).is_some()
+        || matches!(
+            upper.as_str(),
+            "ORG" | "DB" | "DW" | "DS" | "EQU" | "SET" | "END"
+        )
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    match text.find(char::is_whitespace) {
+        Some(end) => (&text[..end], &text[end..]),
+        None => (text, ""),
+    }
+}
+
+/// Splits on commas outside quotes and parentheses.
+fn split_operands(text: &str) -> Vec<&str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let mut operands = Vec::new();
+    let mut quote = None;
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                operands.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    operands.push(text[start..].trim());
+    operands
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, ';') => return &text[..i],
+            _ => {}
+        }
+    }
+    text
+}
@@ -1,112 +1,486 @@
-extern crate sdl2;
-use intel_8080_emu_rust::emulator::machine::SpaceInvadersMachine;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use sdl2::{event::Event, pixels::Color, rect::Rect};
-use std::time::Duration;
-use std::time::Instant;
-
-const SCALE_FACTOR: u32 = 2;
-const SCREEN_WIDTH: u32 = 224 * SCALE_FACTOR;
-const SCREEN_HEIGHT: u32 = 256 * SCALE_FACTOR;
-const VIDEO_MEM_START: usize = 0x2400;
-const VIDEO_MEM_END: usize = 0x4000;
+use intel_8080_emu_rust::debugger::gdb_stub::GdbStub;
+use intel_8080_emu_rust::disassembler::RecursiveDisassembler;
+use intel_8080_emu_rust::emulator::altair::{self, Altair};
+use intel_8080_emu_rust::emulator::console::{Console, StdConsole};
+use intel_8080_emu_rust::emulator::cpm::{Cpm, Exit};
+use intel_8080_emu_rust::emulator::dcdd;
+use intel_8080_emu_rust::emulator::disk::{self, DiskFormat, DiskImage, DiskSystem};
+use intel_8080_emu_rust::emulator::hiscore;
+use intel_8080_emu_rust::emulator::machine::{Engine, SpaceInvadersMachine, ROM_PATH};
+use intel_8080_emu_rust::emulator::midway;
+use intel_8080_emu_rust::emulator::romset;
+use intel_8080_emu_rust::emulator::trace::Tracer;
+use intel_8080_emu_rust::symbols::SymbolTable;
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+const DEFAULT_GDB_PORT: u16 = 1234;
 
 // TODO: Run with cargo clippy -- -W clippy::pedantic
 
 fn main() {
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-    let mut invaders = SpaceInvadersMachine::new();
-
-    let window = video_subsystem
-        .window(
-            "Intel 8080 Emulator",
-            (SCREEN_WIDTH) as u32,
-            (SCREEN_HEIGHT) as u32,
-        )
-        .position_centered()
-        .build()
-        .unwrap();
-    let mut last_time = Instant::now();
-    let mut frame_count = 0;
-    let mut canvas = window.into_canvas().build().unwrap();
-    let mut event_pump = sdl_context.event_pump().unwrap();
-    let target_fps = 120;
-    let frame_duration = Duration::from_millis(1000 / target_fps);
-
-    'running: loop {
-        // this starts emulation per 33000 cycles
-        let frame_start = Instant::now();
-        invaders.start_emulation();
-        draw_screen(&mut canvas, &invaders.get_memory());
-        frame_count += 1;
-        if last_time.elapsed() >= Duration::new(1, 0) {
-            println!("FPS: {}", frame_count);
-            frame_count = 0;
-            last_time = Instant::now();
+    let args: Vec<String> = std::env::args().collect();
+    // Everything after the program is its command tail
+    if let Some(pos) = args.iter().position(|arg| arg == "--cpm") {
+        let program = args.get(pos + 1).expect("--cpm needs a .COM file");
+        run_cpm(program, &args[pos + 2..].join(" "));
+        return;
+    }
+    // Up to four images for drives A: to D:, each read-only with a :ro suffix
+    if let Some(pos) = args.iter().position(|arg| arg == "--boot") {
+        boot_cpm(&args[pos + 1..]);
+        return;
+    }
+    if let Some(pos) = args.iter().position(|arg| arg == "--altair") {
+        let program = args.get(pos + 1).filter(|arg| !arg.starts_with("--"));
+        run_altair(program.map(String::as_str), &args);
+        return;
+    }
+    let symbols = match args.iter().position(|arg| arg == "--symbols") {
+        Some(pos) => {
+            let path = args.get(pos + 1).expect("--symbols needs a file");
+            SymbolTable::load(path)
+                .unwrap_or_else(|error| panic!("Could not read symbols {}: {}", path, error))
         }
+        None => SymbolTable::new(),
+    };
+    if let Some(pos) = args.iter().position(|arg| arg == "--identify") {
+        let path = args.get(pos + 1).expect("--identify needs a file");
+        identify_rom(path);
+        return;
+    }
+    let rom_path = match args.iter().position(|arg| arg == "--roms") {
+        Some(pos) => romset::split_rom_path(args.get(pos + 1).expect("--roms needs a path")),
+        None => ROM_PATH.iter().map(PathBuf::from).collect(),
+    };
+    if let Some(pos) = args.iter().position(|arg| arg == "--gdb") {
+        let port = args
+            .get(pos + 1)
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_GDB_PORT);
+        let tracer = trace_from_args(&args, &symbols);
+        run_gdb_server(load_machine(&args, &rom_path), port, symbols, tracer);
+        return;
+    }
+    if let Some(pos) = args.iter().position(|arg| arg == "--disassemble") {
+        let path = args.get(pos + 1).filter(|arg| !arg.starts_with("--"));
+        write_disassembly(
+            &load_machine(&args, &rom_path),
+            path.map(String::as_str),
+            &symbols,
+        );
+        return;
+    }
 
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => break 'running,
-                Event::KeyDown {
-                    keycode: Some(keycode),
-                    ..
-                } => {
-                    invaders.handle_key_down(keycode);
-                }
-                Event::KeyUp {
-                    keycode: Some(keycode),
-                    ..
-                } => {
-                    invaders.handle_key_up(keycode);
-                }
-                _ => {}
+    let mut invaders = load_machine(&args, &rom_path);
+    invaders.get_state_mut().tracer = trace_from_args(&args, &symbols).map(Box::new);
+    if args.iter().any(|arg| arg == "--recompiler") {
+        if invaders.get_state().tracer.is_some() {
+            eprintln!("--trace only works with the interpreter; ignoring --recompiler");
+        } else {
+            invaders.set_engine(Engine::Recompiler);
+        }
+    }
+    let hiscore_path = hiscore::default_path(invaders.profile().name());
+    if let Some(Err(error)) = invaders
+        .hiscore_mut()
+        .map(|hiscore| hiscore.load(&hiscore_path))
+    {
+        eprintln!("Ignoring {}: {}", hiscore_path.display(), error);
+    }
+
+    window::run(&mut invaders);
+
+    if let Err(error) = invaders.save_hiscore(&hiscore_path) {
+        eprintln!("Could not save {}: {}", hiscore_path.display(), error);
+    }
+}
+
+/// Finds and verifies the ROM set of `--game <name>` (default invaders),
+/// exiting with the reason if it is bad, then applies each
+/// `--dip "<switch>=<setting>"`.
+fn load_machine(args: &[String], rom_path: &[PathBuf]) -> SpaceInvadersMachine {
+    let fail = |message: String| -> ! {
+        eprintln!("{}", message);
+        std::process::exit(1);
+    };
+    let profile = match args.iter().position(|arg| arg == "--game") {
+        Some(pos) => {
+            let name = args.get(pos + 1).expect("--game needs a name");
+            midway::find(name).unwrap_or_else(|| {
+                let names: Vec<&str> = midway::PROFILES.iter().map(|p| p.name()).collect();
+                fail(format!(
+                    "Unknown game {}; known: {}",
+                    name,
+                    names.join(", ")
+                ))
+            })
+        }
+        None => &midway::INVADERS,
+    };
+    let mut machine = SpaceInvadersMachine::from_profile(profile, rom_path)
+        .unwrap_or_else(|error| fail(error.to_string()));
+    if !profile.rom.verified() {
+        eprintln!(
+            "{}: no checksums are recorded for this set; its files were only checked for size",
+            profile.name()
+        );
+    }
+    for pair in args.windows(2).filter(|pair| pair[0] == "--dip") {
+        let (name, setting) = pair[1]
+            .split_once('=')
+            .unwrap_or_else(|| fail("--dip takes <switch>=<setting>".to_string()));
+        if !machine.board_mut().set_dip(name, setting) {
+            fail(format!(
+                "{} has no DIP switch setting {}",
+                profile.name(),
+                pair[1]
+            ));
+        }
+    }
+    machine
+}
+
+/// Prints which known ROM set, and which file of it, `path` holds.
+fn identify_rom(path: &str) {
+    let data =
+        std::fs::read(path).unwrap_or_else(|error| panic!("Could not read {}: {}", path, error));
+    match romset::identify(&data) {
+        Ok((set, Some(file))) => println!("{}: {} from {}", path, file.name, set.description),
+        Ok((set, None)) => println!("{}: complete {} image", path, set.description),
+        Err(error) => {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the machine headless under a GDB remote stub until the debugger detaches.
+fn run_gdb_server(
+    mut invaders: SpaceInvadersMachine,
+    port: u16,
+    symbols: SymbolTable,
+    tracer: Option<Tracer>,
+) {
+    invaders.get_state_mut().tracer = tracer.map(Box::new);
+    let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+    println!("Waiting for GDB on 127.0.0.1:{}", port);
+    let mut stub = GdbStub::new(&mut invaders);
+    stub.set_symbols(symbols);
+    if let Err(error) = stub.serve(&listener) {
+        eprintln!("GDB session ended: {}", error);
+    }
+}
+
+/// Builds a tracer from `--trace <file>` and its filters: `--trace-range <start>-<end>`,
+/// `--trace-from <addr>` and `--trace-limit <lines>`. Addresses may be symbol names.
+fn trace_from_args(args: &[String], symbols: &SymbolTable) -> Option<Tracer> {
+    let value = |flag: &str| {
+        args.iter().position(|arg| arg == flag).map(|pos| {
+            args.get(pos + 1)
+                .unwrap_or_else(|| panic!("{} needs a value", flag))
+        })
+    };
+    let address = |text: &str| {
+        symbols
+            .resolve(text)
+            .unwrap_or_else(|| panic!("Unknown address {}", text))
+    };
+
+    let path = value("--trace")?;
+    let mut tracer = Tracer::to_file(path)
+        .unwrap_or_else(|error| panic!("Could not create trace {}: {}", path, error))
+        .with_symbols(symbols.clone());
+    if let Some(range) = value("--trace-range") {
+        let (start, end) = range
+            .split_once('-')
+            .expect("--trace-range takes <start>-<end>");
+        tracer = tracer.with_range(address(start)..=address(end));
+    }
+    if let Some(trigger) = value("--trace-from") {
+        tracer = tracer.with_trigger(address(trigger));
+    }
+    if let Some(limit) = value("--trace-limit") {
+        tracer = tracer.with_limit(limit.parse().expect("--trace-limit takes a line count"));
+    }
+    Some(tracer)
+}
+
+/// Runs a CP/M program with drive A: on the current directory and the
+/// console on stdin and stdout.
+fn run_cpm(program: &str, args: &str) {
+    let mut cpm = Cpm::new(".", StdConsole::new());
+    if let Err(error) = cpm.load_com_file(program, args) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+    let exit = cpm.run();
+    cpm.console_mut().write(b'\n');
+    if let Exit::Halted { pc } = exit {
+        eprintln!("Halted at {:04X}", pc);
+        std::process::exit(1);
+    }
+}
+
+/// Runs a program on an Altair with the terminal on stdin and stdout. Files
+/// ending `.tap` are MITS paper tapes; anything else is a memory image
+/// loaded at 0. With `--rom`, the machine starts in the ROM instead, to boot
+/// the disks given with `--disk`.
+fn run_altair(program: Option<&str>, args: &[String]) {
+    let value = |flag: &str| {
+        let pos = args.iter().position(|arg| arg == flag)?;
+        args.get(pos + 1)
+    };
+    let fail = |path: &str, error: &dyn std::fmt::Display| -> ! {
+        eprintln!("{}: {}", path, error);
+        std::process::exit(1);
+    };
+    let ram = value("--ram").map_or(64, |kb| kb.parse().expect("--ram takes kilobytes"));
+    let mut altair = Altair::new(ram * 1024, StdConsole::new());
+    if let Some(sense) = value("--sense") {
+        let sense = u8::from_str_radix(sense, 16).expect("--sense takes a hex byte");
+        altair.set_switches((sense as u16) << 8);
+    }
+
+    // Drives in order, each read-only with a :ro suffix
+    let disks: Vec<&String> = args
+        .windows(2)
+        .filter(|pair| pair[0] == "--disk")
+        .map(|pair| &pair[1])
+        .collect();
+    if disks.len() > dcdd::DRIVES {
+        eprintln!("--disk can be given at most {} times", dcdd::DRIVES);
+        std::process::exit(1);
+    }
+    for (drive, disk) in disks.into_iter().enumerate() {
+        let (path, read_only) = match disk.strip_suffix(":ro") {
+            Some(path) => (path, true),
+            None => (disk.as_str(), false),
+        };
+        match DiskImage::open(path, DiskFormat::Mits, read_only) {
+            Ok(image) => {
+                altair.mount(drive, image);
+            }
+            Err(error) => fail(path, &error),
+        }
+    }
+
+    if let Some(program) = program {
+        let image = std::fs::read(program).unwrap_or_else(|error| fail(program, &error));
+        if program.to_ascii_lowercase().ends_with(".tap") {
+            match altair::parse_tape(&image) {
+                Ok(tape) => altair.load_tape(&tape),
+                Err(error) => fail(program, &error),
             }
+        } else {
+            altair.load(0, &image);
         }
+    }
+    // A ROM at the top of memory unless an address is given, e.g. dbl.bin@FF00
+    if let Some(rom) = value("--rom") {
+        let (path, address) = match rom.split_once('@') {
+            Some((path, address)) => (path, Some(address)),
+            None => (rom.as_str(), None),
+        };
+        let image = std::fs::read(path).unwrap_or_else(|error| fail(path, &error));
+        let address = address.map_or(0x10000 - image.len().min(0x10000), |address| {
+            usize::from_str_radix(address, 16).expect("--rom address is hex")
+        });
+        altair.load_rom(address as u16, &image);
+        if program.is_none() {
+            // Set the address switches, EXAMINE, then put the sense switches back
+            let sense = altair.switches();
+            altair.set_switches(address as u16);
+            altair.examine();
+            altair.set_switches(sense);
+        }
+    }
 
-        let frame_time = frame_start.elapsed();
-        if frame_time < frame_duration {
-            std::thread::sleep(frame_duration - frame_time);
+    let exit = altair.run();
+    altair.console_mut().write(b'\n');
+    if let Exit::Halted { pc } = exit {
+        eprintln!("Halted at {:04X}", pc);
+        std::process::exit(1);
+    }
+}
+
+/// Boots CP/M from disk images with the console on stdin and stdout.
+fn boot_cpm(disks: &[String]) {
+    let mut system = DiskSystem::new(StdConsole::new());
+    if disks.len() > disk::DRIVES {
+        eprintln!("--boot takes at most {} disk images", disk::DRIVES);
+        std::process::exit(1);
+    }
+    for (drive, disk) in disks.iter().enumerate() {
+        let (path, read_only) = match disk.strip_suffix(":ro") {
+            Some(path) => (path, true),
+            None => (disk.as_str(), false),
+        };
+        match DiskImage::open(path, DiskFormat::Ibm3740, read_only) {
+            Ok(image) => {
+                system.mount(drive, image);
+            }
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
         }
     }
+    if let Err(error) = system.boot() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+    let exit = system.run();
+    system.console_mut().write(b'\n');
+    if let Exit::Halted { pc } = exit {
+        eprintln!("Halted at {:04X}", pc);
+        std::process::exit(1);
+    }
+}
+
+/// Writes a reassemblable listing of the loaded ROM to `path`, or stdout.
+fn write_disassembly(invaders: &SpaceInvadersMachine, path: Option<&str>, symbols: &SymbolTable) {
+    let profile = invaders.profile().disassembly;
+    let mut disassembler = RecursiveDisassembler::from_profile(invaders.get_memory(), profile);
+    disassembler.apply_symbols(symbols);
+    let listing = disassembler.listing(profile.name);
+    match path {
+        Some(path) => std::fs::write(path, listing).unwrap(),
+        None => print!("{}", listing),
+    }
 }
 
-fn draw_screen(canvas: &mut Canvas<Window>, memory: &[u8]) {
-    canvas.set_draw_color(Color::BLACK);
-    canvas.clear();
-
-    // Loop over each byte in video memory
-    for addr in VIDEO_MEM_START..VIDEO_MEM_END {
-        let byte = memory[addr];
-
-        // Each byte represents 8 vertical pixels
-        for bit in 0..8 {
-            let pixel_on = (byte >> bit) & 1;
-
-            if pixel_on != 0 {
-                // Calculate the x and y coordinates (rotated for Space Invaders)
-                let pixel_index = (addr - VIDEO_MEM_START) * 8 + bit;
-                let x = (pixel_index % 256) as u32;
-                let y = (pixel_index / 256) as u32;
-
-                // Scale the coordinates to fit the doubled screen size
-                let scaled_x = (256 - x).wrapping_mul(SCALE_FACTOR);
-                let scaled_y = y.wrapping_mul(SCALE_FACTOR);
-
-                // Draw a scaled rectangle to represent the pixel
-                canvas.set_draw_color(Color::WHITE);
-                let rect = Rect::new(
-                    scaled_y as i32,
-                    scaled_x as i32,
-                    SCALE_FACTOR, // Width of the rectangle (scaled pixel)
-                    SCALE_FACTOR, // Height of the rectangle (scaled pixel)
-                );
-                canvas.fill_rect(rect).unwrap();
+/// The game window, with the machine's inputs on the keyboard.
+#[cfg(feature = "sdl")]
+mod window {
+    use intel_8080_emu_rust::emulator::machine::SpaceInvadersMachine;
+    use intel_8080_emu_rust::emulator::video;
+    use sdl2::keyboard::Keycode;
+    use sdl2::render::Canvas;
+    use sdl2::video::Window;
+    use sdl2::{event::Event, pixels::Color, rect::Rect};
+    use std::time::Duration;
+    use std::time::Instant;
+
+    const SCALE_FACTOR: u32 = 2;
+    const SCREEN_WIDTH: u32 = video::WIDTH as u32 * SCALE_FACTOR;
+    const SCREEN_HEIGHT: u32 = video::HEIGHT as u32 * SCALE_FACTOR;
+
+    /// Runs the machine in a window until it is closed.
+    pub fn run(invaders: &mut SpaceInvadersMachine) {
+        let sdl_context = sdl2::init().unwrap();
+        let video_subsystem = sdl_context.video().unwrap();
+        let window = video_subsystem
+            .window("Intel 8080 Emulator", SCREEN_WIDTH, SCREEN_HEIGHT)
+            .position_centered()
+            .build()
+            .unwrap();
+        let mut last_time = Instant::now();
+        let mut frame_count = 0;
+        let mut canvas = window.into_canvas().build().unwrap();
+        let mut event_pump = sdl_context.event_pump().unwrap();
+        let target_fps = 120;
+        let frame_duration = Duration::from_millis(1000 / target_fps);
+
+        'running: loop {
+            // this starts emulation per 33000 cycles
+            let frame_start = Instant::now();
+            invaders.start_emulation();
+            draw_screen(&mut canvas, invaders);
+            frame_count += 1;
+            if last_time.elapsed() >= Duration::new(1, 0) {
+                println!("FPS: {}", frame_count);
+                frame_count = 0;
+                last_time = Instant::now();
+            }
+
+            for event in event_pump.poll_iter() {
+                match event {
+                    Event::Quit { .. } => break 'running,
+                    Event::KeyDown {
+                        keycode: Some(keycode),
+                        ..
+                    } => {
+                        if let Some(input) = input_for_key(keycode) {
+                            invaders.board_mut().set_input(input, true);
+                        }
+                    }
+                    Event::KeyUp {
+                        keycode: Some(keycode),
+                        ..
+                    } => {
+                        if let Some(input) = input_for_key(keycode) {
+                            invaders.board_mut().set_input(input, false);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            let frame_time = frame_start.elapsed();
+            if frame_time < frame_duration {
+                std::thread::sleep(frame_duration - frame_time);
+            }
+        }
+    }
+
+    /// Keyboard layout shared by every profile; keys a game lacks do nothing.
+    fn input_for_key(key: Keycode) -> Option<&'static str> {
+        Some(match key {
+            Keycode::Tab => "Coin",
+            Keycode::RETURN => "P1 Start",
+            Keycode::Num2 => "P2 Start",
+            Keycode::Left => "P1 Left",
+            Keycode::Right => "P1 Right",
+            Keycode::Up => "P1 Up",
+            Keycode::Down => "P1 Down",
+            Keycode::Space => "P1 Fire",
+            Keycode::A => "P2 Left",
+            Keycode::D => "P2 Right",
+            Keycode::W => "P2 Up",
+            Keycode::S => "P2 Down",
+            Keycode::LCtrl => "P2 Fire",
+            Keycode::T => "Tilt",
+            _ => return None,
+        })
+    }
+
+    fn draw_screen(canvas: &mut Canvas<Window>, invaders: &SpaceInvadersMachine) {
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+
+        let mut pixels = vec![0; video::WIDTH * video::HEIGHT * 4];
+        video::render(
+            &invaders.get_memory()[video::VIDEO_RAM],
+            invaders.colour_map(),
+            &mut pixels,
+        );
+        for (index, pixel) in pixels.chunks_exact(4).enumerate() {
+            if pixel[..3] == [0, 0, 0] {
+                continue;
             }
+            // Draw a scaled rectangle to represent the pixel
+            let (x, y) = ((index % video::WIDTH) as u32, (index / video::WIDTH) as u32);
+            canvas.set_draw_color(Color::RGB(pixel[0], pixel[1], pixel[2]));
+            let rect = Rect::new(
+                (x * SCALE_FACTOR) as i32,
+                (y * SCALE_FACTOR) as i32,
+                SCALE_FACTOR,
+                SCALE_FACTOR,
+            );
+            canvas.fill_rect(rect).unwrap();
         }
+
+        canvas.present();
     }
+}
 
-    canvas.present();
+#[cfg(not(feature = "sdl"))]
+mod window {
+    use intel_8080_emu_rust::emulator::machine::SpaceInvadersMachine;
+
+    pub fn run(_invaders: &mut SpaceInvadersMachine) {
+        eprintln!("Built without the game window; rebuild with --features sdl");
+        std::process::exit(1);
+    }
 }
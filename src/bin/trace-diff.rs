@@ -0,0 +1,46 @@
+//! Compares two execution traces and reports where they first diverge.
+//!
+//! Usage: trace_diff [--ignore-cycles] [--bytes] <expected> <actual>
+
+use intel_8080_emu_rust::emulator::trace::{diff, DiffOptions};
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = DiffOptions {
+        ignore_cycles: args.iter().any(|arg| arg == "--ignore-cycles"),
+        compare_bytes: args.iter().any(|arg| arg == "--bytes"),
+    };
+    let files: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    let [expected, actual] = files.as_slice() else {
+        eprintln!("Usage: trace_diff [--ignore-cycles] [--bytes] <expected> <actual>");
+        return ExitCode::from(2);
+    };
+
+    let open = |path: &str| match File::open(path) {
+        Ok(file) => Some(BufReader::new(file)),
+        Err(error) => {
+            eprintln!("Could not open {}: {}", path, error);
+            None
+        }
+    };
+    let (Some(expected), Some(actual)) = (open(expected), open(actual)) else {
+        return ExitCode::from(2);
+    };
+    match diff(expected, actual, options) {
+        Ok(None) => {
+            println!("Traces match");
+            ExitCode::SUCCESS
+        }
+        Ok(Some(divergence)) => {
+            println!("{}", divergence);
+            ExitCode::FAILURE
+        }
+        Err(error) => {
+            eprintln!("Could not read traces: {}", error);
+            ExitCode::from(2)
+        }
+    }
+}
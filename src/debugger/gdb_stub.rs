@@ -0,0 +1,638 @@
+//! GDB remote serial protocol server.
+//!
+//! Speaks enough of the protocol for `target remote localhost:<port>`: the
+//! register file (A, flags, BC, DE, HL, SP, PC), memory access, software
+//! breakpoints, watchpoints, single-step and continue with Ctrl-C.
+
+use super::{DebugTarget, Debugger, StopReason, WatchKind};
+use crate::disassembler::{Disassembler, FormatOptions};
+use crate::emulator::cheat::{self, RamSearch, SearchCondition};
+use crate::symbols::SymbolTable;
+use std::fmt::Write as _;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+const PACKET_SIZE: usize = 0x1000;
+const NUM_REGISTERS: usize = 7;
+const INTERRUPT_BYTE: u8 = 0x03;
+const DISASSEMBLY_LINES: usize = 10;
+const SEARCH_RESULTS_SHOWN: usize = 20;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.i8080.cpu">
+    <reg name="a" bitsize="8" type="uint8" regnum="0"/>
+    <reg name="flags" bitsize="8" type="uint8"/>
+    <reg name="bc" bitsize="16" type="uint16"/>
+    <reg name="de" bitsize="16" type="uint16"/>
+    <reg name="hl" bitsize="16" type="uint16"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>
+"#;
+
+enum Incoming {
+    Packet(String),
+    Interrupt,
+}
+
+struct Connection {
+    stream: TcpStream,
+    no_ack: bool,
+    pending: Vec<u8>,
+}
+
+impl Connection {
+    fn read_byte(&mut self) -> io::Result<u8> {
+        if !self.pending.is_empty() {
+            return Ok(self.pending.remove(0));
+        }
+        let mut byte = [0u8];
+        self.stream.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn read_packet(&mut self) -> io::Result<Incoming> {
+        loop {
+            match self.read_byte()? {
+                INTERRUPT_BYTE => return Ok(Incoming::Interrupt),
+                b'$' => break,
+                // Acks, nacks and line noise between packets
+                _ => continue,
+            }
+        }
+
+        let mut body = Vec::new();
+        loop {
+            match self.read_byte()? {
+                b'#' => break,
+                b'}' => body.push(self.read_byte()? ^ 0x20),
+                byte => body.push(byte),
+            }
+        }
+        let checksum = [self.read_byte()?, self.read_byte()?];
+
+        if !self.no_ack {
+            let expected = format!("{:02x}", checksum_of(&body));
+            let ack: &[u8] = if expected.as_bytes() == checksum.to_ascii_lowercase() {
+                b"+"
+            } else {
+                b"-"
+            };
+            self.stream.write_all(ack)?;
+        }
+        Ok(Incoming::Packet(
+            String::from_utf8_lossy(&body).into_owned(),
+        ))
+    }
+
+    fn send_packet(&mut self, body: &str) -> io::Result<()> {
+        let framed = format!("${}#{:02x}", body, checksum_of(body.as_bytes()));
+        loop {
+            self.stream.write_all(framed.as_bytes())?;
+            self.stream.flush()?;
+            if self.no_ack {
+                return Ok(());
+            }
+            match self.read_byte()? {
+                b'+' => return Ok(()),
+                b'-' => continue,
+                // Anything else is the start of the next packet; keep it
+                other => {
+                    self.pending.push(other);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Non-blocking check for a Ctrl-C sent while the target is running.
+    fn poll_interrupt(&mut self) -> bool {
+        if self.stream.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let mut buffer = [0u8; 64];
+        let interrupted = match self.stream.read(&mut buffer) {
+            Ok(0) => true,
+            Ok(n) => {
+                let interrupted = buffer[..n].contains(&INTERRUPT_BYTE);
+                self.pending
+                    .extend(buffer[..n].iter().filter(|&&b| b != INTERRUPT_BYTE));
+                interrupted
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => false,
+            Err(_) => true,
+        };
+        let _ = self.stream.set_nonblocking(false);
+        interrupted
+    }
+}
+
+pub struct GdbStub<'a, T: DebugTarget> {
+    target: &'a mut T,
+    debugger: Debugger,
+    symbols: SymbolTable,
+    search: Option<RamSearch>,
+    swbreak: bool,
+}
+
+impl<'a, T: DebugTarget> GdbStub<'a, T> {
+    pub fn new(target: &'a mut T) -> Self {
+        GdbStub {
+            target,
+            debugger: Debugger::new(),
+            symbols: SymbolTable::new(),
+            search: None,
+            swbreak: false,
+        }
+    }
+
+    /// Names used by the monitor commands, both in output and for entering addresses.
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
+    /// Waits for a single debugger to connect and serves it until it detaches.
+    pub fn serve(&mut self, listener: &TcpListener) -> io::Result<()> {
+        let (stream, _) = listener.accept()?;
+        self.run_session(stream)
+    }
+
+    pub fn run_session(&mut self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut connection = Connection {
+            stream,
+            no_ack: false,
+            pending: Vec::new(),
+        };
+
+        loop {
+            let packet = match connection.read_packet() {
+                Ok(Incoming::Packet(packet)) => packet,
+                // Ctrl-C while already stopped
+                Ok(Incoming::Interrupt) => {
+                    connection.send_packet("S02")?;
+                    continue;
+                }
+                Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            match packet.as_bytes().first() {
+                Some(b'c') => {
+                    self.resume_at(&packet[1..]);
+                    let reason = self
+                        .debugger
+                        .run(self.target, || connection.poll_interrupt());
+                    connection.send_packet(&self.stop_reply(reason))?;
+                }
+                Some(b's') => {
+                    self.resume_at(&packet[1..]);
+                    let reason = self.debugger.step(self.target);
+                    connection.send_packet(&self.stop_reply(reason))?;
+                }
+                Some(b'k') => return Ok(()),
+                Some(b'D') => {
+                    connection.send_packet("OK")?;
+                    return Ok(());
+                }
+                _ if packet == "QStartNoAckMode" => {
+                    connection.send_packet("OK")?;
+                    connection.no_ack = true;
+                }
+                _ => {
+                    let reply = self.handle_packet(&packet);
+                    connection.send_packet(&reply)?;
+                }
+            }
+        }
+    }
+
+    fn resume_at(&mut self, addr: &str) {
+        if let Some(addr) = parse_hex(addr) {
+            self.target.state_mut().pc = addr as u16;
+        }
+    }
+
+    fn stop_reply(&self, reason: StopReason) -> String {
+        match reason {
+            StopReason::Step => "S05".to_string(),
+            StopReason::Interrupted => "S02".to_string(),
+            StopReason::Breakpoint(_) if self.swbreak => "T05swbreak:;".to_string(),
+            StopReason::Breakpoint(_) => "S05".to_string(),
+            StopReason::Watchpoint { kind, addr } => {
+                let name = match kind {
+                    WatchKind::Write => "watch",
+                    WatchKind::Read => "rwatch",
+                    WatchKind::Access => "awatch",
+                };
+                format!("T05{}:{:04x};", name, addr)
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: &str) -> String {
+        if packet.is_empty() {
+            return String::new();
+        }
+        let (command, args) = packet.split_at(1);
+        match command {
+            "?" => "S05".to_string(),
+            "g" => self.read_registers(),
+            "G" => self.write_registers(args),
+            "p" => match parse_hex(args) {
+                Some(reg) if reg < NUM_REGISTERS => encode_register(self.target.state(), reg),
+                _ => "E01".to_string(),
+            },
+            "P" => self.write_register(args),
+            "m" => self.read_memory(args),
+            "M" => self.write_memory(args),
+            "Z" | "z" => self.update_point(command == "Z", args),
+            "H" | "T" => "OK".to_string(),
+            "q" => self.handle_query(packet),
+            // vCont, vMustReplyEmpty and anything else unsupported
+            _ => String::new(),
+        }
+    }
+
+    fn handle_query(&mut self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            self.swbreak = packet.contains("swbreak+");
+            return format!(
+                "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+;swbreak+",
+                PACKET_SIZE
+            );
+        }
+        if let Some(args) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            return read_xfer(TARGET_XML, args);
+        }
+        if let Some(command) = packet.strip_prefix("qRcmd,") {
+            return match decode_hex_bytes(command) {
+                Some(bytes) => {
+                    let output = self.monitor(&String::from_utf8_lossy(&bytes));
+                    if output.is_empty() {
+                        "OK".to_string()
+                    } else {
+                        encode_hex_bytes(output.as_bytes())
+                    }
+                }
+                None => "E01".to_string(),
+            };
+        }
+        match packet {
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            "qSymbol::" => "OK".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// `monitor` commands typed at the GDB prompt.
+    fn monitor(&mut self, command: &str) -> String {
+        let mut words = command.split_whitespace();
+        match words.next() {
+            Some("regs") => {
+                let state = self.target.state();
+                format!(
+                    "A={:02X} F={:02X} BC={:02X}{:02X} DE={:02X}{:02X} HL={:02X}{:02X} SP={:04X} PC={:04X} IE={}\n",
+                    state.a,
+                    state.get_flags_as_byte(),
+                    state.b,
+                    state.c,
+                    state.d,
+                    state.e,
+                    state.h,
+                    state.l,
+                    state.sp,
+                    state.pc,
+                    state.int_enable as u8
+                )
+            }
+            Some("breakpoints") => {
+                let mut output = String::new();
+                let mut breakpoints: Vec<u16> = self.debugger.breakpoints().collect();
+                breakpoints.sort_unstable();
+                for addr in breakpoints {
+                    let line = format!("break  {:04X}  {}", addr, self.symbols.name_of(addr).unwrap_or_default());
+                    let _ = writeln!(output, "{}", line.trim_end());
+                }
+                for w in self.debugger.watchpoints() {
+                    let _ = writeln!(output, "{:?}  {:04X} len {}", w.kind, w.addr, w.len);
+                }
+                output
+            }
+            Some("dis") => {
+                let state = self.target.state();
+                let mut addr = words
+                    .next()
+                    .and_then(|addr| self.symbols.resolve(addr))
+                    .unwrap_or(state.pc);
+                let count = words
+                    .next()
+                    .and_then(|count| count.parse().ok())
+                    .unwrap_or(DISASSEMBLY_LINES);
+                let options = FormatOptions {
+                    mnemonic_width: 7,
+                    ..FormatOptions::default()
+                };
+                let name_of = |addr: u16| self.symbols.name_of(addr).map(str::to_string);
+                let mut output = String::new();
+                for _ in 0..count {
+                    if let Some(name) = self.symbols.name_of(addr) {
+                        let _ = writeln!(output, "{}:", name);
+                    }
+                    let instruction = Disassembler::decode(&state.memory, addr as usize);
+                    let marker = if addr == state.pc { '>' } else { ' ' };
+                    let line = Disassembler::format_line_with(&instruction, &options, name_of);
+                    let _ = writeln!(output, "{} {}", marker, line);
+                    addr = instruction.next_address();
+                }
+                output
+            }
+            Some("sym") => match words.next() {
+                Some(location) => match self.symbols.resolve(location) {
+                    Some(addr) => match self.symbols.name_of(addr) {
+                        Some(name) => format!("{:04X}  {}\n", addr, name),
+                        None => format!("{:04X}\n", addr),
+                    },
+                    None => format!("Unknown symbol: {}\n", location),
+                },
+                None => format!("{} symbols loaded\n", self.symbols.len()),
+            },
+            Some(command @ ("break" | "delete")) => {
+                let Some(addr) = words.next().and_then(|addr| self.symbols.resolve(addr)) else {
+                    return format!("Usage: {} <addr|symbol>\n", command);
+                };
+                if command == "break" {
+                    self.debugger.add_breakpoint(addr);
+                } else {
+                    self.debugger.remove_breakpoint(addr);
+                }
+                format!("{}  {:04X}\n", command, addr)
+            }
+            Some("search") => self.search(words.next(), words.next()),
+            Some(command @ ("freeze" | "unfreeze" | "cheat")) => {
+                let args: Vec<&str> = words.collect();
+                self.cheat(command, &args)
+            }
+            Some("game") => match self.target.inspector() {
+                Some(inspector) => inspector.snapshot().to_string(),
+                None => "Game state is not available for this game\n".to_string(),
+            },
+            _ => "Commands: regs, breakpoints, dis [addr] [count], sym [name], break <addr>, delete <addr>, game,\n  search [new|list|eq|ne|gt|lt <value>|changed|unchanged|increased|decreased],\n  freeze <addr> <value>, unfreeze <addr>, cheat [on|off <name>]\n".to_string(),
+        }
+    }
+
+    /// `monitor search`: narrows down RAM addresses over several commands.
+    fn search(&mut self, kind: Option<&str>, value: Option<&str>) -> String {
+        let memory = &self.target.state().memory;
+        let search = match (kind, self.search.as_mut()) {
+            (None | Some("new"), _) => {
+                let search = RamSearch::new(memory, cheat::WORK_RAM);
+                let count = search.candidates().len();
+                self.search = Some(search);
+                return format!("{} candidates\n", count);
+            }
+            (Some(_), None) => {
+                return "No search running; start one with 'search new'\n".to_string()
+            }
+            (Some(_), Some(search)) => search,
+        };
+        if kind != Some("list") {
+            let Some(condition) = SearchCondition::parse(kind.unwrap_or_default(), value) else {
+                return "Usage: search [new|list|eq|ne|gt|lt <value>|changed|unchanged|increased|decreased]\n".to_string();
+            };
+            search.filter(memory, condition);
+        }
+        let candidates = search.candidates();
+        let mut output = format!("{} candidates\n", candidates.len());
+        for &addr in candidates.iter().take(SEARCH_RESULTS_SHOWN) {
+            let line = format!(
+                "{:04X}  {:02X}  {}",
+                addr,
+                memory[addr as usize],
+                self.symbols.name_of(addr).unwrap_or_default()
+            );
+            let _ = writeln!(output, "{}", line.trim_end());
+        }
+        output
+    }
+
+    /// `monitor freeze`, `unfreeze` and `cheat`.
+    fn cheat(&mut self, command: &str, args: &[&str]) -> String {
+        let symbols = &self.symbols;
+        let Some(cheats) = self.target.cheats() else {
+            return "This target has no cheat support\n".to_string();
+        };
+        match (command, args) {
+            ("freeze", [addr, value]) => {
+                let value = cheat::parse_value(value);
+                match (symbols.resolve(addr), value) {
+                    (Some(addr), Some(value)) => {
+                        cheats.freeze(addr, value);
+                        format!("freeze  {:04X}={:02X}\n", addr, value)
+                    }
+                    _ => "Usage: freeze <addr> <value>\n".to_string(),
+                }
+            }
+            ("unfreeze", [addr]) => match symbols.resolve(addr) {
+                Some(addr) if cheats.unfreeze(addr) => format!("unfreeze  {:04X}\n", addr),
+                _ => format!("{} is not frozen\n", addr),
+            },
+            ("cheat", [state @ ("on" | "off"), name @ ..]) if !name.is_empty() => {
+                let name = name.join(" ");
+                if cheats.set_enabled(&name, *state == "on") {
+                    format!("{}  {}\n", name, state)
+                } else {
+                    format!("No cheat named '{}'\n", name)
+                }
+            }
+            ("cheat", []) => {
+                let mut output = String::new();
+                for cheat in cheats.cheats() {
+                    let mark = if cheat.enabled { "on " } else { "off" };
+                    let _ = writeln!(output, "{}  {}", mark, cheat.name);
+                }
+                for (addr, value) in cheats.frozen() {
+                    let _ = writeln!(output, "frozen  {:04X}={:02X}", addr, value);
+                }
+                output
+            }
+            _ => {
+                "Usage: freeze <addr> <value>, unfreeze <addr>, cheat [on|off <name>]\n".to_string()
+            }
+        }
+    }
+
+    fn read_registers(&self) -> String {
+        (0..NUM_REGISTERS)
+            .map(|reg| encode_register(self.target.state(), reg))
+            .collect()
+    }
+
+    fn write_registers(&mut self, args: &str) -> String {
+        let Some(bytes) = decode_hex_bytes(args) else {
+            return "E01".to_string();
+        };
+        if bytes.len() < 12 {
+            return "E01".to_string();
+        }
+        let word = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let state = self.target.state_mut();
+        state.a = bytes[0];
+        state.set_flags_from_byte(bytes[1]);
+        [state.b, state.c] = word(2).to_be_bytes();
+        [state.d, state.e] = word(4).to_be_bytes();
+        [state.h, state.l] = word(6).to_be_bytes();
+        state.sp = word(8);
+        state.pc = word(10);
+        "OK".to_string()
+    }
+
+    fn write_register(&mut self, args: &str) -> String {
+        let Some((reg, value)) = args.split_once('=') else {
+            return "E01".to_string();
+        };
+        let (Some(reg), Some(bytes)) = (parse_hex(reg), decode_hex_bytes(value)) else {
+            return "E01".to_string();
+        };
+        // One byte for A and the flags, two for the pairs, SP and PC
+        let size = if reg < 2 { 1 } else { 2 };
+        if reg >= NUM_REGISTERS || bytes.len() != size {
+            return "E01".to_string();
+        }
+        let state = self.target.state_mut();
+        let word = || u16::from_le_bytes([bytes[0], bytes[1]]);
+        match reg {
+            0 => state.a = bytes[0],
+            1 => state.set_flags_from_byte(bytes[0]),
+            2 => [state.b, state.c] = word().to_be_bytes(),
+            3 => [state.d, state.e] = word().to_be_bytes(),
+            4 => [state.h, state.l] = word().to_be_bytes(),
+            5 => state.sp = word(),
+            _ => state.pc = word(),
+        }
+        "OK".to_string()
+    }
+
+    fn read_memory(&self, args: &str) -> String {
+        let Some((addr, len)) = parse_addr_len(args) else {
+            return "E01".to_string();
+        };
+        let memory = &self.target.state().memory;
+        let bytes: Vec<u8> = (0..len.min(PACKET_SIZE / 2))
+            .map(|i| memory[addr.wrapping_add(i) & 0xFFFF])
+            .collect();
+        encode_hex_bytes(&bytes)
+    }
+
+    fn write_memory(&mut self, args: &str) -> String {
+        let Some((location, data)) = args.split_once(':') else {
+            return "E01".to_string();
+        };
+        let (Some((addr, len)), Some(bytes)) = (parse_addr_len(location), decode_hex_bytes(data))
+        else {
+            return "E01".to_string();
+        };
+        if bytes.len() != len {
+            return "E01".to_string();
+        }
+        let memory = &mut self.target.state_mut().memory;
+        for (i, byte) in bytes.into_iter().enumerate() {
+            memory[addr.wrapping_add(i) & 0xFFFF] = byte;
+        }
+        "OK".to_string()
+    }
+
+    fn update_point(&mut self, insert: bool, args: &str) -> String {
+        let mut fields = args.split(',');
+        let (Some(kind), Some(addr), Some(len)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return "E01".to_string();
+        };
+        let (Some(addr), Some(len)) = (parse_hex(addr), parse_hex(len)) else {
+            return "E01".to_string();
+        };
+        let (addr, len) = (addr as u16, len as u16);
+
+        let watch_kind = match kind {
+            // Software and hardware breakpoints are handled the same way
+            "0" | "1" => {
+                if insert {
+                    self.debugger.add_breakpoint(addr);
+                } else {
+                    self.debugger.remove_breakpoint(addr);
+                }
+                return "OK".to_string();
+            }
+            "2" => WatchKind::Write,
+            "3" => WatchKind::Read,
+            "4" => WatchKind::Access,
+            _ => return String::new(),
+        };
+        if insert {
+            self.debugger.add_watchpoint(addr, len, watch_kind);
+        } else {
+            self.debugger.remove_watchpoint(addr, len, watch_kind);
+        }
+        "OK".to_string()
+    }
+}
+
+fn encode_register(state: &crate::emulator::data_types::State8080, reg: usize) -> String {
+    match reg {
+        0 => format!("{:02x}", state.a),
+        1 => format!("{:02x}", state.get_flags_as_byte()),
+        2 => encode_hex_bytes(&[state.c, state.b]),
+        3 => encode_hex_bytes(&[state.e, state.d]),
+        4 => encode_hex_bytes(&[state.l, state.h]),
+        5 => encode_hex_bytes(&state.sp.to_le_bytes()),
+        _ => encode_hex_bytes(&state.pc.to_le_bytes()),
+    }
+}
+
+fn read_xfer(document: &str, args: &str) -> String {
+    let Some((offset, len)) = parse_addr_len(args) else {
+        return "E01".to_string();
+    };
+    if offset >= document.len() {
+        return "l".to_string();
+    }
+    let end = (offset + len).min(document.len());
+    let marker = if end == document.len() { 'l' } else { 'm' };
+    format!("{}{}", marker, &document[offset..end])
+}
+
+fn checksum_of(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+fn parse_hex(text: &str) -> Option<usize> {
+    usize::from_str_radix(text.trim(), 16).ok()
+}
+
+fn parse_addr_len(text: &str) -> Option<(usize, usize)> {
+    let (addr, len) = text.split_once(',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+fn encode_hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{:02x}", b);
+        out
+    })
+}
+
+fn decode_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,259 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod gdb_stub;
+
+use crate::emulator::cheat::CheatEngine;
+use crate::emulator::cpu::{self, CPU};
+use crate::emulator::data_types::State8080;
+use crate::emulator::inspector::GameInspector;
+use rustc_hash::FxHashSet;
+
+// How many instructions run between polls of the `should_stop` callback
+const POLL_INTERVAL: usize = 4096;
+
+/// Anything the debugger can single-step: a bare CPU or a whole machine
+/// (which also takes care of its own interrupt timing).
+pub trait DebugTarget {
+    fn state(&self) -> &State8080;
+    fn state_mut(&mut self) -> &mut State8080;
+    /// Executes exactly one instruction and returns the cycles it took.
+    fn step(&mut self) -> u8;
+    /// Cheats applied every frame, for targets that have frames.
+    fn cheats(&mut self) -> Option<&mut CheatEngine> {
+        None
+    }
+    /// Decoded game state, for targets running a game the inspector knows.
+    fn inspector(&self) -> Option<GameInspector<'_>> {
+        None
+    }
+}
+
+impl DebugTarget for CPU {
+    fn state(&self) -> &State8080 {
+        &self.state
+    }
+
+    fn state_mut(&mut self) -> &mut State8080 {
+        &mut self.state
+    }
+
+    fn step(&mut self) -> u8 {
+        cpu::emulate_8080_op(&mut self.state)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WatchKind {
+    Write,
+    Read,
+    Access,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addr: u16,
+    pub len: u16,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    fn covers(&self, addr: u16) -> bool {
+        addr.wrapping_sub(self.addr) < self.len.max(1)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// A single step completed without hitting anything.
+    Step,
+    /// Execution reached an address with a breakpoint set.
+    Breakpoint(u16),
+    /// The last instruction touched a watched address.
+    Watchpoint { kind: WatchKind, addr: u16 },
+    /// The `should_stop` callback asked for a halt (e.g. Ctrl-C from GDB).
+    Interrupted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct MemoryAccess {
+    addr: u16,
+    write: bool,
+}
+
+/// Breakpoint and watchpoint bookkeeping shared by the debugger frontends.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: FxHashSet<u16>,
+    watchpoints: Vec<Watchpoint>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    pub fn has_breakpoint(&self, addr: u16) -> bool {
+        self.breakpoints.contains(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn add_watchpoint(&mut self, addr: u16, len: u16, kind: WatchKind) {
+        let watchpoint = Watchpoint { addr, len, kind };
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    pub fn remove_watchpoint(&mut self, addr: u16, len: u16, kind: WatchKind) -> bool {
+        let before = self.watchpoints.len();
+        self.watchpoints
+            .retain(|w| !(w.addr == addr && w.len == len && w.kind == kind));
+        self.watchpoints.len() != before
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+    }
+
+    /// Executes one instruction, reporting a watchpoint hit if it touched one.
+    pub fn step<T: DebugTarget>(&self, target: &mut T) -> StopReason {
+        if self.watchpoints.is_empty() {
+            target.step();
+            return StopReason::Step;
+        }
+
+        let state = target.state();
+        let pc = state.pc;
+        let opcode = [
+            state.memory[pc as usize],
+            state.memory[pc.wrapping_add(1) as usize],
+            state.memory[pc.wrapping_add(2) as usize],
+        ];
+        let (bc, de, hl, sp) = (
+            u16::from_be_bytes([state.b, state.c]),
+            u16::from_be_bytes([state.d, state.e]),
+            u16::from_be_bytes([state.h, state.l]),
+            state.sp,
+        );
+
+        target.step();
+
+        let sp_after = target.state().sp;
+        for access in memory_accesses(opcode, bc, de, hl, sp, sp_after) {
+            for watchpoint in &self.watchpoints {
+                let hit = match watchpoint.kind {
+                    WatchKind::Write => access.write,
+                    WatchKind::Read => !access.write,
+                    WatchKind::Access => true,
+                };
+                if hit && watchpoint.covers(access.addr) {
+                    return StopReason::Watchpoint {
+                        kind: watchpoint.kind,
+                        addr: access.addr,
+                    };
+                }
+            }
+        }
+        StopReason::Step
+    }
+
+    /// Runs until a breakpoint or watchpoint is hit, or until `should_stop`
+    /// (polled every few thousand instructions) returns true. The instruction
+    /// at the current PC always executes, so resuming from a breakpoint works.
+    pub fn run<T: DebugTarget, F: FnMut() -> bool>(
+        &self,
+        target: &mut T,
+        mut should_stop: F,
+    ) -> StopReason {
+        let mut executed = 0usize;
+        loop {
+            if let reason @ StopReason::Watchpoint { .. } = self.step(target) {
+                return reason;
+            }
+
+            let pc = target.state().pc;
+            if self.breakpoints.contains(&pc) {
+                return StopReason::Breakpoint(pc);
+            }
+
+            executed += 1;
+            if executed.is_multiple_of(POLL_INTERVAL) && should_stop() {
+                return StopReason::Interrupted;
+            }
+        }
+    }
+}
+
+/// Works out which memory locations the instruction `opcode` touched, given
+/// the register pairs before it ran and SP afterwards. Conditional calls and
+/// returns are recognised as taken by the stack pointer having moved.
+fn memory_accesses(
+    opcode: [u8; 3],
+    bc: u16,
+    de: u16,
+    hl: u16,
+    sp: u16,
+    sp_after: u16,
+) -> heapless::Vec<MemoryAccess, 4> {
+    let mut accesses = heapless::Vec::new();
+    let mut add = |addr: u16, len: u16, write: bool| {
+        for i in 0..len {
+            let _ = accesses.push(MemoryAccess {
+                addr: addr.wrapping_add(i),
+                write,
+            });
+        }
+    };
+    let direct = u16::from_le_bytes([opcode[1], opcode[2]]);
+    let stack_moved = sp != sp_after;
+
+    match opcode[0] {
+        // MOV r,M and the register-indirect ALU group
+        0x46 | 0x4E | 0x56 | 0x5E | 0x66 | 0x6E | 0x7E => add(hl, 1, false),
+        0x86 | 0x8E | 0x96 | 0x9E | 0xA6 | 0xAE | 0xB6 | 0xBE => add(hl, 1, false),
+        // MOV M,r and MVI M
+        0x70..=0x75 | 0x77 | 0x36 => add(hl, 1, true),
+        // INR M / DCR M
+        0x34 | 0x35 => {
+            add(hl, 1, false);
+            add(hl, 1, true);
+        }
+        0x0A => add(bc, 1, false),
+        0x1A => add(de, 1, false),
+        0x02 => add(bc, 1, true),
+        0x12 => add(de, 1, true),
+        0x3A => add(direct, 1, false),
+        0x32 => add(direct, 1, true),
+        0x2A => add(direct, 2, false),
+        0x22 => add(direct, 2, true),
+        // PUSH, CALL and RST (conditional calls only if taken)
+        0xC5 | 0xD5 | 0xE5 | 0xF5 | 0xCD => add(sp.wrapping_sub(2), 2, true),
+        op if op & 0xC7 == 0xC7 => add(sp.wrapping_sub(2), 2, true),
+        op if op & 0xC7 == 0xC4 && stack_moved => add(sp.wrapping_sub(2), 2, true),
+        // POP, RET (conditional returns only if taken)
+        0xC1 | 0xD1 | 0xE1 | 0xF1 | 0xC9 => add(sp, 2, false),
+        op if op & 0xC7 == 0xC0 && stack_moved => add(sp, 2, false),
+        // XTHL
+        0xE3 => {
+            add(sp, 2, false);
+            add(sp, 2, true);
+        }
+        _ => {}
+    }
+    accesses
+}
@@ -0,0 +1,443 @@
+use crate::emulator::data_types::{Register, RegisterPair, CYCLE_TABLE};
+use std::fmt;
+
+const REGISTERS: [Register; 8] = [
+    Register::B,
+    Register::C,
+    Register::D,
+    Register::E,
+    Register::H,
+    Register::L,
+    Register::M,
+    Register::A,
+];
+const PAIRS: [RegisterPair; 4] = [
+    RegisterPair::BC,
+    RegisterPair::DE,
+    RegisterPair::HL,
+    RegisterPair::SP,
+];
+const STACK_PAIRS: [RegisterPair; 4] = [
+    RegisterPair::BC,
+    RegisterPair::DE,
+    RegisterPair::HL,
+    RegisterPair::PSW,
+];
+const CONDITIONS: [&str; 8] = ["NZ", "Z", "NC", "C", "PO", "PE", "P", "M"];
+pub(crate) const JUMPS: [&str; 8] = ["JNZ", "JZ", "JNC", "JC", "JPO", "JPE", "JP", "JM"];
+pub(crate) const CALLS: [&str; 8] = ["CNZ", "CZ", "CNC", "CC", "CPO", "CPE", "CP", "CM"];
+pub(crate) const RETURNS: [&str; 8] = ["RNZ", "RZ", "RNC", "RC", "RPO", "RPE", "RP", "RM"];
+pub(crate) const ALU: [&str; 8] = ["ADD", "ADC", "SUB", "SBB", "ANA", "XRA", "ORA", "CMP"];
+pub(crate) const ALU_IMMEDIATE: [&str; 8] =
+    ["ADI", "ACI", "SUI", "SBI", "ANI", "XRI", "ORI", "CPI"];
+pub(crate) const ROTATES: [&str; 8] = ["RLC", "RRC", "RAL", "RAR", "DAA", "CMA", "STC", "CMC"];
+
+// Conditional calls and returns take longer when the branch is taken
+const CALL_TAKEN_CYCLES: u8 = 17;
+const RETURN_TAKEN_CYCLES: u8 = 11;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Register(Register),
+    RegisterPair(RegisterPair),
+    Immediate8(u8),
+    Immediate16(u16),
+    /// A memory or branch address.
+    Address(u16),
+    Port(u8),
+    Restart(u8),
+}
+
+/// How an instruction affects control flow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    Jump,
+    ConditionalJump,
+    Call,
+    ConditionalCall,
+    Return,
+    ConditionalReturn,
+    /// PCHL: the target is only known at run time.
+    IndirectJump,
+    Halt,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Syntax {
+    #[default]
+    Intel,
+    /// Z80-style mnemonics (`LD A,(HL)`, `JP NZ,...`) for the 8080 subset.
+    Zilog,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HexStyle {
+    /// `$1F`
+    #[default]
+    Dollar,
+    /// `1FH`, with a leading zero when the number starts with a letter
+    Suffix,
+    /// `0x1F`
+    CStyle,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FormatOptions {
+    pub syntax: Syntax,
+    pub hex: HexStyle,
+    /// Column the operands start at; 0 separates them with a single space.
+    pub mnemonic_width: usize,
+}
+
+impl HexStyle {
+    pub fn format(&self, value: u16, digits: usize) -> String {
+        match self {
+            HexStyle::Dollar => format!("${:0width$X}", value, width = digits),
+            HexStyle::CStyle => format!("0x{:0width$X}", value, width = digits),
+            HexStyle::Suffix => {
+                let hex = format!("{:0width$X}", value, width = digits);
+                if hex.starts_with(|c: char| c.is_ascii_alphabetic()) {
+                    format!("0{}H", hex)
+                } else {
+                    format!("{}H", hex)
+                }
+            }
+        }
+    }
+}
+
+/// A fully decoded 8080 instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u8,
+    pub bytes: [u8; 3],
+    pub length: u8,
+    /// Intel mnemonic, e.g. `MOV` or `JNZ`.
+    pub mnemonic: &'static str,
+    pub operands: Operands,
+    /// Destination of jumps, calls and RSTs.
+    pub branch_target: Option<u16>,
+    pub flow: Flow,
+    pub cycles: u8,
+    /// Cycle count when a conditional call or return is taken.
+    pub cycles_taken: Option<u8>,
+    /// False for the undocumented aliases (`*NOP`, `*JMP`, `*CALL`, `*RET`).
+    pub documented: bool,
+}
+
+/// Decodes the instruction at `address`. Operand bytes past the end of
+/// `memory` wrap around to the start, as they would in the 64K address space.
+pub fn decode(memory: &[u8], address: u16) -> Instruction {
+    let fetch = |offset: u16| -> u8 {
+        if memory.is_empty() {
+            0
+        } else {
+            memory[address.wrapping_add(offset) as usize % memory.len()]
+        }
+    };
+    let opcode = fetch(0);
+    let imm8 = fetch(1);
+    let imm16 = u16::from_le_bytes([fetch(1), fetch(2)]);
+
+    let mut instruction = Instruction {
+        address,
+        opcode,
+        bytes: [opcode, 0, 0],
+        length: 1,
+        mnemonic: "NOP",
+        operands: Operands::new(),
+        branch_target: None,
+        flow: Flow::Next,
+        cycles: CYCLE_TABLE[opcode as usize],
+        cycles_taken: None,
+        documented: true,
+    };
+
+    let reg_dst = REGISTERS[((opcode >> 3) & 7) as usize];
+    let reg_src = REGISTERS[(opcode & 7) as usize];
+    let pair = PAIRS[((opcode >> 4) & 3) as usize];
+    let condition = ((opcode >> 3) & 7) as usize;
+
+    let (mnemonic, operands): (&'static str, Operands) = match opcode {
+        0x00 => ("NOP", Operands::new()),
+        0x08 | 0x10 | 0x18 | 0x20 | 0x28 | 0x30 | 0x38 => {
+            instruction.documented = false;
+            ("NOP", Operands::new())
+        }
+        op if op & 0xCF == 0x01 => (
+            "LXI",
+            operand_list(&[Operand::RegisterPair(pair), Operand::Immediate16(imm16)]),
+        ),
+        op if op & 0xCF == 0x09 => ("DAD", operand_list(&[Operand::RegisterPair(pair)])),
+        0x02 | 0x12 => ("STAX", operand_list(&[Operand::RegisterPair(pair)])),
+        0x0A | 0x1A => ("LDAX", operand_list(&[Operand::RegisterPair(pair)])),
+        0x22 => ("SHLD", operand_list(&[Operand::Address(imm16)])),
+        0x2A => ("LHLD", operand_list(&[Operand::Address(imm16)])),
+        0x32 => ("STA", operand_list(&[Operand::Address(imm16)])),
+        0x3A => ("LDA", operand_list(&[Operand::Address(imm16)])),
+        op if op & 0xCF == 0x03 => ("INX", operand_list(&[Operand::RegisterPair(pair)])),
+        op if op & 0xCF == 0x0B => ("DCX", operand_list(&[Operand::RegisterPair(pair)])),
+        op if op & 0xC7 == 0x04 => ("INR", operand_list(&[Operand::Register(reg_dst)])),
+        op if op & 0xC7 == 0x05 => ("DCR", operand_list(&[Operand::Register(reg_dst)])),
+        op if op & 0xC7 == 0x06 => (
+            "MVI",
+            operand_list(&[Operand::Register(reg_dst), Operand::Immediate8(imm8)]),
+        ),
+        op if op & 0xC7 == 0x07 => (ROTATES[condition], Operands::new()),
+        0x76 => {
+            instruction.flow = Flow::Halt;
+            ("HLT", Operands::new())
+        }
+        0x40..=0x7F => (
+            "MOV",
+            operand_list(&[Operand::Register(reg_dst), Operand::Register(reg_src)]),
+        ),
+        0x80..=0xBF => (ALU[condition], operand_list(&[Operand::Register(reg_src)])),
+        op if op & 0xC7 == 0xC0 => {
+            instruction.flow = Flow::ConditionalReturn;
+            instruction.cycles_taken = Some(RETURN_TAKEN_CYCLES);
+            (RETURNS[condition], Operands::new())
+        }
+        0xC9 | 0xD9 => {
+            instruction.documented = opcode == 0xC9;
+            instruction.flow = Flow::Return;
+            ("RET", Operands::new())
+        }
+        0xE9 => {
+            instruction.flow = Flow::IndirectJump;
+            ("PCHL", Operands::new())
+        }
+        0xF9 => ("SPHL", Operands::new()),
+        op if op & 0xCF == 0xC1 => (
+            "POP",
+            operand_list(&[Operand::RegisterPair(STACK_PAIRS[((op >> 4) & 3) as usize])]),
+        ),
+        op if op & 0xCF == 0xC5 => (
+            "PUSH",
+            operand_list(&[Operand::RegisterPair(STACK_PAIRS[((op >> 4) & 3) as usize])]),
+        ),
+        op if op & 0xC7 == 0xC2 => {
+            instruction.flow = Flow::ConditionalJump;
+            instruction.branch_target = Some(imm16);
+            (JUMPS[condition], operand_list(&[Operand::Address(imm16)]))
+        }
+        0xC3 | 0xCB => {
+            instruction.documented = opcode == 0xC3;
+            instruction.flow = Flow::Jump;
+            instruction.branch_target = Some(imm16);
+            ("JMP", operand_list(&[Operand::Address(imm16)]))
+        }
+        0xD3 => ("OUT", operand_list(&[Operand::Port(imm8)])),
+        0xDB => ("IN", operand_list(&[Operand::Port(imm8)])),
+        0xE3 => ("XTHL", Operands::new()),
+        0xEB => ("XCHG", Operands::new()),
+        0xF3 => ("DI", Operands::new()),
+        0xFB => ("EI", Operands::new()),
+        op if op & 0xC7 == 0xC4 => {
+            instruction.flow = Flow::ConditionalCall;
+            instruction.branch_target = Some(imm16);
+            instruction.cycles_taken = Some(CALL_TAKEN_CYCLES);
+            (CALLS[condition], operand_list(&[Operand::Address(imm16)]))
+        }
+        0xCD | 0xDD | 0xED | 0xFD => {
+            instruction.documented = opcode == 0xCD;
+            instruction.flow = Flow::Call;
+            instruction.branch_target = Some(imm16);
+            ("CALL", operand_list(&[Operand::Address(imm16)]))
+        }
+        op if op & 0xC7 == 0xC6 => (
+            ALU_IMMEDIATE[condition],
+            operand_list(&[Operand::Immediate8(imm8)]),
+        ),
+        // RST n
+        _ => {
+            instruction.flow = Flow::Call;
+            instruction.branch_target = Some((condition * 8) as u16);
+            ("RST", operand_list(&[Operand::Restart(condition as u8)]))
+        }
+    };
+
+    instruction.mnemonic = mnemonic;
+    instruction.length = match operands.last() {
+        Some(Operand::Immediate8(_)) | Some(Operand::Port(_)) => 2,
+        Some(Operand::Immediate16(_)) | Some(Operand::Address(_)) => 3,
+        _ => 1,
+    };
+    instruction.operands = operands;
+    for i in 1..instruction.length {
+        instruction.bytes[i as usize] = fetch(i as u16);
+    }
+    instruction
+}
+
+type Operands = heapless::Vec<Operand, 2>;
+
+fn operand_list(operands: &[Operand]) -> Operands {
+    operands.iter().copied().collect()
+}
+
+impl Instruction {
+    /// The raw bytes making up this instruction.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.length as usize]
+    }
+
+    /// Address of the instruction that follows this one.
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.length as u16)
+    }
+
+    pub fn format(&self, options: &FormatOptions) -> String {
+        self.format_with(options, |_| None)
+    }
+
+    /// Formats the instruction, asking `name_of` for a label to print in
+    /// place of each address operand.
+    pub fn format_with<F: Fn(u16) -> Option<String>>(
+        &self,
+        options: &FormatOptions,
+        name_of: F,
+    ) -> String {
+        let address = |value: u16| name_of(value).unwrap_or_else(|| options.hex.format(value, 4));
+        let (mnemonic, operands) = match options.syntax {
+            Syntax::Intel => self.intel_parts(options.hex, &address),
+            Syntax::Zilog => self.zilog_parts(options.hex, &address),
+        };
+        if operands.is_empty() {
+            mnemonic.to_string()
+        } else {
+            format!(
+                "{:<width$} {}",
+                mnemonic,
+                operands,
+                width = options.mnemonic_width.saturating_sub(1)
+            )
+        }
+    }
+
+    fn intel_parts(
+        &self,
+        hex: HexStyle,
+        address: &dyn Fn(u16) -> String,
+    ) -> (&'static str, String) {
+        let operands: Vec<String> = self
+            .operands
+            .iter()
+            .map(|operand| match *operand {
+                Operand::Register(register) => format!("{:?}", register),
+                Operand::RegisterPair(pair) => intel_pair(pair).to_string(),
+                Operand::Immediate8(value) | Operand::Port(value) => hex.format(value as u16, 2),
+                Operand::Immediate16(value) => hex.format(value, 4),
+                Operand::Address(value) => address(value),
+                Operand::Restart(n) => n.to_string(),
+            })
+            .collect();
+        (self.mnemonic, operands.join(","))
+    }
+
+    fn zilog_parts(
+        &self,
+        hex: HexStyle,
+        address: &dyn Fn(u16) -> String,
+    ) -> (&'static str, String) {
+        let op = self.opcode;
+        let condition = CONDITIONS[((op >> 3) & 7) as usize];
+        let register = |operand: usize| match self.operands.get(operand) {
+            Some(Operand::Register(Register::M)) => "(HL)".to_string(),
+            Some(Operand::Register(register)) => format!("{:?}", register),
+            _ => String::new(),
+        };
+        let pair = || match self.operands.first() {
+            Some(Operand::RegisterPair(pair)) => zilog_pair(*pair),
+            _ => "",
+        };
+        let imm8 = hex.format(self.bytes[1] as u16, 2);
+        let imm16 = u16::from_le_bytes([self.bytes[1], self.bytes[2]]);
+
+        match self.mnemonic {
+            "NOP" | "DAA" | "DI" | "EI" | "RET" => (self.mnemonic, String::new()),
+            "LXI" => ("LD", format!("{},{}", pair(), hex.format(imm16, 4))),
+            "DAD" => ("ADD", format!("HL,{}", pair())),
+            "STAX" => ("LD", format!("({}),A", pair())),
+            "LDAX" => ("LD", format!("A,({})", pair())),
+            "SHLD" => ("LD", format!("({}),HL", address(imm16))),
+            "LHLD" => ("LD", format!("HL,({})", address(imm16))),
+            "STA" => ("LD", format!("({}),A", address(imm16))),
+            "LDA" => ("LD", format!("A,({})", address(imm16))),
+            "INX" => ("INC", pair().to_string()),
+            "DCX" => ("DEC", pair().to_string()),
+            "INR" => ("INC", register(0)),
+            "DCR" => ("DEC", register(0)),
+            "MVI" => ("LD", format!("{},{}", register(0), imm8)),
+            "RLC" => ("RLCA", String::new()),
+            "RRC" => ("RRCA", String::new()),
+            "RAL" => ("RLA", String::new()),
+            "RAR" => ("RRA", String::new()),
+            "CMA" => ("CPL", String::new()),
+            "STC" => ("SCF", String::new()),
+            "CMC" => ("CCF", String::new()),
+            "HLT" => ("HALT", String::new()),
+            "MOV" => ("LD", format!("{},{}", register(0), register(1))),
+            "ADD" => ("ADD", format!("A,{}", register(0))),
+            "ADC" => ("ADC", format!("A,{}", register(0))),
+            "SUB" => ("SUB", register(0)),
+            "SBB" => ("SBC", format!("A,{}", register(0))),
+            "ANA" => ("AND", register(0)),
+            "XRA" => ("XOR", register(0)),
+            "ORA" => ("OR", register(0)),
+            "CMP" => ("CP", register(0)),
+            "ADI" => ("ADD", format!("A,{}", imm8)),
+            "ACI" => ("ADC", format!("A,{}", imm8)),
+            "SUI" => ("SUB", imm8),
+            "SBI" => ("SBC", format!("A,{}", imm8)),
+            "ANI" => ("AND", imm8),
+            "XRI" => ("XOR", imm8),
+            "ORI" => ("OR", imm8),
+            "CPI" => ("CP", imm8),
+            "PCHL" => ("JP", "(HL)".to_string()),
+            "SPHL" => ("LD", "SP,HL".to_string()),
+            "POP" => ("POP", pair().to_string()),
+            "PUSH" => ("PUSH", pair().to_string()),
+            "JMP" => ("JP", address(imm16)),
+            "CALL" => ("CALL", address(imm16)),
+            "OUT" => ("OUT", format!("({}),A", imm8)),
+            "IN" => ("IN", format!("A,({})", imm8)),
+            "XTHL" => ("EX", "(SP),HL".to_string()),
+            "XCHG" => ("EX", "DE,HL".to_string()),
+            "RST" => ("RST", hex.format(((op >> 3) & 7) as u16 * 8, 2)),
+            _ => match self.flow {
+                Flow::ConditionalReturn => ("RET", condition.to_string()),
+                Flow::ConditionalJump => ("JP", format!("{},{}", condition, address(imm16))),
+                _ => ("CALL", format!("{},{}", condition, address(imm16))),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.format(&FormatOptions::default()))
+    }
+}
+
+fn intel_pair(pair: RegisterPair) -> &'static str {
+    match pair {
+        RegisterPair::BC => "B",
+        RegisterPair::DE => "D",
+        RegisterPair::HL => "H",
+        RegisterPair::SP => "SP",
+        RegisterPair::PSW => "PSW",
+    }
+}
+
+fn zilog_pair(pair: RegisterPair) -> &'static str {
+    match pair {
+        RegisterPair::BC => "BC",
+        RegisterPair::DE => "DE",
+        RegisterPair::HL => "HL",
+        RegisterPair::SP => "SP",
+        RegisterPair::PSW => "AF",
+    }
+}
@@ -0,0 +1,69 @@
+pub mod instruction;
+pub mod recursive;
+
+pub use instruction::{decode, FormatOptions, HexStyle, Instruction, Operand, Syntax};
+pub use recursive::{DisassemblyProfile, RecursiveDisassembler};
+
+pub struct Disassembler;
+
+impl Disassembler {
+    pub fn decode(buffer: &[u8], pc: usize) -> Instruction {
+        decode(buffer, pc as u16)
+    }
+
+    /// Renders one listing line: address, raw bytes and the formatted instruction.
+    pub fn format_line(instruction: &Instruction, options: &FormatOptions) -> String {
+        Self::format_line_with(instruction, options, |_| None)
+    }
+
+    /// Like `format_line`, but shows named addresses by name.
+    pub fn format_line_with<F: Fn(u16) -> Option<String>>(
+        instruction: &Instruction,
+        options: &FormatOptions,
+        name_of: F,
+    ) -> String {
+        let bytes: Vec<String> = instruction
+            .bytes()
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect();
+        format!(
+            "{:04X}  {:<8}  {}",
+            instruction.address,
+            bytes.join(" "),
+            instruction.format_with(options, name_of)
+        )
+    }
+
+    pub fn disassemble_8080_op(buffer: &[u8], pc: usize) -> usize {
+        let instruction = Self::decode(buffer, pc);
+        let options = FormatOptions {
+            mnemonic_width: 7,
+            ..FormatOptions::default()
+        };
+        println!("{:04X} {}", pc, instruction.format(&options));
+
+        instruction.length as usize // Return the number of bytes used by the instruction
+    }
+
+    /// Decodes `buffer` linearly from the start, one instruction after another.
+    pub fn instructions(buffer: &[u8]) -> Vec<Instruction> {
+        let mut instructions = Vec::new();
+        let mut pc = 0;
+        while pc < buffer.len() {
+            let instruction = Self::decode(buffer, pc);
+            pc += instruction.length as usize;
+            instructions.push(instruction);
+        }
+        instructions
+    }
+
+    pub fn disassemble(buffer: &[u8]) {
+        let mut pc = 0;
+
+        // Disassemble the entire buffer
+        while pc < buffer.len() {
+            pc += Self::disassemble_8080_op(buffer, pc);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Flow-following disassembly: starts at the entry points, follows jumps and
+//! calls, and treats every byte it never reaches as data.
+
+use super::instruction::{decode, Flow, FormatOptions, HexStyle, Instruction, Operand};
+use crate::symbols::SymbolTable;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+const DATA_BYTES_PER_LINE: usize = 8;
+const LABEL_COLUMN: usize = 8;
+const COMMENT_COLUMN: usize = 32;
+
+/// Where a machine's program lives and where the CPU can start executing it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassemblyProfile {
+    pub name: &'static str,
+    pub rom: Range<usize>,
+    /// Reset plus the RST vectors the hardware raises.
+    pub entry_points: &'static [u16],
+}
+
+impl DisassemblyProfile {
+    /// Space Invaders: 8K of ROM, reset plus the RST 1 (mid-screen) and
+    /// RST 2 (VBLANK) interrupt vectors.
+    pub const SPACE_INVADERS: DisassemblyProfile = DisassemblyProfile {
+        name: "Space Invaders",
+        rom: 0x0000..0x2000,
+        entry_points: &[0x0000, 0x0008, 0x0010],
+    };
+
+    /// Gun Fight: 4K of ROM, with the same vectors.
+    pub const GUN_FIGHT: DisassemblyProfile = DisassemblyProfile {
+        name: "Gun Fight",
+        rom: 0x0000..0x1000,
+        entry_points: &[0x0000, 0x0008, 0x0010],
+    };
+
+    /// Space Invaders Part II: the 8K of Space Invaders plus 2K at 0x4000.
+    /// The RAM between is traced over as data.
+    pub const SPACE_INVADERS_PART_II: DisassemblyProfile = DisassemblyProfile {
+        name: "Space Invaders Part II",
+        rom: 0x0000..0x4800,
+        entry_points: &[0x0000, 0x0008, 0x0010],
+    };
+
+    pub const SPACE_INVADERS_DELUXE: DisassemblyProfile = DisassemblyProfile {
+        name: "Space Invaders Deluxe",
+        rom: 0x0000..0x4800,
+        entry_points: &[0x0000, 0x0008, 0x0010],
+    };
+
+    pub const LUNAR_RESCUE: DisassemblyProfile = DisassemblyProfile {
+        name: "Lunar Rescue",
+        rom: 0x0000..0x5000,
+        entry_points: &[0x0000, 0x0008, 0x0010],
+    };
+
+    pub const BALLOON_BOMBER: DisassemblyProfile = DisassemblyProfile {
+        name: "Balloon Bomber",
+        rom: 0x0000..0x4800,
+        entry_points: &[0x0000, 0x0008, 0x0010],
+    };
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ByteKind {
+    Data,
+    /// First byte of an instruction.
+    Code,
+    /// Operand byte of the instruction that starts before it.
+    Operand,
+}
+
+pub struct RecursiveDisassembler<'a> {
+    memory: &'a [u8],
+    region: Range<usize>,
+    kinds: Vec<ByteKind>,
+    labels: BTreeMap<u16, String>,
+    /// Named addresses outside the region, emitted as `EQU`s.
+    externals: BTreeMap<u16, String>,
+}
+
+impl<'a> RecursiveDisassembler<'a> {
+    pub fn new(memory: &'a [u8], region: Range<usize>) -> Self {
+        let region = region.start..region.end.min(memory.len());
+        RecursiveDisassembler {
+            memory,
+            kinds: vec![ByteKind::Data; region.len()],
+            region,
+            labels: BTreeMap::new(),
+            externals: BTreeMap::new(),
+        }
+    }
+
+    /// Disassembles the ROM region of a profile from its entry points.
+    pub fn from_profile(memory: &'a [u8], profile: &DisassemblyProfile) -> Self {
+        let mut disassembler = Self::new(memory, profile.rom.clone());
+        disassembler.trace(profile.entry_points);
+        disassembler
+    }
+
+    /// Follows execution from each entry point, marking reached bytes as code.
+    pub fn trace(&mut self, entry_points: &[u16]) {
+        let mut pending: Vec<u16> = entry_points.to_vec();
+        for &entry in entry_points {
+            if self.contains(entry) {
+                self.add_label(entry);
+            }
+        }
+
+        while let Some(start) = pending.pop() {
+            let mut addr = start;
+            loop {
+                if !self.contains(addr) || self.kind(addr) != ByteKind::Data {
+                    break;
+                }
+                let instruction = decode(self.memory, addr);
+                if !instruction.documented || !self.fits(&instruction) {
+                    break;
+                }
+                self.mark(&instruction);
+
+                if let Some(target) = instruction.branch_target {
+                    if self.contains(target) {
+                        self.add_label(target);
+                        pending.push(target);
+                    }
+                }
+                if let Some(target) = data_reference(&instruction) {
+                    if self.contains(target) {
+                        self.add_label(target);
+                    }
+                }
+
+                match instruction.flow {
+                    Flow::Jump | Flow::Return | Flow::IndirectJump => break,
+                    _ => addr = instruction.next_address(),
+                }
+            }
+        }
+
+        // Labels that landed inside an instruction cannot be emitted
+        let kinds = &self.kinds;
+        let region_start = self.region.start;
+        self.labels
+            .retain(|&addr, _| kinds[addr as usize - region_start] != ByteKind::Operand);
+    }
+
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.contains(addr) && self.kind(addr) == ByteKind::Code
+    }
+
+    pub fn labels(&self) -> &BTreeMap<u16, String> {
+        &self.labels
+    }
+
+    /// Gives `addr` a name, replacing the generated `Lxxxx` one.
+    pub fn set_label(&mut self, addr: u16, name: &str) {
+        if self.contains(addr) {
+            self.labels.insert(addr, name.to_string());
+        }
+    }
+
+    /// Names code and data from a symbol table. Symbols outside the region
+    /// (RAM variables, I/O buffers) are defined with `EQU` in the listing.
+    pub fn apply_symbols(&mut self, symbols: &SymbolTable) {
+        for (addr, name) in symbols.iter() {
+            if self.contains(addr) {
+                if self.kind(addr) != ByteKind::Operand {
+                    self.labels.insert(addr, name.to_string());
+                }
+            } else {
+                self.externals.insert(addr, name.to_string());
+            }
+        }
+    }
+
+    /// Renders a listing that assembles back to the original bytes.
+    pub fn listing(&self, title: &str) -> String {
+        let options = FormatOptions {
+            hex: HexStyle::Suffix,
+            mnemonic_width: LABEL_COLUMN,
+            ..FormatOptions::default()
+        };
+        let name_of = |addr: u16| {
+            self.labels
+                .get(&addr)
+                .or_else(|| self.externals.get(&addr))
+                .cloned()
+        };
+
+        let mut output = String::new();
+        let _ = writeln!(output, "; {}", title);
+        for (&addr, name) in &self.externals {
+            let _ = writeln!(
+                output,
+                "{:<width$}{:<width$}{}",
+                format!("{} ", name),
+                "EQU",
+                options.hex.format(addr, 4),
+                width = LABEL_COLUMN
+            );
+        }
+        let _ = writeln!(
+            output,
+            "{:indent$}{:<width$}{}",
+            "",
+            "ORG",
+            options.hex.format(self.region.start as u16, 4),
+            indent = LABEL_COLUMN,
+            width = LABEL_COLUMN
+        );
+
+        let mut addr = self.region.start;
+        while addr < self.region.end {
+            let current = addr as u16;
+            if let Some(label) = self.labels.get(&current) {
+                let _ = writeln!(output, "\n{}:", label);
+            }
+
+            let (text, length) = if self.kind(current) == ByteKind::Code {
+                let instruction = decode(self.memory, current);
+                (
+                    instruction.format_with(&options, name_of),
+                    instruction.length as usize,
+                )
+            } else {
+                let length = self.data_run(addr);
+                let bytes: Vec<String> = self.memory[addr..addr + length]
+                    .iter()
+                    .map(|&b| options.hex.format(b as u16, 2))
+                    .collect();
+                (
+                    format!("{:<width$}{}", "DB", bytes.join(","), width = LABEL_COLUMN),
+                    length,
+                )
+            };
+
+            let line = format!("{:indent$}{}", "", text, indent = LABEL_COLUMN);
+            let _ = writeln!(
+                output,
+                "{:<width$}; {:04X}",
+                line,
+                addr,
+                width = COMMENT_COLUMN.max(line.len() + 1)
+            );
+            addr += length;
+        }
+        let _ = writeln!(output, "\n{:indent$}END", "", indent = LABEL_COLUMN);
+        output
+    }
+
+    /// Number of data bytes from `addr` that fit on one DB line, stopping at
+    /// the next code byte or label.
+    fn data_run(&self, addr: usize) -> usize {
+        let mut length = 1;
+        while length < DATA_BYTES_PER_LINE && addr + length < self.region.end {
+            let next = (addr + length) as u16;
+            if self.kind(next) != ByteKind::Data || self.labels.contains_key(&next) {
+                break;
+            }
+            length += 1;
+        }
+        length
+    }
+
+    fn contains(&self, addr: u16) -> bool {
+        self.region.contains(&(addr as usize))
+    }
+
+    fn kind(&self, addr: u16) -> ByteKind {
+        self.kinds[addr as usize - self.region.start]
+    }
+
+    /// True when every byte of the instruction is inside the region and not
+    /// already claimed by another instruction.
+    fn fits(&self, instruction: &Instruction) -> bool {
+        (0..instruction.length as u16).all(|i| {
+            let addr = instruction.address.wrapping_add(i);
+            self.contains(addr) && self.kind(addr) == ByteKind::Data
+        })
+    }
+
+    fn mark(&mut self, instruction: &Instruction) {
+        let start = instruction.address as usize - self.region.start;
+        self.kinds[start] = ByteKind::Code;
+        for i in 1..instruction.length as usize {
+            self.kinds[start + i] = ByteKind::Operand;
+        }
+    }
+
+    fn add_label(&mut self, addr: u16) {
+        self.labels
+            .entry(addr)
+            .or_insert_with(|| format!("L{:04X}", addr));
+    }
+}
+
+/// Memory address read or written directly by LDA/STA/LHLD/SHLD.
+fn data_reference(instruction: &Instruction) -> Option<u16> {
+    match instruction.operands.first() {
+        Some(Operand::Address(addr)) if instruction.branch_target.is_none() => Some(*addr),
+        _ => None,
+    }
+}
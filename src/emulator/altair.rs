@@ -0,0 +1,452 @@
+#![cfg(not(feature = "wasm"))]
+//! The MITS Altair 8800.
+//!
+//! [`Altair`] is an 8080 with a configurable amount of RAM from address 0,
+//! a front panel and two serial boards, both bridged to one [`Console`]:
+//!
+//! | Port | Board | Use |
+//! |------|-------|-----|
+//! | 0x00 | 88-SIO | Status: bit 0 is low when a key is waiting, bit 7 low when it can send |
+//! | 0x01 | 88-SIO | Data |
+//! | 0x08-0x0A | 88-DCDD | Floppy disks, see [`Dcdd`] |
+//! | 0x10 | 88-2SIO | 6850 ACIA status: bit 0 set when a key is waiting, bit 1 when it can send |
+//! | 0x11 | 88-2SIO | Data |
+//! | 0xFF | Front panel | Sense switches, the high byte of the switch register |
+//!
+//! Addresses above the RAM read 0xFF and ignore writes, so programs that
+//! size memory find what a real machine would have. ROMs, such as the disk
+//! boot loader at 0xFF00, ignore writes too. Parity bits are stripped from
+//! characters sent to the terminal.
+//!
+//! Programs come from memory images, MITS paper tapes (see [`parse_tape`])
+//! or disks booted by a ROM.
+
+use super::console::{Console, StdConsole};
+use super::cpm::Exit;
+use super::cpu::emulate_8080_op;
+use super::data_types::State8080;
+use super::dcdd::{self, Dcdd};
+use super::disk::DiskImage;
+use super::io::IoBus;
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+
+pub const SIO_STATUS: u8 = 0x00;
+pub const SIO_DATA: u8 = 0x01;
+pub const SIO2_STATUS: u8 = 0x10;
+pub const SIO2_DATA: u8 = 0x11;
+pub const SENSE_SWITCHES: u8 = 0xFF;
+
+/// What the front panel lights show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PanelLights {
+    /// A15 to A0: the address being examined, or the next to execute.
+    pub address: u16,
+    /// D7 to D0: the byte at that address.
+    pub data: u8,
+    pub inte: bool,
+    pub hlta: bool,
+    /// Lit while the machine is stopped.
+    pub wait: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TapeError {
+    /// No checksummed records were found.
+    NoRecords,
+    /// A record's checksum did not match, at this offset in the tape.
+    Checksum { offset: usize },
+    /// The tape ends inside the record starting at this offset.
+    Truncated { offset: usize },
+}
+
+impl fmt::Display for TapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TapeError::NoRecords => write!(f, "no load records found on the tape"),
+            TapeError::Checksum { offset } => {
+                write!(f, "bad checksum in the record at byte {}", offset)
+            }
+            TapeError::Truncated { offset } => {
+                write!(f, "tape ends inside the record at byte {}", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TapeError {}
+
+/// The program a paper tape carries.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tape {
+    /// Load address and bytes of each record, in tape order.
+    pub records: Vec<(u16, Vec<u8>)>,
+    /// Where to start, from the end-of-file record.
+    pub start: Option<u16>,
+}
+
+const LOAD_RECORD: u8 = 0x3C;
+const END_RECORD: u8 = 0x78;
+
+/// Reads a tape in the MITS checksum loader format, as Altair BASIC was
+/// punched. A load record is 0x3C, a byte count, the load address (low byte
+/// first), the bytes and a checksum: the sum of the address and data bytes.
+/// An end record is 0x78 and the start address. Leader and the bootstrap
+/// loader punched ahead of the records are skipped: loading starts at the
+/// first load record whose checksum holds.
+pub fn parse_tape(tape: &[u8]) -> Result<Tape, TapeError> {
+    let first = (0..tape.len())
+        .find(|&offset| tape[offset] == LOAD_RECORD && load_record(tape, offset).is_ok())
+        .ok_or(TapeError::NoRecords)?;
+
+    let mut result = Tape::default();
+    let mut offset = first;
+    while offset < tape.len() {
+        match tape[offset] {
+            LOAD_RECORD => {
+                let (address, data) = load_record(tape, offset)?;
+                offset += data.len() + 5;
+                result.records.push((address, data));
+            }
+            END_RECORD => {
+                let bytes = tape
+                    .get(offset + 1..offset + 3)
+                    .ok_or(TapeError::Truncated { offset })?;
+                result.start = Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+                break;
+            }
+            // Blank tape between records
+            _ => offset += 1,
+        }
+    }
+    Ok(result)
+}
+
+fn load_record(tape: &[u8], offset: usize) -> Result<(u16, Vec<u8>), TapeError> {
+    let count = *tape
+        .get(offset + 1)
+        .ok_or(TapeError::Truncated { offset })? as usize;
+    let record = tape
+        .get(offset + 2..offset + 5 + count)
+        .ok_or(TapeError::Truncated { offset })?;
+    let (body, checksum) = record.split_at(count + 2);
+    let sum = body.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    if sum != checksum[0] {
+        return Err(TapeError::Checksum { offset });
+    }
+    Ok((u16::from_le_bytes([body[0], body[1]]), body[2..].to_vec()))
+}
+
+/// The serial boards, disk controller and sense switches.
+struct AltairBus<C> {
+    console: C,
+    dcdd: Dcdd,
+    sense: u8,
+    input_ended: bool,
+}
+
+impl<C: Console> AltairBus<C> {
+    fn key_waiting(&mut self) -> bool {
+        let ready = self.console.ready();
+        if !ready && self.console.at_end() {
+            self.input_ended = true;
+        }
+        ready
+    }
+
+    fn read_key(&mut self) -> u8 {
+        self.console.read().unwrap_or_else(|| {
+            self.input_ended = true;
+            0
+        })
+    }
+}
+
+impl<C: Console + Send + 'static> IoBus for AltairBus<C> {
+    fn input(&mut self, port: u8) -> u8 {
+        match port {
+            SIO_STATUS => {
+                if self.key_waiting() {
+                    0x00
+                } else {
+                    0x01
+                }
+            }
+            SIO2_STATUS => {
+                if self.key_waiting() {
+                    0x03
+                } else {
+                    0x02
+                }
+            }
+            SIO_DATA | SIO2_DATA => self.read_key(),
+            dcdd::STATUS..=dcdd::DATA => self.dcdd.input(port),
+            SENSE_SWITCHES => self.sense,
+            _ => 0xFF,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            SIO_DATA | SIO2_DATA => self.console.write(value & 0x7F),
+            dcdd::STATUS..=dcdd::DATA => self.dcdd.output(port, value),
+            // Writes to the 2SIO's control register configure the ACIA,
+            // which the console does not need
+            _ => {}
+        }
+    }
+}
+
+pub struct Altair<C: Console + Send + 'static = StdConsole> {
+    state: State8080,
+    ram: usize,
+    roms: Vec<(u16, Vec<u8>)>,
+    switches: u16,
+    running: bool,
+    halted: bool,
+    _console: PhantomData<C>,
+}
+
+impl<C: Console + Send + 'static> Altair<C> {
+    /// A stopped machine with `ram` bytes of memory from address 0, up to
+    /// 64K.
+    pub fn new(ram: usize, console: C) -> Self {
+        let ram = ram.min(0x10000);
+        let mut state = State8080::default();
+        state.history.set_capacity(0);
+        state.memory[ram..].fill(0xFF);
+        state.io = Some(Box::new(AltairBus {
+            console,
+            dcdd: Dcdd::new(),
+            sense: 0,
+            input_ended: false,
+        }));
+        Altair {
+            state,
+            ram,
+            roms: Vec::new(),
+            switches: 0,
+            running: false,
+            halted: false,
+            _console: PhantomData,
+        }
+    }
+
+    fn bus(&self) -> &AltairBus<C> {
+        let io: &dyn Any = self.state.io.as_deref().expect("machine has a bus");
+        io.downcast_ref().expect("machine has an Altair bus")
+    }
+
+    fn bus_mut(&mut self) -> &mut AltairBus<C> {
+        let io: &mut dyn Any = self.state.io.as_deref_mut().expect("machine has a bus");
+        io.downcast_mut().expect("machine has an Altair bus")
+    }
+
+    pub fn ram(&self) -> usize {
+        self.ram
+    }
+
+    /// Copies `image` into memory at `address`, as far as the RAM goes.
+    pub fn load(&mut self, address: u16, image: &[u8]) {
+        let start = address as usize;
+        let end = (start + image.len()).min(self.ram);
+        if start < end {
+            self.state.memory[start..end].copy_from_slice(&image[..end - start]);
+        }
+    }
+
+    /// Installs a ROM at `address`, such as the MITS disk boot loader at
+    /// 0xFF00. Programs can read it but not write it.
+    pub fn load_rom(&mut self, address: u16, rom: &[u8]) {
+        let start = address as usize;
+        let rom = &rom[..rom.len().min(0x10000 - start)];
+        self.state.memory[start..start + rom.len()].copy_from_slice(rom);
+        self.roms.push((address, rom.to_vec()));
+    }
+
+    /// What a write to `address` must leave behind, for addresses that are
+    /// ROM or not there at all.
+    fn fixed_byte(&self, address: u16) -> Option<u8> {
+        for (start, rom) in &self.roms {
+            if let Some(offset) = (address as usize).checked_sub(*start as usize) {
+                if let Some(&byte) = rom.get(offset) {
+                    return Some(byte);
+                }
+            }
+        }
+        (address as usize >= self.ram).then_some(0xFF)
+    }
+
+    /// Puts `image`, a MITS 8" disk, in drive `drive` (0 to 15) of the
+    /// 88-DCDD, returning the disk that was there.
+    pub fn mount(&mut self, drive: usize, image: DiskImage) -> Option<DiskImage> {
+        self.bus_mut().dcdd.mount(drive, image)
+    }
+
+    /// Takes a disk out, finishing any sector being written.
+    pub fn eject(&mut self, drive: usize) -> Option<DiskImage> {
+        self.bus_mut().dcdd.eject(drive)
+    }
+
+    pub fn disk(&self, drive: usize) -> Option<&DiskImage> {
+        self.bus().dcdd.disk(drive)
+    }
+
+    /// Loads every record of a paper tape and, if it names one, sets the
+    /// program counter to its start address.
+    pub fn load_tape(&mut self, tape: &Tape) {
+        for (address, data) in &tape.records {
+            self.load(*address, data);
+        }
+        if let Some(start) = tape.start {
+            self.state.pc = start;
+        }
+    }
+
+    /// The sixteen switches along the bottom of the panel. The upper eight
+    /// double as the sense switches programs read from port 0xFF.
+    pub fn switches(&self) -> u16 {
+        self.switches
+    }
+
+    pub fn set_switches(&mut self, switches: u16) {
+        self.switches = switches;
+        self.bus_mut().sense = (switches >> 8) as u8;
+    }
+
+    /// EXAMINE: jumps to the address on the switches.
+    pub fn examine(&mut self) {
+        self.state.pc = self.switches;
+    }
+
+    /// EXAMINE NEXT: steps to the following address.
+    pub fn examine_next(&mut self) {
+        self.state.pc = self.state.pc.wrapping_add(1);
+    }
+
+    /// DEPOSIT: stores the low eight switches at the current address.
+    pub fn deposit(&mut self) {
+        let address = self.state.pc as usize;
+        if address < self.ram {
+            self.state.memory[address] = self.switches as u8;
+        }
+    }
+
+    /// DEPOSIT NEXT: steps to the following address and deposits there.
+    pub fn deposit_next(&mut self) {
+        self.examine_next();
+        self.deposit();
+    }
+
+    /// RESET: clears the program counter and interrupt enable. Memory is
+    /// kept.
+    pub fn reset(&mut self) {
+        self.state.pc = 0;
+        self.state.int_enable = false;
+        self.halted = false;
+    }
+
+    /// RUN.
+    pub fn start(&mut self) {
+        self.running = true;
+        self.halted = false;
+    }
+
+    /// STOP.
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn lights(&self) -> PanelLights {
+        PanelLights {
+            address: self.state.pc,
+            data: self.state.memory[self.state.pc as usize],
+            inte: self.state.int_enable,
+            hlta: self.halted,
+            wait: !self.running,
+        }
+    }
+
+    /// RUN, then runs until the CPU halts or reads or polls the terminal
+    /// after its input has ended.
+    pub fn run(&mut self) -> Exit {
+        self.start();
+        loop {
+            if let Some(exit) = self.step() {
+                return exit;
+            }
+        }
+    }
+
+    /// RUN for at most `limit` instructions, returning None if the machine
+    /// is still going.
+    pub fn run_for(&mut self, limit: u64) -> Option<Exit> {
+        self.start();
+        (0..limit).find_map(|_| self.step())
+    }
+
+    /// Executes one instruction, as SINGLE STEP does. A `HLT` stops the
+    /// machine with HLTA lit.
+    pub fn step(&mut self) -> Option<Exit> {
+        let state = &self.state;
+        let pc = state.pc;
+        if state.memory[pc as usize] == 0x76 {
+            self.halted = true;
+            self.running = false;
+            return Some(Exit::Halted { pc });
+        }
+        // Every address the instruction might store to
+        let direct = u16::from_le_bytes([
+            state.memory[pc.wrapping_add(1) as usize],
+            state.memory[pc.wrapping_add(2) as usize],
+        ]);
+        let pair = |high: u8, low: u8| u16::from_le_bytes([low, high]);
+        let sp = state.sp;
+        let targets = [
+            pair(state.h, state.l),
+            pair(state.b, state.c),
+            pair(state.d, state.e),
+            sp.wrapping_sub(2),
+            sp.wrapping_sub(1),
+            sp,
+            sp.wrapping_add(1),
+            direct,
+            direct.wrapping_add(1),
+        ];
+        emulate_8080_op(&mut self.state);
+        for target in targets {
+            if let Some(byte) = self.fixed_byte(target) {
+                self.state.memory[target as usize] = byte;
+            }
+        }
+
+        let bus = self.bus_mut();
+        if bus.input_ended {
+            bus.input_ended = false;
+            self.running = false;
+            return Some(Exit::EndOfInput);
+        }
+        None
+    }
+
+    pub fn console(&self) -> &C {
+        &self.bus().console
+    }
+
+    pub fn console_mut(&mut self) -> &mut C {
+        &mut self.bus_mut().console
+    }
+
+    pub fn get_state(&self) -> &State8080 {
+        &self.state
+    }
+
+    pub fn get_state_mut(&mut self) -> &mut State8080 {
+        &mut self.state
+    }
+}
@@ -0,0 +1,118 @@
+#![cfg(not(feature = "wasm"))]
+//! Runs many headless Space Invaders machines side by side.
+//!
+//! Each call to [`BatchRunner::step`] gives every machine its input port
+//! value, runs one video frame on all of them across a pool of scoped
+//! threads, and hands back borrowed views of their video and work RAM.
+
+use super::machine::{Engine, SpaceInvadersMachine};
+use std::num::NonZeroUsize;
+use std::thread;
+
+const WORK_RAM: std::ops::Range<usize> = 0x2000..0x2400;
+const VIDEO_RAM: std::ops::Range<usize> = 0x2400..0x4000;
+
+/// What one machine looks like after a frame.
+#[derive(Clone, Copy, Debug)]
+pub struct Observation<'a> {
+    /// 1bpp video memory, 32 bytes per column of 256 pixels.
+    pub framebuffer: &'a [u8],
+    /// Work RAM, where the game keeps scores, lives and object state.
+    pub ram: &'a [u8],
+}
+
+impl<'a> Observation<'a> {
+    fn of(machine: &'a SpaceInvadersMachine) -> Self {
+        let memory = machine.get_memory();
+        Observation {
+            framebuffer: &memory[VIDEO_RAM],
+            ram: &memory[WORK_RAM],
+        }
+    }
+}
+
+pub struct BatchRunner {
+    machines: Vec<SpaceInvadersMachine>,
+    threads: usize,
+}
+
+impl BatchRunner {
+    /// Creates `count` machines with the ROM loaded, using one thread per
+    /// available core.
+    pub fn new(count: usize) -> Self {
+        Self::from_machines((0..count).map(|_| SpaceInvadersMachine::new()).collect())
+    }
+
+    pub fn from_machines(machines: Vec<SpaceInvadersMachine>) -> Self {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        BatchRunner { machines, threads }
+    }
+
+    /// Caps the number of worker threads; 1 runs everything on the caller's thread.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        for machine in &mut self.machines {
+            machine.set_engine(engine);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    pub fn machine(&self, index: usize) -> &SpaceInvadersMachine {
+        &self.machines[index]
+    }
+
+    pub fn machine_mut(&mut self, index: usize) -> &mut SpaceInvadersMachine {
+        &mut self.machines[index]
+    }
+
+    /// Sets each machine's input port 1 to the matching entry of `inputs`
+    /// (see the bit constants in [`super::input`]) and runs one frame on all
+    /// of them.
+    ///
+    /// # Panics
+    /// If `inputs` does not hold exactly one value per machine.
+    pub fn step(&mut self, inputs: &[u8]) -> Vec<Observation<'_>> {
+        assert_eq!(
+            inputs.len(),
+            self.machines.len(),
+            "BatchRunner::step needs one input per machine"
+        );
+        for (machine, &input) in self.machines.iter_mut().zip(inputs) {
+            machine.board_mut().set_pressed(1, input);
+        }
+
+        let chunk = self.machines.len().div_ceil(self.threads).max(1);
+        if chunk >= self.machines.len() {
+            self.machines
+                .iter_mut()
+                .for_each(SpaceInvadersMachine::run_frame);
+        } else {
+            thread::scope(|scope| {
+                for machines in self.machines.chunks_mut(chunk) {
+                    scope.spawn(|| {
+                        machines
+                            .iter_mut()
+                            .for_each(SpaceInvadersMachine::run_frame)
+                    });
+                }
+            });
+        }
+        self.observations()
+    }
+
+    /// The current observation of every machine, without running anything.
+    pub fn observations(&self) -> Vec<Observation<'_>> {
+        self.machines.iter().map(Observation::of).collect()
+    }
+}
@@ -0,0 +1,116 @@
+#![cfg(not(feature = "wasm"))]
+//! Single-board computers assembled from peripheral models.
+//!
+//! A [`Board`] is an 8080 with 64K of RAM and a [`PortMap`] of devices, such
+//! as a [`Usart8251`](super::usart::Usart8251) and a
+//! [`Pit8253`](super::pit::Pit8253). Devices are clocked with the cycles of
+//! every instruction. Once interrupts are enabled, the port map is asked for
+//! an interrupt: a [`Pic8259`](super::pic::Pic8259) answers with a `CALL`
+//! to its vector, and a rising edge on a wired pin with its `RST`.
+//!
+//! ```ignore
+//! let mut ports = PortMap::new();
+//! ports
+//!     .attach(0xEC, Usart8251::new(StdConsole::new()))
+//!     .attach(0xDC, Pit8253::new(2_000_000, [1_000_000, 0, 0]))
+//!     .wire(0xEC, usart::RX_READY_PIN, 7)
+//!     .wire(0xDC, 0, 6);
+//! let mut board = Board::new(ports);
+//! ```
+
+use super::cpm::Exit;
+use super::cpu::{acknowledge_interrupt, emulate_8080_op, InterruptSource};
+use super::data_types::State8080;
+use super::io::PortMap;
+use std::any::Any;
+
+/// Cycles a halted CPU lets pass between looks for an interrupt.
+const HALT_CYCLES: u32 = 4;
+
+pub struct Board {
+    state: State8080,
+}
+
+impl Board {
+    pub fn new(ports: PortMap) -> Self {
+        let mut state = State8080::default();
+        state.history.set_capacity(0);
+        state.io = Some(Box::new(ports));
+        Board { state }
+    }
+
+    /// Copies `image` into memory at `address`.
+    pub fn load(&mut self, address: u16, image: &[u8]) {
+        let start = address as usize;
+        let end = (start + image.len()).min(0x10000);
+        self.state.memory[start..end].copy_from_slice(&image[..end - start]);
+    }
+
+    pub fn ports(&self) -> &PortMap {
+        let io: &dyn Any = self.state.io.as_deref().expect("board has a bus");
+        io.downcast_ref().expect("board has a port map")
+    }
+
+    pub fn ports_mut(&mut self) -> &mut PortMap {
+        let io: &mut dyn Any = self.state.io.as_deref_mut().expect("board has a bus");
+        io.downcast_mut().expect("board has a port map")
+    }
+
+    /// Runs until the CPU halts for good or waits on input that has ended.
+    pub fn run(&mut self) -> Exit {
+        loop {
+            if let Some(exit) = self.step() {
+                return exit;
+            }
+        }
+    }
+
+    /// Runs for at most `limit` instructions, returning None if the board
+    /// is still going.
+    pub fn run_for(&mut self, limit: u64) -> Option<Exit> {
+        (0..limit).find_map(|_| self.step())
+    }
+
+    /// Executes one instruction, then clocks the devices and takes a
+    /// pending interrupt. A `HLT` with interrupts enabled waits for one
+    /// instead, as the devices keep running; with them disabled it stops
+    /// the board.
+    pub fn step(&mut self) -> Option<Exit> {
+        let pc = self.state.pc;
+        let op = self.state.memory[pc as usize];
+        let cycles = match op {
+            0x76 if !self.state.int_enable => return Some(Exit::Halted { pc }),
+            0x76 => HALT_CYCLES,
+            _ => emulate_8080_op(&mut self.state) as u32,
+        };
+
+        let ports = self.ports_mut();
+        ports.tick(cycles);
+        if ports.end_of_input() {
+            return Some(Exit::EndOfInput);
+        }
+        // Interrupts come on only after the instruction following EI, so
+        // that a handler's EI; RET returns before the next one is taken
+        if self.state.int_enable && op != 0xFB && self.ports_mut().requesting() {
+            if op == 0x76 {
+                self.state.pc = pc.wrapping_add(1);
+            }
+            let mut io = self.state.io.take().expect("board has a bus");
+            let ports: &mut dyn Any = io.as_mut();
+            let ports: &mut PortMap = ports.downcast_mut().expect("board has a port map");
+            if let Some(cycles) = acknowledge_interrupt(&mut self.state, ports) {
+                ports.tick(cycles as u32);
+            }
+            self.state.io = Some(io);
+        }
+        None
+    }
+
+    pub fn get_state(&self) -> &State8080 {
+        &self.state
+    }
+
+    pub fn get_state_mut(&mut self) -> &mut State8080 {
+        &mut self.state
+    }
+}
@@ -0,0 +1,254 @@
+//! RAM search and freeze codes.
+//!
+//! [`RamSearch`] narrows down which addresses hold a value by repeatedly
+//! filtering against the current memory or the previous snapshot.
+//! [`CheatEngine`] writes frozen values back into RAM every frame, either
+//! single addresses or named codes loaded from a cheat file:
+//!
+//! ```text
+//! [Infinite lives]
+//! 21FF=03
+//! ```
+//!
+//! Addresses and values are hex; `#` and `;` start comments.
+
+use crate::symbols::parse_number;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Cheats shipped for Space Invaders.
+pub const SPACE_INVADERS: &str = include_str!("../../cheats/invaders.cht");
+
+/// Default RAM searched: Space Invaders work RAM, excluding video memory.
+pub const WORK_RAM: RangeInclusive<u16> = 0x2000..=0x23FF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchCondition {
+    Equal(u8),
+    NotEqual(u8),
+    Greater(u8),
+    Less(u8),
+    /// Different from the previous snapshot.
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+impl SearchCondition {
+    /// Parses `eq 3`, `gt 0`, `changed`, ... as typed in the debugger.
+    pub fn parse(kind: &str, value: Option<&str>) -> Option<SearchCondition> {
+        Some(match kind {
+            "changed" => SearchCondition::Changed,
+            "unchanged" => SearchCondition::Unchanged,
+            "increased" => SearchCondition::Increased,
+            "decreased" => SearchCondition::Decreased,
+            _ => return SearchCondition::with_value(kind, value.and_then(parse_value)?),
+        })
+    }
+
+    /// A comparison against `value`: eq, ne, gt or lt.
+    pub fn with_value(kind: &str, value: u8) -> Option<SearchCondition> {
+        Some(match kind {
+            "eq" | "=" => SearchCondition::Equal(value),
+            "ne" | "!=" => SearchCondition::NotEqual(value),
+            "gt" | ">" => SearchCondition::Greater(value),
+            "lt" | "<" => SearchCondition::Less(value),
+            _ => return None,
+        })
+    }
+
+    fn matches(self, now: u8, before: u8) -> bool {
+        match self {
+            SearchCondition::Equal(value) => now == value,
+            SearchCondition::NotEqual(value) => now != value,
+            SearchCondition::Greater(value) => now > value,
+            SearchCondition::Less(value) => now < value,
+            SearchCondition::Changed => now != before,
+            SearchCondition::Unchanged => now == before,
+            SearchCondition::Increased => now > before,
+            SearchCondition::Decreased => now < before,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RamSearch {
+    range: RangeInclusive<u16>,
+    /// Memory in `range` as of the last filter.
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl RamSearch {
+    /// Starts a search with every address in `range` as a candidate.
+    pub fn new(memory: &[u8], range: RangeInclusive<u16>) -> Self {
+        RamSearch {
+            snapshot: memory[*range.start() as usize..=*range.end() as usize].to_vec(),
+            candidates: range.clone().collect(),
+            range,
+        }
+    }
+
+    /// Keeps the candidates matching `condition` and takes a new snapshot.
+    /// Returns how many are left.
+    pub fn filter(&mut self, memory: &[u8], condition: SearchCondition) -> usize {
+        let start = *self.range.start();
+        let snapshot = &self.snapshot;
+        self.candidates.retain(|&addr| {
+            condition.matches(memory[addr as usize], snapshot[(addr - start) as usize])
+        });
+        self.snapshot
+            .copy_from_slice(&memory[start as usize..=*self.range.end() as usize]);
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    /// Value of `addr` in the last snapshot.
+    pub fn previous(&self, addr: u16) -> Option<u8> {
+        self.range
+            .contains(&addr)
+            .then(|| self.snapshot[(addr - self.range.start()) as usize])
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Cheat {
+    pub name: String,
+    /// `(address, value)` pairs written while the cheat is on.
+    pub codes: Vec<(u16, u8)>,
+    pub enabled: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheatError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CheatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for CheatError {}
+
+/// Parses a hex byte such as `3`, `FF` or `$1F`.
+pub fn parse_value(text: &str) -> Option<u8> {
+    parse_number(text).and_then(|value| u8::try_from(value).ok())
+}
+
+/// Parses a cheat file. Codes start disabled.
+pub fn parse(text: &str) -> Result<Vec<Cheat>, CheatError> {
+    let mut cheats: Vec<Cheat> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| CheatError {
+            line: index + 1,
+            message,
+        };
+        let line = line.split(['#', ';']).next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            cheats.push(Cheat {
+                name: name.trim().to_string(),
+                codes: Vec::new(),
+                enabled: false,
+            });
+            continue;
+        }
+        let (addr, value) = line
+            .split_once('=')
+            .ok_or_else(|| error(format!("expected ADDRESS=VALUE, found '{}'", line)))?;
+        let addr = parse_number(addr).ok_or_else(|| error(format!("bad address '{}'", addr)))?;
+        let value =
+            parse_value(value).ok_or_else(|| error(format!("bad value '{}'", value.trim())))?;
+        cheats
+            .last_mut()
+            .ok_or_else(|| error("code before the first [name]".to_string()))?
+            .codes
+            .push((addr, value));
+    }
+    Ok(cheats)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CheatEngine {
+    cheats: Vec<Cheat>,
+    /// Addresses frozen by hand, outside any named cheat.
+    frozen: BTreeMap<u16, u8>,
+}
+
+impl CheatEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the cheats in `text`, replacing any with the same name.
+    pub fn load(&mut self, text: &str) -> Result<usize, CheatError> {
+        let cheats = parse(text)?;
+        let count = cheats.len();
+        for cheat in cheats {
+            match self.cheats.iter_mut().find(|c| c.name == cheat.name) {
+                Some(existing) => *existing = cheat,
+                None => self.cheats.push(cheat),
+            }
+        }
+        Ok(count)
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /// Turns a named cheat on or off; names match case-insensitively.
+    /// Returns false if there is no such cheat.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self
+            .cheats
+            .iter_mut()
+            .find(|cheat| cheat.name.eq_ignore_ascii_case(name))
+        {
+            Some(cheat) => {
+                cheat.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn freeze(&mut self, addr: u16, value: u8) {
+        self.frozen.insert(addr, value);
+    }
+
+    pub fn unfreeze(&mut self, addr: u16) -> bool {
+        self.frozen.remove(&addr).is_some()
+    }
+
+    pub fn frozen(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.frozen.iter().map(|(&addr, &value)| (addr, value))
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.frozen.is_empty() || self.cheats.iter().any(|cheat| cheat.enabled)
+    }
+
+    /// Writes every frozen value and enabled code into `memory`. Call once
+    /// per frame.
+    pub fn apply(&self, memory: &mut [u8]) {
+        let enabled = self.cheats.iter().filter(|cheat| cheat.enabled);
+        for (addr, value) in enabled.flat_map(|cheat| cheat.codes.iter().copied()) {
+            memory[addr as usize] = value;
+        }
+        for (&addr, &value) in &self.frozen {
+            memory[addr as usize] = value;
+        }
+    }
+}
@@ -0,0 +1,259 @@
+#![cfg(not(feature = "wasm"))]
+//! Terminals for the machines that have one: CP/M, the Altair and boards
+//! with a serial chip.
+
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Read, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// Where console characters come from and go to.
+pub trait Console {
+    /// Whether a key is waiting (BDOS 11).
+    fn ready(&mut self) -> bool;
+    /// The next key, or None once the input has ended.
+    fn read(&mut self) -> Option<u8>;
+    /// True once no key will ever be ready again, so a program polling for
+    /// one can be stopped.
+    fn at_end(&mut self) -> bool {
+        false
+    }
+    fn write(&mut self, byte: u8);
+    /// True when typed characters already show, as on a terminal in line
+    /// mode, so the BDOS should not echo them again.
+    fn local_echo(&self) -> bool {
+        false
+    }
+}
+
+/// Keys read from a stream by a thread, so they can be polled without
+/// blocking.
+struct Keys {
+    keys: Receiver<u8>,
+    waiting: Option<u8>,
+    ended: bool,
+}
+
+impl Keys {
+    fn spawn<R: Read + Send + 'static>(source: R, newline_to_cr: bool) -> Self {
+        let (sender, keys) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in io::BufReader::new(source).bytes() {
+                let Ok(byte) = byte else { break };
+                let key = if newline_to_cr && byte == b'\n' {
+                    b'\r'
+                } else {
+                    byte
+                };
+                if sender.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+        Keys {
+            keys,
+            waiting: None,
+            ended: false,
+        }
+    }
+
+    fn ready(&mut self) -> bool {
+        if self.waiting.is_none() {
+            match self.keys.try_recv() {
+                Ok(key) => self.waiting = Some(key),
+                Err(TryRecvError::Disconnected) => self.ended = true,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        self.waiting.is_some()
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        let key = self.waiting.take().or_else(|| self.keys.recv().ok());
+        self.ended = key.is_none();
+        key
+    }
+
+    fn at_end(&mut self) -> bool {
+        !self.ready() && self.ended
+    }
+}
+
+/// Reads stdin and writes stdout. Line ends become CR on the way in, and CRs
+/// are dropped on the way out. A thread reads stdin so that [`Console::ready`]
+/// can tell whether a key is waiting without blocking.
+pub struct StdConsole {
+    stdout: io::Stdout,
+    keys: Keys,
+}
+
+impl StdConsole {
+    pub fn new() -> Self {
+        StdConsole {
+            stdout: io::stdout(),
+            keys: Keys::spawn(io::stdin(), true),
+        }
+    }
+}
+
+impl Default for StdConsole {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console for StdConsole {
+    fn ready(&mut self) -> bool {
+        self.stdout.flush().ok();
+        self.keys.ready()
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        self.stdout.flush().ok();
+        self.keys.read()
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.keys.at_end()
+    }
+
+    fn write(&mut self, byte: u8) {
+        if byte != b'\r' {
+            self.stdout.write_all(&[byte]).ok();
+        }
+    }
+
+    fn local_echo(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+}
+
+/// A pseudo-terminal for a terminal program such as `screen` or `minicom`
+/// to open, leaving stdin and stdout to the emulator. Bytes pass through
+/// untouched in both directions.
+#[cfg(unix)]
+pub struct PtyConsole {
+    master: std::fs::File,
+    path: std::path::PathBuf,
+    keys: Keys,
+    // Held open so reads of the master wait for a terminal rather than fail
+    _slave: std::fs::File,
+}
+
+#[cfg(unix)]
+impl PtyConsole {
+    pub fn open() -> io::Result<Self> {
+        use std::ffi::CStr;
+        use std::os::fd::{AsRawFd, FromRawFd};
+
+        let check = |result: libc::c_int| {
+            if result < 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(result)
+            }
+        };
+        // SAFETY: posix_openpt returns a new descriptor that the File takes
+        // ownership of, and ptsname a NUL-terminated name that is copied
+        // before anything else can overwrite it.
+        let (master, path) = unsafe {
+            let fd = check(libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY))?;
+            let master = std::fs::File::from_raw_fd(fd);
+            check(libc::grantpt(fd))?;
+            check(libc::unlockpt(fd))?;
+            let name = libc::ptsname(fd);
+            if name.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let path = CStr::from_ptr(name).to_string_lossy().into_owned();
+            (master, std::path::PathBuf::from(path))
+        };
+        let slave = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)?;
+        // Raw until the terminal program sets its own mode, so nothing the
+        // machine prints is echoed back to it as typing
+        // SAFETY: `termios` is plain data filled in by tcgetattr.
+        unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            check(libc::tcgetattr(slave.as_raw_fd(), &mut termios))?;
+            libc::cfmakeraw(&mut termios);
+            check(libc::tcsetattr(slave.as_raw_fd(), libc::TCSANOW, &termios))?;
+        }
+        Ok(PtyConsole {
+            keys: Keys::spawn(master.try_clone()?, false),
+            master,
+            path,
+            _slave: slave,
+        })
+    }
+
+    /// The device to point the terminal program at, e.g. `/dev/pts/3`.
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+}
+
+#[cfg(unix)]
+impl Console for PtyConsole {
+    fn ready(&mut self) -> bool {
+        self.keys.ready()
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        self.keys.read()
+    }
+
+    fn write(&mut self, byte: u8) {
+        self.master.write_all(&[byte]).ok();
+    }
+}
+
+/// Canned input and captured output, for tests and scripts.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptConsole {
+    input: VecDeque<u8>,
+    output: Vec<u8>,
+}
+
+impl ScriptConsole {
+    /// Input is typed as given; use `\r` to end lines.
+    pub fn new(input: &str) -> Self {
+        ScriptConsole {
+            input: input.bytes().collect(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Types more input after what is left.
+    pub fn type_text(&mut self, input: &str) {
+        self.input.extend(input.bytes());
+    }
+
+    pub fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub fn output_text(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+}
+
+impl Console for ScriptConsole {
+    fn ready(&mut self) -> bool {
+        !self.input.is_empty()
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        self.input.pop_front()
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.input.is_empty()
+    }
+
+    fn write(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+}
@@ -0,0 +1,828 @@
+#![cfg(not(feature = "wasm"))]
+//! A CP/M 2.2 host for running `.COM` programs.
+//!
+//! Programs load at 0x0100 and call the BDOS with `CALL 5` as usual, but
+//! neither the BDOS nor the BIOS is 8080 code: their entry points hold
+//! `RET`, and [`Cpm`] traps the CPU when it gets there and does the work in
+//! Rust. Disk files come from host directories, one per drive, and are read
+//! and written a 128-byte record at a time, so FCBs can be copied or reused
+//! freely. The console is a [`Console`](super::console::Console), normally
+//! stdin and stdout.
+//!
+//! Memory is laid out like a 64K system whose CCP has been overwritten:
+//!
+//! | Address | Contents |
+//! |---------|----------|
+//! | 0x0000  | `JMP` to BIOS warm boot |
+//! | 0x0005  | `JMP` to the BDOS |
+//! | 0x005C  | Default FCB, filled from the first two arguments |
+//! | 0x0080  | Command tail and default DMA buffer |
+//! | 0x0100  | Program (TPA) |
+//! | 0xEC06  | BDOS entry, the top of the TPA |
+//! | 0xFA00  | BIOS jump table |
+
+use super::console::{Console, StdConsole};
+use super::cpu::emulate_8080_op;
+use super::data_types::State8080;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const TPA: u16 = 0x0100;
+pub const BDOS_ENTRY: u16 = 0xEC06;
+pub const BIOS: u16 = 0xFA00;
+/// The `RET` each BIOS jump table entry jumps to; reaching one runs the call.
+const BIOS_STUBS: u16 = BIOS + 0x40;
+const BIOS_CALLS: u16 = 17;
+const DPB: u16 = BIOS + 0x60;
+const ALLOCATION: u16 = BIOS + 0x80;
+const DEFAULT_FCB: usize = 0x5C;
+const DEFAULT_DMA: u16 = 0x80;
+const RECORD: usize = 128;
+const EOF: u8 = 0x1A;
+pub const DRIVES: usize = 16;
+
+/// An 8" single-density disk, which is what programs that ask get told.
+const DISK_PARAMETERS: [u8; 15] = [26, 0, 3, 7, 0, 242, 0, 63, 0, 0xC0, 0, 16, 0, 2, 0];
+
+/// Why [`Cpm::run`] stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// The program returned to CP/M: `JMP 0`, `RET` from the TPA, BDOS 0 or
+    /// BIOS warm boot.
+    WarmBoot,
+    /// It read or polled the console after the input had ended.
+    EndOfInput,
+    Halted {
+        pc: u16,
+    },
+}
+
+#[derive(Debug)]
+pub enum CpmError {
+    Io { path: PathBuf, error: io::Error },
+    TooLarge { size: usize },
+}
+
+impl fmt::Display for CpmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpmError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            CpmError::TooLarge { size } => write!(
+                f,
+                "program is {} bytes, the TPA holds {}",
+                size,
+                BDOS_ENTRY - TPA
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CpmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CpmError::Io { error, .. } => Some(error),
+            CpmError::TooLarge { .. } => None,
+        }
+    }
+}
+
+pub struct Cpm<C: Console = StdConsole> {
+    state: State8080,
+    console: C,
+    drives: [Option<PathBuf>; DRIVES],
+    drive: u8,
+    user: u8,
+    dma: u16,
+    column: usize,
+    search: VecDeque<SearchResult>,
+    exit: Option<Exit>,
+}
+
+struct SearchResult {
+    name: [u8; 11],
+    records: usize,
+}
+
+impl<C: Console> Cpm<C> {
+    /// A machine with drive A: on `dir` and nothing loaded.
+    pub fn new<P: AsRef<Path>>(dir: P, console: C) -> Self {
+        let mut cpm = Cpm {
+            state: State8080::default(),
+            console,
+            drives: Default::default(),
+            drive: 0,
+            user: 0,
+            dma: DEFAULT_DMA,
+            column: 0,
+            search: VecDeque::new(),
+            exit: None,
+        };
+        cpm.drives[0] = Some(dir.as_ref().to_path_buf());
+        cpm.state.history.set_capacity(0);
+        cpm.cold_boot();
+        cpm
+    }
+
+    /// Maps drive `drive` (0 for A:) to a host directory.
+    pub fn mount<P: AsRef<Path>>(&mut self, drive: usize, dir: P) {
+        self.drives[drive] = Some(dir.as_ref().to_path_buf());
+    }
+
+    fn cold_boot(&mut self) {
+        let memory = &mut self.state.memory;
+        memory[0x0000..0x0003].copy_from_slice(&jump(BIOS + 3));
+        memory[0x0005..0x0008].copy_from_slice(&jump(BDOS_ENTRY));
+        memory[BDOS_ENTRY as usize] = 0xC9;
+        for call in 0..BIOS_CALLS {
+            let entry = (BIOS + call * 3) as usize;
+            memory[entry..entry + 3].copy_from_slice(&jump(BIOS_STUBS + call));
+            memory[(BIOS_STUBS + call) as usize] = 0xC9;
+        }
+        memory[DPB as usize..DPB as usize + DISK_PARAMETERS.len()]
+            .copy_from_slice(&DISK_PARAMETERS);
+    }
+
+    /// Puts `program` in the TPA with `args` as its command tail, the way
+    /// the CCP would for `PROGRAM args`.
+    pub fn load_com(&mut self, program: &[u8], args: &str) -> Result<(), CpmError> {
+        if program.len() > (BDOS_ENTRY - TPA) as usize {
+            return Err(CpmError::TooLarge {
+                size: program.len(),
+            });
+        }
+        let memory = &mut self.state.memory;
+        memory[TPA as usize..TPA as usize + program.len()].copy_from_slice(program);
+
+        let args = args.trim().to_ascii_uppercase();
+        let mut words = args.split_whitespace();
+        memory[DEFAULT_FCB..0x80].fill(0);
+        for fcb in [DEFAULT_FCB, DEFAULT_FCB + 16] {
+            let (drive, name) = parse_file_name(words.next().unwrap_or(""));
+            memory[fcb] = drive;
+            memory[fcb + 1..fcb + 12].copy_from_slice(&name);
+        }
+        let tail = if args.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", args)
+        };
+        let tail = &tail.as_bytes()[..tail.len().min(RECORD - 2)];
+        memory[0x80] = tail.len() as u8;
+        memory[0x81..0x81 + tail.len()].copy_from_slice(tail);
+        memory[0x81 + tail.len()] = 0;
+
+        // Returning from the program warm-boots, as it does under the CCP
+        self.state.sp = BDOS_ENTRY - 6;
+        self.state.memory[self.state.sp as usize..self.state.sp as usize + 2].fill(0);
+        self.state.pc = TPA;
+        self.dma = DEFAULT_DMA;
+        self.exit = None;
+        Ok(())
+    }
+
+    pub fn load_com_file<P: AsRef<Path>>(&mut self, path: P, args: &str) -> Result<(), CpmError> {
+        let path = path.as_ref();
+        let program = fs::read(path).map_err(|error| CpmError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        self.load_com(&program, args)
+    }
+
+    /// Runs until the program exits.
+    pub fn run(&mut self) -> Exit {
+        loop {
+            if let Some(exit) = self.step() {
+                return exit;
+            }
+        }
+    }
+
+    /// Runs at most `limit` instructions, returning None if the program is
+    /// still going.
+    pub fn run_for(&mut self, limit: u64) -> Option<Exit> {
+        (0..limit).find_map(|_| self.step())
+    }
+
+    /// Executes one instruction, or one BDOS or BIOS call.
+    pub fn step(&mut self) -> Option<Exit> {
+        let pc = self.state.pc;
+        if pc == BDOS_ENTRY {
+            self.bdos();
+        } else if (BIOS_STUBS..BIOS_STUBS + BIOS_CALLS).contains(&pc) {
+            self.bios(pc - BIOS_STUBS);
+        } else if self.state.memory[pc as usize] == 0x76 {
+            return Some(Exit::Halted { pc });
+        } else {
+            emulate_8080_op(&mut self.state);
+            return None;
+        }
+        if self.exit.is_none() {
+            self.ret();
+        }
+        self.exit.take()
+    }
+
+    pub fn console(&self) -> &C {
+        &self.console
+    }
+
+    pub fn console_mut(&mut self) -> &mut C {
+        &mut self.console
+    }
+
+    pub fn get_state(&self) -> &State8080 {
+        &self.state
+    }
+
+    pub fn get_state_mut(&mut self) -> &mut State8080 {
+        &mut self.state
+    }
+
+    fn ret(&mut self) {
+        let sp = self.state.sp as usize;
+        self.state.pc =
+            u16::from_le_bytes([self.state.memory[sp], self.state.memory[(sp + 1) & 0xFFFF]]);
+        self.state.sp = self.state.sp.wrapping_add(2);
+    }
+
+    /// BDOS results go in A and L for bytes, HL and BA for words.
+    fn set_result(&mut self, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.state.a = low;
+        self.state.l = low;
+        self.state.b = high;
+        self.state.h = high;
+    }
+
+    fn de(&self) -> usize {
+        u16::from_le_bytes([self.state.e, self.state.d]) as usize
+    }
+
+    fn bios(&mut self, call: u16) {
+        match call {
+            // BOOT, WBOOT
+            0 | 1 => self.exit = Some(Exit::WarmBoot),
+            // CONST
+            2 => self.state.a = self.console_status() as u8,
+            // CONIN
+            3 => match self.console.read() {
+                Some(key) => self.state.a = key,
+                None => self.exit = Some(Exit::EndOfInput),
+            },
+            // CONOUT
+            4 => self.console.write(self.state.c),
+            // READER
+            7 => self.state.a = EOF,
+            // SELDSK: there are no disk images behind the drives
+            9 => {
+                self.state.h = 0;
+                self.state.l = 0;
+            }
+            // SETDMA
+            12 => self.dma = u16::from_le_bytes([self.state.c, self.state.b]),
+            // READ, WRITE
+            13 | 14 => self.state.a = 1,
+            // LISTST
+            15 => self.state.a = 0xFF,
+            // SECTRAN
+            16 => {
+                self.state.h = self.state.b;
+                self.state.l = self.state.c;
+            }
+            // LIST, PUNCH, HOME, SETTRK, SETSEC
+            _ => {}
+        }
+    }
+
+    fn bdos(&mut self) {
+        let e = self.state.e;
+        let result = match self.state.c {
+            0 => {
+                self.exit = Some(Exit::WarmBoot);
+                return;
+            }
+            1 => match self.read_key() {
+                Some(key) => {
+                    self.echo(key);
+                    key as u16
+                }
+                None => return,
+            },
+            2 => {
+                self.print(e);
+                0
+            }
+            3 => EOF as u16,
+            // Punch and list output go nowhere
+            4 | 5 => 0,
+            6 => match e {
+                0xFF if self.console.ready() => match self.read_key() {
+                    Some(key) => key as u16,
+                    None => return,
+                },
+                0xFF => {
+                    // Stops the program if nothing will ever be typed
+                    self.console_status();
+                    0
+                }
+                0xFE => self.console_status(),
+                _ => {
+                    self.console.write(e);
+                    0
+                }
+            },
+            7 => self.state.memory[3] as u16,
+            8 => {
+                self.state.memory[3] = e;
+                0
+            }
+            9 => {
+                let mut address = self.de();
+                while self.state.memory[address] != b'$' {
+                    self.print(self.state.memory[address]);
+                    address = (address + 1) & 0xFFFF;
+                }
+                0
+            }
+            10 => {
+                self.read_line();
+                0
+            }
+            11 => self.console_status(),
+            12 => 0x0022,
+            13 => {
+                self.drive = 0;
+                self.dma = DEFAULT_DMA;
+                0
+            }
+            14 => {
+                if self.drives.get(e as usize).is_some_and(Option::is_some) {
+                    self.drive = e;
+                    0
+                } else {
+                    0xFF
+                }
+            }
+            15 => self.open(),
+            // Nothing is buffered, so closing only checks the file is there
+            16 => self.find(self.de()).map_or(0xFF, |_| 0),
+            17 => self.search_first(),
+            18 => self.search_next(),
+            19 => self.delete(),
+            20 => self.read_sequential(),
+            21 => self.write_sequential(),
+            22 => self.make(),
+            23 => self.rename(),
+            24 => self
+                .drives
+                .iter()
+                .enumerate()
+                .filter(|(_, dir)| dir.is_some())
+                .map(|(drive, _)| 1 << drive)
+                .sum(),
+            25 => self.drive as u16,
+            26 => {
+                self.dma = self.de() as u16;
+                0
+            }
+            27 => ALLOCATION,
+            // Write protection and attributes are accepted and ignored
+            28..=30 => 0,
+            31 => DPB,
+            32 => {
+                if e != 0xFF {
+                    self.user = e & 0x0F;
+                }
+                self.user as u16
+            }
+            33 => self.read_random(),
+            34 | 40 => self.write_random(),
+            35 => self.file_size(),
+            36 => {
+                let fcb = self.de();
+                let record = position(&self.state.memory, fcb);
+                set_random_record(&mut self.state.memory, fcb, record);
+                0
+            }
+            // Drive reset, and anything newer than CP/M 2.2
+            _ => 0,
+        };
+        self.set_result(result);
+    }
+
+    fn console_status(&mut self) -> u16 {
+        if self.console.ready() {
+            0xFF
+        } else {
+            if self.console.at_end() {
+                self.exit = Some(Exit::EndOfInput);
+            }
+            0
+        }
+    }
+
+    fn read_key(&mut self) -> Option<u8> {
+        let key = self.console.read();
+        if key.is_none() {
+            self.exit = Some(Exit::EndOfInput);
+        }
+        key
+    }
+
+    fn echo(&mut self, key: u8) {
+        if !self.console.local_echo() {
+            self.print(key);
+        }
+    }
+
+    /// Console output with tabs expanded to every eighth column.
+    fn print(&mut self, byte: u8) {
+        match byte {
+            b'\t' => {
+                self.console.write(b' ');
+                self.column += 1;
+                while !self.column.is_multiple_of(8) {
+                    self.console.write(b' ');
+                    self.column += 1;
+                }
+                return;
+            }
+            b'\r' => self.column = 0,
+            0x08 => self.column = self.column.saturating_sub(1),
+            0x20.. => self.column += 1,
+            _ => {}
+        }
+        self.console.write(byte);
+    }
+
+    /// BDOS 10: a line with backspace, into the buffer at DE.
+    fn read_line(&mut self) {
+        let buffer = self.de();
+        let max = self.state.memory[buffer] as usize;
+        let mut line = Vec::new();
+        loop {
+            let Some(key) = self.read_key() else {
+                return;
+            };
+            match key {
+                b'\r' | b'\n' => break,
+                0x03 if line.is_empty() => {
+                    self.exit = Some(Exit::WarmBoot);
+                    return;
+                }
+                0x08 | 0x7F => {
+                    let erased = line.pop().is_some();
+                    if erased && !self.console.local_echo() {
+                        for byte in [0x08, b' ', 0x08] {
+                            self.print(byte);
+                        }
+                    }
+                }
+                _ if line.len() < max => {
+                    self.echo(key);
+                    line.push(key);
+                }
+                _ => {}
+            }
+        }
+        self.echo(b'\r');
+        poke(&mut self.state.memory, buffer + 1, line.len() as u8);
+        write_bytes(&mut self.state.memory, buffer + 2, &line);
+    }
+
+    fn drive_dir(&self, fcb: usize) -> Option<&Path> {
+        let drive = match self.state.memory[fcb] {
+            0 | b'?' => self.drive as usize,
+            drive => drive as usize - 1,
+        };
+        self.drives.get(drive)?.as_deref()
+    }
+
+    /// Files in the FCB's drive whose names match its name, which may hold
+    /// `?` wildcards, sorted by name.
+    fn matches(&self, fcb: usize) -> Vec<(PathBuf, [u8; 11])> {
+        let Some(dir) = self.drive_dir(fcb) else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let pattern: [u8; 11] = read_bytes(&self.state.memory, fcb + 1);
+        let mut found: Vec<_> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+            .filter_map(|entry| {
+                let name = cpm_name(entry.file_name().to_str()?)?;
+                let matches = pattern
+                    .iter()
+                    .zip(name)
+                    .all(|(&want, have)| want & 0x7F == b'?' || want & 0x7F == have);
+                matches.then(|| (entry.path(), name))
+            })
+            .collect();
+        found.sort_by_key(|&(_, name)| name);
+        found
+    }
+
+    fn find(&self, fcb: usize) -> Option<PathBuf> {
+        self.matches(fcb).into_iter().next().map(|(path, _)| path)
+    }
+
+    /// The host path for a file the FCB names, whether or not it exists.
+    fn host_path(&self, fcb: usize) -> Option<PathBuf> {
+        if let Some(path) = self.find(fcb) {
+            return Some(path);
+        }
+        let name: [u8; 11] = read_bytes(&self.state.memory, fcb + 1);
+        let text = |bytes: &[u8]| -> String {
+            bytes
+                .iter()
+                .map(|&byte| (byte & 0x7F) as char)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        };
+        let (base, extension) = (text(&name[..8]), text(&name[8..]));
+        if base.is_empty() || name.contains(&b'?') {
+            return None;
+        }
+        let file = if extension.is_empty() {
+            base
+        } else {
+            format!("{}.{}", base, extension)
+        };
+        Some(self.drive_dir(fcb)?.join(file))
+    }
+
+    fn open(&mut self) -> u16 {
+        let fcb = self.de();
+        let Some(path) = self.find(fcb) else {
+            return 0xFF;
+        };
+        let records = file_records(&path);
+        let memory = &mut self.state.memory;
+        let extent = (peek(memory, fcb + 14) as usize & 0x3F) * 32
+            + (peek(memory, fcb + 12) as usize & 0x1F);
+        let count = records.saturating_sub(extent * RECORD).min(RECORD);
+        poke(memory, fcb + 15, count as u8);
+        write_bytes(memory, fcb + 16, &[0; 16]);
+        0
+    }
+
+    fn make(&mut self) -> u16 {
+        let fcb = self.de();
+        let Some(path) = self.host_path(fcb) else {
+            return 0xFF;
+        };
+        if fs::File::create(&path).is_err() {
+            return 0xFF;
+        }
+        write_bytes(&mut self.state.memory, fcb + 12, &[0; 21]);
+        0
+    }
+
+    fn delete(&mut self) -> u16 {
+        let found = self.matches(self.de());
+        let deleted = found
+            .iter()
+            .filter(|(path, _)| fs::remove_file(path).is_ok())
+            .count();
+        if deleted == 0 {
+            0xFF
+        } else {
+            0
+        }
+    }
+
+    fn rename(&mut self) -> u16 {
+        let fcb = self.de();
+        let Some(from) = self.find(fcb) else {
+            return 0xFF;
+        };
+        // The new name sits in the second half of the FCB, on the same drive
+        let new = (fcb + 16) & 0xFFFF;
+        let drive = self.state.memory[new];
+        self.state.memory[new] = self.state.memory[fcb];
+        let to = match self.find(new) {
+            Some(_) => None,
+            None => self.host_path(new),
+        };
+        self.state.memory[new] = drive;
+        match to.map(|to| fs::rename(from, to)) {
+            Some(Ok(())) => 0,
+            _ => 0xFF,
+        }
+    }
+
+    fn search_first(&mut self) -> u16 {
+        self.search = self
+            .matches(self.de())
+            .into_iter()
+            .map(|(path, name)| SearchResult {
+                name,
+                records: file_records(&path),
+            })
+            .collect();
+        self.search_next()
+    }
+
+    /// Writes the next match as a directory entry at the start of the DMA
+    /// buffer.
+    fn search_next(&mut self) -> u16 {
+        let Some(result) = self.search.pop_front() else {
+            return 0xFF;
+        };
+        let mut entry = [0xE5; RECORD];
+        entry[0] = self.user;
+        entry[1..12].copy_from_slice(&result.name);
+        entry[12..15].fill(0);
+        entry[15] = result.records.min(RECORD) as u8;
+        entry[16..32].fill(0);
+        write_bytes(&mut self.state.memory, self.dma as usize, &entry);
+        0
+    }
+
+    fn read_sequential(&mut self) -> u16 {
+        let fcb = self.de();
+        let record = position(&self.state.memory, fcb);
+        let result = self.read_record(fcb, record);
+        if result == 0 {
+            set_position(&mut self.state.memory, fcb, record + 1);
+        }
+        result
+    }
+
+    fn write_sequential(&mut self) -> u16 {
+        let fcb = self.de();
+        let record = position(&self.state.memory, fcb);
+        let result = self.write_record(fcb, record);
+        if result == 0 {
+            set_position(&mut self.state.memory, fcb, record + 1);
+        }
+        result
+    }
+
+    /// Random reads and writes leave the FCB positioned at their record, so
+    /// sequential access carries on from there.
+    fn read_random(&mut self) -> u16 {
+        let fcb = self.de();
+        let record = random_record(&self.state.memory, fcb);
+        if record >= 0x10000 {
+            return 6;
+        }
+        set_position(&mut self.state.memory, fcb, record);
+        self.read_record(fcb, record)
+    }
+
+    fn write_random(&mut self) -> u16 {
+        let fcb = self.de();
+        let record = random_record(&self.state.memory, fcb);
+        if record >= 0x10000 {
+            return 6;
+        }
+        set_position(&mut self.state.memory, fcb, record);
+        self.write_record(fcb, record)
+    }
+
+    fn file_size(&mut self) -> u16 {
+        let fcb = self.de();
+        let Some(path) = self.find(fcb) else {
+            return 0xFF;
+        };
+        set_random_record(&mut self.state.memory, fcb, file_records(&path));
+        0
+    }
+
+    /// Reads a record into the DMA buffer, padding a short last record with
+    /// ^Z. Returns 1 past the end of the file.
+    fn read_record(&mut self, fcb: usize, record: usize) -> u16 {
+        let Some(path) = self.find(fcb) else {
+            return 1;
+        };
+        let mut data = Vec::with_capacity(RECORD);
+        let read = fs::File::open(path).and_then(|mut file| {
+            file.seek(SeekFrom::Start((record * RECORD) as u64))?;
+            file.take(RECORD as u64).read_to_end(&mut data)
+        });
+        if read.is_err() || data.is_empty() {
+            return 1;
+        }
+        data.resize(RECORD, EOF);
+        write_bytes(&mut self.state.memory, self.dma as usize, &data);
+        0
+    }
+
+    /// Writes the DMA buffer as a record. Returns 2 if the disk refuses.
+    fn write_record(&mut self, fcb: usize, record: usize) -> u16 {
+        let Some(path) = self.find(fcb) else {
+            return 2;
+        };
+        let data: [u8; RECORD] = read_bytes(&self.state.memory, self.dma as usize);
+        let written = OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|mut file| {
+                file.seek(SeekFrom::Start((record * RECORD) as u64))?;
+                file.write_all(&data)
+            });
+        if written.is_err() {
+            return 2;
+        }
+        0
+    }
+}
+
+fn jump(address: u16) -> [u8; 3] {
+    let [low, high] = address.to_le_bytes();
+    [0xC3, low, high]
+}
+
+fn file_records(path: &Path) -> usize {
+    let size = fs::metadata(path).map_or(0, |metadata| metadata.len() as usize);
+    size.div_ceil(RECORD)
+}
+
+/// Sequential position from the FCB's current record, extent and S2 bytes.
+fn position(memory: &[u8], fcb: usize) -> usize {
+    let extent =
+        (peek(memory, fcb + 14) as usize & 0x3F) * 32 + (peek(memory, fcb + 12) as usize & 0x1F);
+    extent * RECORD + peek(memory, fcb + 32) as usize % RECORD
+}
+
+fn set_position(memory: &mut [u8], fcb: usize, record: usize) {
+    poke(memory, fcb + 32, (record % RECORD) as u8);
+    poke(memory, fcb + 12, (record / RECORD % 32) as u8);
+    poke(memory, fcb + 14, (record / RECORD / 32) as u8);
+}
+
+fn random_record(memory: &[u8], fcb: usize) -> usize {
+    let [low, middle, high] = read_bytes(memory, fcb + 33);
+    u32::from_le_bytes([low, middle, high, 0]) as usize
+}
+
+fn set_random_record(memory: &mut [u8], fcb: usize, record: usize) {
+    write_bytes(memory, fcb + 33, &(record as u32).to_le_bytes()[..3]);
+}
+
+/// Guest addresses from DE or the DMA address wrap at 64K as the CPU's do,
+/// so an FCB or buffer at the top of memory carries on at the bottom.
+fn peek(memory: &[u8], address: usize) -> u8 {
+    memory[address & 0xFFFF]
+}
+
+fn poke(memory: &mut [u8], address: usize, value: u8) {
+    memory[address & 0xFFFF] = value;
+}
+
+fn read_bytes<const N: usize>(memory: &[u8], address: usize) -> [u8; N] {
+    std::array::from_fn(|i| peek(memory, address + i))
+}
+
+fn write_bytes(memory: &mut [u8], address: usize, bytes: &[u8]) {
+    for (i, &byte) in bytes.iter().enumerate() {
+        poke(memory, address + i, byte);
+    }
+}
+
+/// The 8.3 name CP/M would see for a host file, or None if it has none.
+pub fn cpm_name(host: &str) -> Option<[u8; 11]> {
+    let (base, extension) = host.rsplit_once('.').unwrap_or((host, ""));
+    let valid = |part: &str| {
+        part.bytes()
+            .all(|byte| byte.is_ascii_graphic() && !b"<>.,;:=?*[]".contains(&byte))
+    };
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 || !valid(base) || !valid(extension)
+    {
+        return None;
+    }
+    let mut name = [b' '; 11];
+    name[..base.len()].copy_from_slice(base.to_ascii_uppercase().as_bytes());
+    name[8..8 + extension.len()].copy_from_slice(extension.to_ascii_uppercase().as_bytes());
+    Some(name)
+}
+
+/// Parses `[d:]name[.typ]` into an FCB drive byte and name the way the CCP
+/// does, with `*` filling the rest of a field with `?`.
+pub fn parse_file_name(text: &str) -> (u8, [u8; 11]) {
+    let mut name = [b' '; 11];
+    let (drive, text) = match text.as_bytes() {
+        [letter @ b'A'..=b'P', b':', ..] => (letter - b'A' + 1, &text[2..]),
+        _ => (0, text),
+    };
+    let (base, extension) = text.split_once('.').unwrap_or((text, ""));
+    for (field, part) in [(0..8, base), (8..11, extension)] {
+        let field = &mut name[field];
+        for (slot, byte) in (0..field.len()).zip(part.bytes()) {
+            if byte == b'*' {
+                field[slot..].fill(b'?');
+                break;
+            }
+            field[slot] = byte.to_ascii_uppercase();
+        }
+    }
+    (drive, name)
+}
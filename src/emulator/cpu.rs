@@ -1,5 +1,4 @@
-use super::data_types::{self, CYCLE_TABLE};
-use super::instruction_lookup::get_instruction_mnemonic;
+use super::data_types::{self, Register, RegisterPair, CYCLE_TABLE};
 
 pub struct CPU {
     pub state: data_types::State8080,
@@ -33,212 +32,304 @@ impl CPU {
     }
 }
 
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub fn unimplemented_instruction(instruction: u8) {
     panic!("Error: Unimplemented instruction 0x{:02X}\n", instruction);
 }
 
 pub fn emulate_8080_op(state: &mut data_types::State8080) -> u8 {
-    let op_codes = &state.memory[state.pc as usize..];
-    let op_code = op_codes[0];
-    let current_pc = state.pc;
-
-    // Create mnemonic before execution (when we can still access original memory state)
-    let mnemonic = get_instruction_mnemonic(op_code, state);
-
-    // print!("PC: {:04X}  ", state.pc); // Print the address in hex
-    // println!("{:02X} ", op_code); // Print the opcode in hex
-
-    match op_code {
-        0x00 => nop(state),
-        0x01 => lxi(state, data_types::RegisterPair::BC),
-        0x02 => stax(state, data_types::RegisterPair::BC),
-        0x03 => inx(state, &data_types::RegisterPair::BC),
-        0x04 => inr(state, data_types::Register::B),
-        0x05 => dcr(state, data_types::Register::B),
-        0x06 => mvi(state, data_types::Register::B),
-        0x07 => rlc(state),
-        0x08 => unimplemented_instruction(op_code),
-        0x09 => dad(state, &data_types::RegisterPair::BC),
-        0x0A => ldax(state, data_types::RegisterPair::BC),
-        0x0B => dcx(state, &data_types::RegisterPair::BC),
-        0x0C => inr(state, data_types::Register::C),
-        0x0D => dcr(state, data_types::Register::C),
-        0x0E => mvi(state, data_types::Register::C),
-        0x0F => rrc(state),
-        0x10 => unimplemented_instruction(op_code),
-        0x11 => lxi(state, data_types::RegisterPair::DE),
-        0x12 => stax(state, data_types::RegisterPair::DE),
-        0x13 => inx(state, &data_types::RegisterPair::DE),
-        0x14 => inr(state, data_types::Register::D),
-        0x15 => dcr(state, data_types::Register::D),
-        0x16 => mvi(state, data_types::Register::D),
-        0x17 => ral(state),
-        0x18 => unimplemented_instruction(op_code),
-        0x19 => dad(state, &data_types::RegisterPair::DE),
-        0x1A => ldax(state, data_types::RegisterPair::DE),
-        0x1B => dcx(state, &data_types::RegisterPair::DE),
-        0x1C => inr(state, data_types::Register::E),
-        0x1D => dcr(state, data_types::Register::E),
-        0x1E => mvi(state, data_types::Register::E),
-        0x1F => rar(state),
-        0x20 => unimplemented_instruction(op_code),
-        0x21 => lxi(state, data_types::RegisterPair::HL),
-        0x22 => shld(state),
-        0x23 => inx(state, &data_types::RegisterPair::HL),
-        0x24 => inr(state, data_types::Register::H),
-        0x25 => dcr(state, data_types::Register::H),
-        0x26 => mvi(state, data_types::Register::H),
-        0x27 => daa(state),
-        0x28 => unimplemented_instruction(op_code),
-        0x29 => dad(state, &data_types::RegisterPair::HL),
-        0x2A => lhld(state),
-        0x2B => dcx(state, &data_types::RegisterPair::HL),
-        0x2C => inr(state, data_types::Register::L),
-        0x2D => dcr(state, data_types::Register::L),
-        0x2E => mvi(state, data_types::Register::L),
-        0x2F => cma(state),
-        0x30 => unimplemented_instruction(op_code),
-        0x31 => lxi(state, data_types::RegisterPair::SP),
-        0x32 => sta(state),
-        0x33 => inx(state, &data_types::RegisterPair::SP),
-        0x34 => inr(state, data_types::Register::M),
-        0x35 => dcr_m(state),
-        0x36 => mvi_m(state),
-        0x37 => stc(state),
-        0x38 => unimplemented_instruction(op_code),
-        0x39 => dad(state, &data_types::RegisterPair::SP),
-        0x3A => lda(state),
-        0x3B => dcx(state, &data_types::RegisterPair::SP),
-        0x3C => inr(state, data_types::Register::A),
-        0x3D => dcr(state, data_types::Register::A),
-        0x3E => mvi(state, data_types::Register::A),
-        0x3F => cmc(state),
-        // Handle MOV and HLT instructions (0x40 to 0x7F)
-        0x40..=0x7F => {
-            if op_codes[0] == 0x76 {
-                hlt(state);
-            } else {
-                let dest = (op_codes[0] >> 3) & 0x07;
-                let src = op_codes[0] & 0x07;
-                let dest_reg = data_types::Register::from_u8(dest);
-                let src_reg = data_types::Register::from_u8(src);
-                mov(state, dest_reg, src_reg);
-            }
-        }
-        // ADD, ADC instructions
-        0x80..=0x87 => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            add(state, src_reg);
-        }
-        0x88..=0x8F => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            adc(state, src_reg);
-        }
-        // SUB, SBB instructions
-        0x90..=0x97 => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            sub(state, src_reg);
-        }
-        0x98..=0x9F => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            sbb(state, src_reg);
-        }
-        // ANA, XRA, ORA, CMP instructions
-        0xA0..=0xA7 => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            ana(state, src_reg);
-        }
-        0xA8..=0xAF => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            xra(state, src_reg);
-        }
-        0xB0..=0xB7 => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            ora(state, src_reg);
-        }
-        0xB8..=0xBF => {
-            let src = op_codes[0] & 0x07;
-            let src_reg = data_types::Register::from_u8(src);
-            cmp(state, src_reg);
-        }
-        // Control and other instructions
-        0xC0 => rnz(state),
-        0xC1 => pop(state, data_types::RegisterPair::BC),
-        0xC2 => jnz(state),
-        0xC3 => jmp(state),
-        0xC4 => cnz(state),
-        0xC5 => push(state, data_types::RegisterPair::BC),
-        0xC6 => adi(state),
-        0xC7 => rst(state, 0),
-        0xC8 => rz(state),
-        0xC9 => ret(state),
-        0xCA => jz(state),
-        0xCB => unimplemented_instruction(op_code),
-        0xCC => cz(state),
-        0xCD => call(state),
-        0xCE => aci(state),
-        0xCF => rst(state, 1),
-        0xD0 => rnc(state),
-        0xD1 => pop(state, data_types::RegisterPair::DE),
-        0xD2 => jnc(state),
-        0xD3 => out(state),
-        0xD4 => cnc(state),
-        0xD5 => push(state, data_types::RegisterPair::DE),
-        0xD6 => sui(state),
-        0xD7 => rst(state, 2),
-        0xD8 => rc(state),
-        0xD9 => unimplemented_instruction(op_code),
-        0xDA => jc(state),
-        0xDB => inp(state),
-        0xDC => cc(state),
-        0xDD => unimplemented_instruction(op_code),
-        0xDE => sbi(state),
-        0xDF => rst(state, 3),
-        0xE0 => rpo(state),
-        0xE1 => pop(state, data_types::RegisterPair::HL),
-        0xE2 => jpo(state),
-        0xE3 => xthl(state),
-        0xE4 => cpo(state),
-        0xE5 => push(state, data_types::RegisterPair::HL),
-        0xE6 => ani(state),
-        0xE7 => rst(state, 4),
-        0xE8 => rpe(state),
-        0xE9 => pchl(state),
-        0xEA => jpe(state),
-        0xEB => xchg(state),
-        0xEC => cpe(state),
-        0xED => unimplemented_instruction(op_code),
-        0xEE => xri(state),
-        0xEF => rst(state, 5),
-        0xF0 => rp(state),
-        0xF1 => pop_psw(state),
-        0xF2 => jp(state),
-        0xF3 => di(state),
-        0xF4 => cp(state),
-        0xF5 => push_psw(state),
-        0xF6 => ori(state),
-        0xF7 => rst(state, 6),
-        0xF8 => rm(state),
-        0xF9 => sphl(state),
-        0xFA => jm(state),
-        0xFB => ei(state),
-        0xFC => cm(state),
-        0xFD => unimplemented_instruction(op_code),
-        0xFE => cpi(state),
-        0xFF => rst(state, 7),
+    let op_code = state.memory[state.pc as usize];
+
+    // Keep the raw bytes; the history is only disassembled when it is read
+    if cfg!(feature = "history") && state.history.is_enabled() {
+        let pc = state.pc;
+        let operand = |offset: u16| state.memory[pc.wrapping_add(offset) as usize];
+        state.history.record(pc, [op_code, operand(1), operand(2)]);
+    }
+
+    if let Some(mut tracer) = state.tracer.take() {
+        tracer.record(state);
+        state.tracer = Some(tracer);
     }
 
-    // Record instruction after execution
-    state.add_instruction(current_pc, op_code, mnemonic);
+    OPCODE_TABLE[op_code as usize](state);
 
-    return CYCLE_TABLE[op_code as usize];
+    let cycles = CYCLE_TABLE[op_code as usize];
+    if let Some(tracer) = state.tracer.as_mut() {
+        tracer.add_cycles(cycles);
+    }
+    cycles
+}
+
+pub(crate) type OpHandler = fn(&mut data_types::State8080);
+
+/// Handler for every opcode, indexed by the opcode byte.
+pub(crate) static OPCODE_TABLE: [OpHandler; 256] = [
+    nop,                                          // 00
+    |state| lxi(state, RegisterPair::BC),         // 01
+    |state| stax(state, RegisterPair::BC),        // 02
+    |state| inx(state, &RegisterPair::BC),        // 03
+    |state| inr(state, Register::B),              // 04
+    |state| dcr(state, Register::B),              // 05
+    |state| mvi(state, Register::B),              // 06
+    rlc,                                          // 07
+    undocumented,                                 // 08
+    |state| dad(state, &RegisterPair::BC),        // 09
+    |state| ldax(state, RegisterPair::BC),        // 0A
+    |state| dcx(state, &RegisterPair::BC),        // 0B
+    |state| inr(state, Register::C),              // 0C
+    |state| dcr(state, Register::C),              // 0D
+    |state| mvi(state, Register::C),              // 0E
+    rrc,                                          // 0F
+    undocumented,                                 // 10
+    |state| lxi(state, RegisterPair::DE),         // 11
+    |state| stax(state, RegisterPair::DE),        // 12
+    |state| inx(state, &RegisterPair::DE),        // 13
+    |state| inr(state, Register::D),              // 14
+    |state| dcr(state, Register::D),              // 15
+    |state| mvi(state, Register::D),              // 16
+    ral,                                          // 17
+    undocumented,                                 // 18
+    |state| dad(state, &RegisterPair::DE),        // 19
+    |state| ldax(state, RegisterPair::DE),        // 1A
+    |state| dcx(state, &RegisterPair::DE),        // 1B
+    |state| inr(state, Register::E),              // 1C
+    |state| dcr(state, Register::E),              // 1D
+    |state| mvi(state, Register::E),              // 1E
+    rar,                                          // 1F
+    undocumented,                                 // 20
+    |state| lxi(state, RegisterPair::HL),         // 21
+    shld,                                         // 22
+    |state| inx(state, &RegisterPair::HL),        // 23
+    |state| inr(state, Register::H),              // 24
+    |state| dcr(state, Register::H),              // 25
+    |state| mvi(state, Register::H),              // 26
+    daa,                                          // 27
+    undocumented,                                 // 28
+    |state| dad(state, &RegisterPair::HL),        // 29
+    lhld,                                         // 2A
+    |state| dcx(state, &RegisterPair::HL),        // 2B
+    |state| inr(state, Register::L),              // 2C
+    |state| dcr(state, Register::L),              // 2D
+    |state| mvi(state, Register::L),              // 2E
+    cma,                                          // 2F
+    undocumented,                                 // 30
+    |state| lxi(state, RegisterPair::SP),         // 31
+    sta,                                          // 32
+    |state| inx(state, &RegisterPair::SP),        // 33
+    |state| inr(state, Register::M),              // 34
+    dcr_m,                                        // 35
+    mvi_m,                                        // 36
+    stc,                                          // 37
+    undocumented,                                 // 38
+    |state| dad(state, &RegisterPair::SP),        // 39
+    lda,                                          // 3A
+    |state| dcx(state, &RegisterPair::SP),        // 3B
+    |state| inr(state, Register::A),              // 3C
+    |state| dcr(state, Register::A),              // 3D
+    |state| mvi(state, Register::A),              // 3E
+    cmc,                                          // 3F
+    |state| mov(state, Register::B, Register::B), // 40
+    |state| mov(state, Register::B, Register::C), // 41
+    |state| mov(state, Register::B, Register::D), // 42
+    |state| mov(state, Register::B, Register::E), // 43
+    |state| mov(state, Register::B, Register::H), // 44
+    |state| mov(state, Register::B, Register::L), // 45
+    |state| mov(state, Register::B, Register::M), // 46
+    |state| mov(state, Register::B, Register::A), // 47
+    |state| mov(state, Register::C, Register::B), // 48
+    |state| mov(state, Register::C, Register::C), // 49
+    |state| mov(state, Register::C, Register::D), // 4A
+    |state| mov(state, Register::C, Register::E), // 4B
+    |state| mov(state, Register::C, Register::H), // 4C
+    |state| mov(state, Register::C, Register::L), // 4D
+    |state| mov(state, Register::C, Register::M), // 4E
+    |state| mov(state, Register::C, Register::A), // 4F
+    |state| mov(state, Register::D, Register::B), // 50
+    |state| mov(state, Register::D, Register::C), // 51
+    |state| mov(state, Register::D, Register::D), // 52
+    |state| mov(state, Register::D, Register::E), // 53
+    |state| mov(state, Register::D, Register::H), // 54
+    |state| mov(state, Register::D, Register::L), // 55
+    |state| mov(state, Register::D, Register::M), // 56
+    |state| mov(state, Register::D, Register::A), // 57
+    |state| mov(state, Register::E, Register::B), // 58
+    |state| mov(state, Register::E, Register::C), // 59
+    |state| mov(state, Register::E, Register::D), // 5A
+    |state| mov(state, Register::E, Register::E), // 5B
+    |state| mov(state, Register::E, Register::H), // 5C
+    |state| mov(state, Register::E, Register::L), // 5D
+    |state| mov(state, Register::E, Register::M), // 5E
+    |state| mov(state, Register::E, Register::A), // 5F
+    |state| mov(state, Register::H, Register::B), // 60
+    |state| mov(state, Register::H, Register::C), // 61
+    |state| mov(state, Register::H, Register::D), // 62
+    |state| mov(state, Register::H, Register::E), // 63
+    |state| mov(state, Register::H, Register::H), // 64
+    |state| mov(state, Register::H, Register::L), // 65
+    |state| mov(state, Register::H, Register::M), // 66
+    |state| mov(state, Register::H, Register::A), // 67
+    |state| mov(state, Register::L, Register::B), // 68
+    |state| mov(state, Register::L, Register::C), // 69
+    |state| mov(state, Register::L, Register::D), // 6A
+    |state| mov(state, Register::L, Register::E), // 6B
+    |state| mov(state, Register::L, Register::H), // 6C
+    |state| mov(state, Register::L, Register::L), // 6D
+    |state| mov(state, Register::L, Register::M), // 6E
+    |state| mov(state, Register::L, Register::A), // 6F
+    |state| mov(state, Register::M, Register::B), // 70
+    |state| mov(state, Register::M, Register::C), // 71
+    |state| mov(state, Register::M, Register::D), // 72
+    |state| mov(state, Register::M, Register::E), // 73
+    |state| mov(state, Register::M, Register::H), // 74
+    |state| mov(state, Register::M, Register::L), // 75
+    hlt,                                          // 76
+    |state| mov(state, Register::M, Register::A), // 77
+    |state| mov(state, Register::A, Register::B), // 78
+    |state| mov(state, Register::A, Register::C), // 79
+    |state| mov(state, Register::A, Register::D), // 7A
+    |state| mov(state, Register::A, Register::E), // 7B
+    |state| mov(state, Register::A, Register::H), // 7C
+    |state| mov(state, Register::A, Register::L), // 7D
+    |state| mov(state, Register::A, Register::M), // 7E
+    |state| mov(state, Register::A, Register::A), // 7F
+    |state| add(state, Register::B),              // 80
+    |state| add(state, Register::C),              // 81
+    |state| add(state, Register::D),              // 82
+    |state| add(state, Register::E),              // 83
+    |state| add(state, Register::H),              // 84
+    |state| add(state, Register::L),              // 85
+    |state| add(state, Register::M),              // 86
+    |state| add(state, Register::A),              // 87
+    |state| adc(state, Register::B),              // 88
+    |state| adc(state, Register::C),              // 89
+    |state| adc(state, Register::D),              // 8A
+    |state| adc(state, Register::E),              // 8B
+    |state| adc(state, Register::H),              // 8C
+    |state| adc(state, Register::L),              // 8D
+    |state| adc(state, Register::M),              // 8E
+    |state| adc(state, Register::A),              // 8F
+    |state| sub(state, Register::B),              // 90
+    |state| sub(state, Register::C),              // 91
+    |state| sub(state, Register::D),              // 92
+    |state| sub(state, Register::E),              // 93
+    |state| sub(state, Register::H),              // 94
+    |state| sub(state, Register::L),              // 95
+    |state| sub(state, Register::M),              // 96
+    |state| sub(state, Register::A),              // 97
+    |state| sbb(state, Register::B),              // 98
+    |state| sbb(state, Register::C),              // 99
+    |state| sbb(state, Register::D),              // 9A
+    |state| sbb(state, Register::E),              // 9B
+    |state| sbb(state, Register::H),              // 9C
+    |state| sbb(state, Register::L),              // 9D
+    |state| sbb(state, Register::M),              // 9E
+    |state| sbb(state, Register::A),              // 9F
+    |state| ana(state, Register::B),              // A0
+    |state| ana(state, Register::C),              // A1
+    |state| ana(state, Register::D),              // A2
+    |state| ana(state, Register::E),              // A3
+    |state| ana(state, Register::H),              // A4
+    |state| ana(state, Register::L),              // A5
+    |state| ana(state, Register::M),              // A6
+    |state| ana(state, Register::A),              // A7
+    |state| xra(state, Register::B),              // A8
+    |state| xra(state, Register::C),              // A9
+    |state| xra(state, Register::D),              // AA
+    |state| xra(state, Register::E),              // AB
+    |state| xra(state, Register::H),              // AC
+    |state| xra(state, Register::L),              // AD
+    |state| xra(state, Register::M),              // AE
+    |state| xra(state, Register::A),              // AF
+    |state| ora(state, Register::B),              // B0
+    |state| ora(state, Register::C),              // B1
+    |state| ora(state, Register::D),              // B2
+    |state| ora(state, Register::E),              // B3
+    |state| ora(state, Register::H),              // B4
+    |state| ora(state, Register::L),              // B5
+    |state| ora(state, Register::M),              // B6
+    |state| ora(state, Register::A),              // B7
+    |state| cmp(state, Register::B),              // B8
+    |state| cmp(state, Register::C),              // B9
+    |state| cmp(state, Register::D),              // BA
+    |state| cmp(state, Register::E),              // BB
+    |state| cmp(state, Register::H),              // BC
+    |state| cmp(state, Register::L),              // BD
+    |state| cmp(state, Register::M),              // BE
+    |state| cmp(state, Register::A),              // BF
+    rnz,                                          // C0
+    |state| pop(state, RegisterPair::BC),         // C1
+    jnz,                                          // C2
+    jmp,                                          // C3
+    cnz,                                          // C4
+    |state| push(state, RegisterPair::BC),        // C5
+    adi,                                          // C6
+    |state| rst(state, 0),                        // C7
+    rz,                                           // C8
+    ret,                                          // C9
+    jz,                                           // CA
+    undocumented,                                 // CB
+    cz,                                           // CC
+    call,                                         // CD
+    aci,                                          // CE
+    |state| rst(state, 1),                        // CF
+    rnc,                                          // D0
+    |state| pop(state, RegisterPair::DE),         // D1
+    jnc,                                          // D2
+    out,                                          // D3
+    cnc,                                          // D4
+    |state| push(state, RegisterPair::DE),        // D5
+    sui,                                          // D6
+    |state| rst(state, 2),                        // D7
+    rc,                                           // D8
+    undocumented,                                 // D9
+    jc,                                           // DA
+    inp,                                          // DB
+    cc,                                           // DC
+    undocumented,                                 // DD
+    sbi,                                          // DE
+    |state| rst(state, 3),                        // DF
+    rpo,                                          // E0
+    |state| pop(state, RegisterPair::HL),         // E1
+    jpo,                                          // E2
+    xthl,                                         // E3
+    cpo,                                          // E4
+    |state| push(state, RegisterPair::HL),        // E5
+    ani,                                          // E6
+    |state| rst(state, 4),                        // E7
+    rpe,                                          // E8
+    pchl,                                         // E9
+    jpe,                                          // EA
+    xchg,                                         // EB
+    cpe,                                          // EC
+    undocumented,                                 // ED
+    xri,                                          // EE
+    |state| rst(state, 5),                        // EF
+    rp,                                           // F0
+    pop_psw,                                      // F1
+    jp,                                           // F2
+    di,                                           // F3
+    cp,                                           // F4
+    push_psw,                                     // F5
+    ori,                                          // F6
+    |state| rst(state, 6),                        // F7
+    rm,                                           // F8
+    sphl,                                         // F9
+    jm,                                           // FA
+    ei,                                           // FB
+    cm,                                           // FC
+    undocumented,                                 // FD
+    cpi,                                          // FE
+    |state| rst(state, 7),                        // FF
+];
+
+fn undocumented(state: &mut data_types::State8080) {
+    unimplemented_instruction(state.memory[state.pc as usize]);
 }
 
 #[inline(always)]
@@ -401,7 +492,7 @@ fn mvi(state: &mut data_types::State8080, register: data_types::Register) {
 fn rlc(state: &mut data_types::State8080) {
     // TODO: Double check this implementation
     let x = state.a;
-    state.a = (x << 1) | (x >> 7);
+    state.a = x.rotate_left(1);
     state.cc.cy = (x & 0x80) == 0x80;
     state.pc += 1;
 }
@@ -441,7 +532,7 @@ fn dcx(state: &mut data_types::State8080, register_pair: &data_types::RegisterPa
 #[inline(always)]
 fn rrc(state: &mut data_types::State8080) {
     let x = state.a;
-    state.a = (x >> 1) | (x << 7); // Rotate right through carry
+    state.a = x.rotate_right(1); // Rotate right through carry
     state.cc.cy = (x & 0x01) == 0x01; // Carry is the bit shifted out
     state.pc += 1;
 }
@@ -474,6 +565,7 @@ fn shld(state: &mut data_types::State8080) {
 fn daa(state: &mut data_types::State8080) {
     let mut temp: u16 = state.a as u16;
     if state.cc.ac || (temp & 0x0F) > 9 {
+        state.cc.ac = (temp & 0x0F) > 9;
         temp = temp.wrapping_add(6);
     }
     if state.cc.cy || temp > 0x9F {
@@ -594,6 +686,7 @@ fn add(state: &mut data_types::State8080, src: data_types::Register) {
     };
     let answer = state.a as u16 + val as u16;
     set_flag_add(state, answer);
+    state.cc.ac = half_carry(state.a, val, answer);
     state.a = (answer & 0xff) as u8;
     state.pc += 1;
 }
@@ -615,6 +708,7 @@ fn adc(state: &mut data_types::State8080, src: data_types::Register) {
     };
     let answer = state.a as u16 + val as u16 + state.cc.cy as u16;
     set_flags_adc(state, answer);
+    state.cc.ac = half_carry(state.a, val, answer);
     state.a = (answer & 0xff) as u8;
     state.pc += 1;
 }
@@ -639,7 +733,7 @@ fn sub(state: &mut data_types::State8080, src: data_types::Register) {
     state.cc.s = (result & 0x80) != 0;
     state.cc.p = parity(result);
     state.cc.cy = state.a < val;
-    state.cc.ac = (state.a & 0x0F) < (val & 0x0F);
+    state.cc.ac = half_carry_sub(state.a, val, false);
     state.a = result;
     state.pc += 1;
 }
@@ -659,12 +753,14 @@ fn sbb(state: &mut data_types::State8080, src: data_types::Register) {
             state.memory[addr]
         }
     };
-    state.a = state.a.wrapping_sub(val).wrapping_sub(state.cc.cy as u8);
-    state.cc.z = state.a == 0;
-    state.cc.s = (state.a & 0x80) != 0;
-    state.cc.p = parity(state.a);
-    state.cc.cy = state.a < val;
-    state.cc.ac = (state.a & 0x0F) < (val & 0x0F);
+    let borrow = state.cc.cy;
+    let result = state.a.wrapping_sub(val).wrapping_sub(borrow as u8);
+    state.cc.z = result == 0;
+    state.cc.s = (result & 0x80) != 0;
+    state.cc.p = parity(result);
+    state.cc.cy = (state.a as u16) < val as u16 + borrow as u16;
+    state.cc.ac = half_carry_sub(state.a, val, borrow);
+    state.a = result;
     state.pc += 1;
 }
 
@@ -763,7 +859,7 @@ fn cmp(state: &mut data_types::State8080, src: data_types::Register) {
     state.cc.s = (result & 0x80) != 0;
     state.cc.p = parity(result);
     state.cc.cy = state.a < value;
-    state.cc.ac = (state.a & 0x0F) < (value & 0x0F);
+    state.cc.ac = half_carry_sub(state.a, value, false);
     state.pc += 1;
 }
 
@@ -864,8 +960,10 @@ fn push(state: &mut data_types::State8080, register_pair: data_types::RegisterPa
 #[inline(always)]
 fn adi(state: &mut data_types::State8080) {
     // ADI instruction add next immediate value in memory (from what i understand)
-    let answer: u16 = state.a as u16 + state.memory[(state.pc + 1) as usize] as u16;
+    let val = state.memory[(state.pc + 1) as usize];
+    let answer: u16 = state.a as u16 + val as u16;
     set_flag_add(state, answer);
+    state.cc.ac = half_carry(state.a, val, answer);
     state.a = (answer & 0xff) as u8;
     state.pc += 2;
 }
@@ -881,7 +979,7 @@ fn rst(state: &mut data_types::State8080, num: u8) {
 
 #[inline(always)]
 fn rz(state: &mut data_types::State8080) {
-    if state.cc.z == true {
+    if state.cc.z {
         let return_address = pop_stack(state);
         state.pc = return_address;
     } else {
@@ -935,6 +1033,7 @@ fn aci(state: &mut data_types::State8080) {
     state.cc.s = (result & 0x80) != 0;
     state.cc.p = parity(result);
     state.cc.cy = (state.a as u16) + (imm as u16) + (carry as u16) > 0xFF;
+    state.cc.ac = half_carry(state.a, imm, result as u16);
     state.a = result;
     state.pc += 2;
 }
@@ -985,6 +1084,7 @@ fn sui(state: &mut data_types::State8080) {
     state.cc.s = (result & 0x80) != 0;
     state.cc.p = parity(result);
     state.cc.cy = state.a < imm; // Set carry if there's a borrow
+    state.cc.ac = half_carry_sub(state.a, imm, false);
     state.a = result;
     state.pc += 2;
 }
@@ -1013,7 +1113,6 @@ fn jc(state: &mut data_types::State8080) {
 fn inp(state: &mut data_types::State8080) {
     let port = state.memory[(state.pc + 1) as usize];
     state.a = handle_in(state, port);
-    // state.in_port1 = port;
     state.pc += 2;
 }
 
@@ -1036,6 +1135,7 @@ fn sbi(state: &mut data_types::State8080) {
     state.cc.s = (result & 0x80) != 0; // Sign flag
     state.cc.p = parity(result); // Parity flag
     state.cc.cy = (state.a as u16) < (imm as u16 + borrow as u16); // Carry flag (if borrow)
+    state.cc.ac = half_carry_sub(state.a, imm, borrow == 1);
 
     state.a = result;
     state.pc += 2; // Increment the program counter by 2 because `SBI` uses the immediate value
@@ -1157,7 +1257,7 @@ fn pop_psw(state: &mut data_types::State8080) {
 
 #[inline(always)]
 fn jp(state: &mut data_types::State8080) {
-    if state.cc.s {
+    if !state.cc.s {
         state.pc = get_jmp_target_address(state);
     } else {
         state.pc += 3;
@@ -1172,7 +1272,7 @@ fn di(state: &mut data_types::State8080) {
 
 #[inline(always)]
 fn cp(state: &mut data_types::State8080) {
-    if state.cc.s {
+    if !state.cc.s {
         call(state);
     } else {
         state.pc += 3;
@@ -1201,10 +1301,10 @@ fn ori(state: &mut data_types::State8080) {
 #[inline(always)]
 fn rm(state: &mut data_types::State8080) {
     if state.cc.s {
-        state.pc += 1;
-    } else {
         let return_address = pop_stack(state);
         state.pc = return_address;
+    } else {
+        state.pc += 1;
     }
 }
 
@@ -1246,6 +1346,7 @@ fn cpi(state: &mut data_types::State8080) {
     state.cc.s = (result & 0x80) != 0;
     state.cc.p = parity(result);
     state.cc.cy = state.a < imm; // Set carry if there's a borrow
+    state.cc.ac = half_carry_sub(state.a, imm, false);
     state.pc += 2;
 }
 
@@ -1266,9 +1367,23 @@ fn set_flag_add(state: &mut data_types::State8080, res: u16) {
     state.cc.p = parity((res & 0xff) as u8);
 }
 
+/// Carry out of bit 3 of an addition, for the auxiliary carry flag.
+#[inline(always)]
+fn half_carry(a: u8, operand: u8, result: u16) -> bool {
+    (a ^ operand ^ result as u8) & 0x10 != 0
+}
+
+/// Carry out of bit 3 of a subtraction, for the auxiliary carry flag. The
+/// 8080 subtracts by adding the complement of `operand` and the inverted
+/// borrow, so the flag is set when the low nibble does not borrow.
+#[inline(always)]
+fn half_carry_sub(a: u8, operand: u8, borrow: bool) -> bool {
+    (a & 0x0F) + (!operand & 0x0F) + u8::from(!borrow) > 0x0F
+}
+
 #[inline(always)]
 fn parity(value: u8) -> bool {
-    value.count_ones() % 2 == 0
+    value.count_ones().is_multiple_of(2)
 }
 
 #[inline(always)]
@@ -1287,8 +1402,9 @@ fn set_flags_dcr(state: &mut data_types::State8080, value_before: u8, result: u8
     // Parity Flag
     state.cc.p = parity(result);
 
-    // Auxiliary Carry Flag
-    state.cc.ac = (value_before & 0x0F) == 0x00;
+    // Auxiliary Carry Flag: the 8080 adds 0xFF, which carries out of bit 3
+    // unless the low nibble was 0
+    state.cc.ac = (value_before & 0x0F) != 0x00;
 
     // Carry Flag is not affected
 }
@@ -1371,42 +1487,22 @@ fn get_jmp_target_address(state: &data_types::State8080) -> u16 {
         | state.memory[(state.pc + 1) as usize] as u16
 }
 
+/// Without a bus, nothing answers: `IN` reads 0 and `OUT` goes nowhere.
 #[inline(always)]
 fn handle_in(state: &mut data_types::State8080, port: u8) -> u8 {
-    match port {
-        0 => 0xf,
-        1 => state.in_port1,
-        2 => 0,
-        3 => {
-            // Return result from shift register (shift1 << 8 | shift0) >> shift_offset
-            let shift_val = (state.shift1 as u16) << 8 | state.shift0 as u16;
-            (shift_val >> (8 - state.shift_offset)) as u8
-        }
-        _ => {
-            // If the port is not implemented, return 0
-            0
-        }
-    }
+    state.io.as_mut().map_or(0, |io| io.input(port))
 }
 
 #[inline(always)]
 fn handle_out(state: &mut data_types::State8080, port: u8, value: u8) {
-    match port {
-        2 => {
-            // Set the shift register's offset
-            state.shift_offset = value & 0x7;
-        }
-        4 => {
-            // Load shift register: move shift1 to shift0, and load value into shift1
-            state.shift0 = state.shift1;
-            state.shift1 = value;
-        }
-        _ => {
-            // Handle other ports (e.g., sound-related ports, debug ports)
-        }
+    if let Some(io) = state.io.as_mut() {
+        io.output(port, value);
     }
 }
 
+/// Interrupts with `RST interrupt_num`, for machines whose hardware only
+/// ever puts an `RST` on the bus. [`acknowledge_interrupt`] models the INTA
+/// cycles in full.
 #[inline(always)]
 pub fn generate_interrupt(state: &mut data_types::State8080, interrupt_num: u16) {
     // Only generate the interrupt if interrupts are enabled
@@ -1427,6 +1523,76 @@ pub fn generate_interrupt(state: &mut data_types::State8080, interrupt_num: u16)
     }
 }
 
+/// Something that can interrupt the CPU and answers its interrupt
+/// acknowledge (INTA) cycles with the instruction to run, one byte per
+/// cycle: a one-byte `RST`, say, or the three bytes of a `CALL` from an
+/// interrupt controller.
+pub trait InterruptSource {
+    /// Whether an interrupt is being requested.
+    fn requesting(&mut self) -> bool;
+    /// The byte put on the bus by INTA cycle `cycle`, 0 being the cycle
+    /// that fetches the opcode.
+    fn acknowledge(&mut self, cycle: usize) -> u8;
+}
+
+/// Takes an interrupt from `source` if interrupts are enabled and it is
+/// requesting one. Interrupts are disabled and the instruction fetched by the
+/// INTA cycles is executed in place of the one at PC, which is not advanced,
+/// so a handler returns to it. A conditional call that is not taken, like a
+/// `NOP`, leaves PC where it was. Returns the cycles taken.
+///
+/// # Panics
+///
+/// Panics if the source supplies anything other than `NOP`, `RST`, `CALL` or
+/// a conditional call.
+pub fn acknowledge_interrupt(
+    state: &mut data_types::State8080,
+    source: &mut impl InterruptSource,
+) -> Option<u8> {
+    if !state.int_enable || !source.requesting() {
+        return None;
+    }
+    state.int_enable = false;
+
+    let opcode = source.acknowledge(0);
+    let target = match opcode {
+        0x00 => return Some(CYCLE_TABLE[0x00]),
+        _ if opcode & 0xC7 == 0xC7 => u16::from(opcode & 0x38),
+        _ if opcode == 0xCD || opcode & 0xC7 == 0xC4 => {
+            let target = u16::from_le_bytes([source.acknowledge(1), source.acknowledge(2)]);
+            if opcode != 0xCD && !condition(state, opcode) {
+                return Some(CYCLE_TABLE[opcode as usize]);
+            }
+            target
+        }
+        _ => panic!(
+            "Instruction 0x{:02X} cannot be taken during an interrupt acknowledge",
+            opcode
+        ),
+    };
+
+    let [low, high] = state.pc.to_le_bytes();
+    state.sp = state.sp.wrapping_sub(2);
+    state.memory[state.sp as usize] = low;
+    state.memory[state.sp.wrapping_add(1) as usize] = high;
+    state.pc = target;
+    // A call that is taken costs the same as an unconditional one
+    let opcode = if opcode & 0x07 == 0x04 { 0xCD } else { opcode };
+    Some(CYCLE_TABLE[opcode as usize])
+}
+
+/// Whether the condition in bits 3-5 of a conditional jump, call or return
+/// holds: NZ, Z, NC, C, PO, PE, P or M.
+fn condition(state: &data_types::State8080, opcode: u8) -> bool {
+    let flag = match (opcode >> 4) & 0x03 {
+        0 => state.cc.z,
+        1 => state.cc.cy,
+        2 => state.cc.p,
+        _ => state.cc.s,
+    };
+    flag == (opcode & 0x08 != 0)
+}
+
 fn set_flags_inr(state: &mut data_types::State8080, value_before: u8, result: u8) {
     // Zero Flag
     state.cc.z = result == 0;
@@ -1456,11 +1622,11 @@ fn flags_zsp(state: &mut data_types::State8080, value: u8) {
     state.cc.p = parity(value);
 }
 
+/// DAA sets the carry itself and never clears it.
 fn set_flags_daa(state: &mut data_types::State8080, result: u16) {
     state.cc.z = (result & 0xff) == 0;
     state.cc.s = (result & 0x80) != 0;
     state.cc.p = parity(result as u8);
-    state.cc.cy = result > 0xff;
 }
 
 fn set_flags_adc(state: &mut data_types::State8080, result: u16) {
@@ -1,24 +1,17 @@
+use super::history::InstructionHistory;
+use super::io::IoBus;
+use super::trace::Tracer;
 use core::fmt;
 use serde::Serialize;
 use std::fmt::{Debug, Formatter};
 
-#[derive(Clone, Serialize)]
+#[derive(Clone, Default, Serialize)]
 pub struct InstructionInfo {
     pub address: u16,
     pub opcode: u8,
     pub mnemonic: String,
 }
 
-impl Default for InstructionInfo {
-    fn default() -> Self {
-        InstructionInfo {
-            address: 0,
-            opcode: 0,
-            mnemonic: String::new(),
-        }
-    }
-}
-
 #[derive(Default, Serialize)]
 pub struct ConditionCodes {
     pub z: bool,
@@ -39,21 +32,19 @@ pub struct State8080 {
     pub sp: u16,
     pub pc: u16,
 
-    pub shift0: u8,
-    pub shift1: u8,
-    pub shift_offset: u8,
-
-    pub in_port1: u8,
+    /// Board I/O, answering every `IN` and `OUT`.
+    pub io: Option<Box<dyn IoBus>>,
 
     pub memory: [u8; 0x10000], // 64KB memory
     pub cc: ConditionCodes,
     pub int_enable: bool,
     pub cycles: i32,
 
-    // Instruction history circular buffer
-    pub instruction_history: Vec<Option<InstructionInfo>>,
-    pub instruction_index: usize,
-    pub instruction_count: usize,
+    // Recently executed instructions
+    pub history: InstructionHistory,
+
+    /// Opt-in per-instruction trace output.
+    pub tracer: Option<Box<Tracer>>,
 }
 
 impl State8080 {
@@ -85,44 +76,9 @@ impl State8080 {
         self.cc.ac = (flags & 0x10) != 0;
     }
 
-    pub fn add_instruction(&mut self, address: u16, opcode: u8, mnemonic: String) {
-        let instruction = InstructionInfo {
-            address,
-            opcode,
-            mnemonic,
-        };
-
-        // Store at current index
-        self.instruction_history[self.instruction_index] = Some(instruction);
-
-        // Move to next position (wraps around at 50)
-        self.instruction_index = (self.instruction_index + 1) % 50;
-
-        // Track total count (caps at 50)
-        if self.instruction_count < 50 {
-            self.instruction_count += 1;
-        }
-    }
-
+    /// Recently executed instructions, oldest first, with their mnemonics.
     pub fn get_instructions_in_order(&self) -> Vec<InstructionInfo> {
-        let mut result = Vec::new();
-
-        // If buffer is full, start from current index (oldest)
-        let start_idx = if self.instruction_count == 50 {
-            self.instruction_index
-        } else {
-            0
-        };
-
-        // Collect instructions in chronological order
-        for i in 0..self.instruction_count {
-            let idx = (start_idx + i) % 50;
-            if let Some(ref instruction) = self.instruction_history[idx] {
-                result.push(instruction.clone());
-            }
-        }
-
-        result
+        self.history.instructions(None)
     }
 }
 
@@ -139,24 +95,21 @@ impl Default for State8080 {
             sp: 0,
             pc: 0,
 
-            shift0: 0,
-            shift1: 0,
-            shift_offset: 0,
-            in_port1: 0,
+            io: None,
 
             memory: [0; 0x10000],
             cc: ConditionCodes::default(),
             int_enable: false,
             cycles: 0,
 
-            // Initialize instruction history
-            instruction_history: vec![None; 50],
-            instruction_index: 0,
-            instruction_count: 0,
+            history: InstructionHistory::default(),
+
+            tracer: None,
         }
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Register {
     A,
     B,
@@ -199,6 +152,7 @@ impl Register {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum RegisterPair {
     BC,
     DE,
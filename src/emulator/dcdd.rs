@@ -0,0 +1,203 @@
+#![cfg(not(feature = "wasm"))]
+//! The MITS 88-DCDD floppy disk controller.
+//!
+//! The controller moves one byte per `IN` or `OUT`; software steps the head,
+//! watches the sectors come round and copies each 137-byte sector itself.
+//! Its three ports, with every status bit active low:
+//!
+//! | Port | In | Out |
+//! |------|----|-----|
+//! | 0x08 | Status: bit 0 ready for a write byte, 1 head may move, 2 head loaded, 5 interrupts on, 6 at track 0, 7 read byte ready | Select drive 0 to 15; bit 7 deselects |
+//! | 0x09 | Sector position: bit 0 sector true, bits 1-5 sector number | Control: bit 0 step in, 1 step out, 2 load head, 3 unload head, 4 and 5 interrupts on and off, 7 start writing |
+//! | 0x0A | Read data | Write data |
+//!
+//! Disks are [`DiskFormat::Mits`] images. The disk turns one sector for
+//! every two reads of the sector position, which shows each sector first as
+//! true and then as passing. Writes to a read-only image are dropped.
+
+use super::disk::{DiskFormat, DiskImage, TRACKS};
+
+pub const DRIVES: usize = 16;
+pub const STATUS: u8 = 0x08;
+pub const SECTOR_POSITION: u8 = 0x09;
+pub const DATA: u8 = 0x0A;
+
+const SECTOR_SIZE: usize = DiskFormat::Mits.sector_size();
+const SECTORS: u8 = DiskFormat::Mits.sectors() as u8;
+
+struct Drive {
+    image: DiskImage,
+    track: u8,
+    sector: u8,
+    sector_true: bool,
+    head_loaded: bool,
+    /// Position within the sector being read or written.
+    byte: usize,
+    /// Bytes written so far, while writing.
+    write: Option<Vec<u8>>,
+}
+
+#[derive(Default)]
+pub struct Dcdd {
+    drives: [Option<Drive>; DRIVES],
+    selected: Option<usize>,
+    interrupts: bool,
+}
+
+impl Dcdd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts `image`, a [`DiskFormat::Mits`] disk, in `drive`, returning the
+    /// disk that was there. The head starts at track 0.
+    pub fn mount(&mut self, drive: usize, image: DiskImage) -> Option<DiskImage> {
+        assert_eq!(image.format(), DiskFormat::Mits, "not a MITS 8\" image");
+        let old = self.eject(drive);
+        self.drives[drive] = Some(Drive {
+            image,
+            track: 0,
+            sector: 0,
+            sector_true: false,
+            head_loaded: false,
+            byte: 0,
+            write: None,
+        });
+        old
+    }
+
+    pub fn eject(&mut self, drive: usize) -> Option<DiskImage> {
+        let mut old = self.drives[drive].take()?;
+        old.finish_write();
+        Some(old.image)
+    }
+
+    pub fn disk(&self, drive: usize) -> Option<&DiskImage> {
+        self.drives[drive].as_ref().map(|drive| &drive.image)
+    }
+
+    fn drive(&mut self) -> Option<&mut Drive> {
+        self.drives[self.selected?].as_mut()
+    }
+
+    pub fn input(&mut self, port: u8) -> u8 {
+        let interrupts = self.interrupts;
+        let Some(drive) = self.drive() else {
+            return 0xFF;
+        };
+        match port {
+            STATUS => {
+                let mut status = 0xFF;
+                let mut clear = |bit: u8, condition: bool| {
+                    if condition {
+                        status &= !(1 << bit);
+                    }
+                };
+                clear(0, drive.write.is_some());
+                clear(1, true);
+                clear(2, drive.head_loaded);
+                clear(3, true);
+                clear(4, true);
+                clear(5, interrupts);
+                clear(6, drive.track == 0);
+                clear(7, drive.head_loaded && drive.write.is_none());
+                status
+            }
+            SECTOR_POSITION if drive.head_loaded => {
+                if drive.sector_true {
+                    drive.sector_true = false;
+                    0xC1 | drive.sector << 1
+                } else {
+                    drive.finish_write();
+                    drive.sector = (drive.sector + 1) % SECTORS;
+                    drive.sector_true = true;
+                    drive.byte = 0;
+                    0xC0 | drive.sector << 1
+                }
+            }
+            DATA if drive.head_loaded => {
+                let byte = match drive.byte {
+                    byte if byte < SECTOR_SIZE => drive.image.data()[drive.offset() + byte],
+                    _ => 0,
+                };
+                drive.byte += 1;
+                byte
+            }
+            _ => 0xFF,
+        }
+    }
+
+    pub fn output(&mut self, port: u8, value: u8) {
+        match port {
+            STATUS => {
+                if let Some(drive) = self.drive() {
+                    drive.finish_write();
+                }
+                let drive = (value & 0x0F) as usize;
+                self.selected = (value & 0x80 == 0).then_some(drive);
+            }
+            SECTOR_POSITION => {
+                if value & 0x10 != 0 {
+                    self.interrupts = true;
+                }
+                if value & 0x20 != 0 {
+                    self.interrupts = false;
+                }
+                let Some(drive) = self.drive() else {
+                    return;
+                };
+                if value & 0x01 != 0 && (drive.track as usize) < TRACKS - 1 {
+                    drive.finish_write();
+                    drive.track += 1;
+                }
+                if value & 0x02 != 0 && drive.track > 0 {
+                    drive.finish_write();
+                    drive.track -= 1;
+                }
+                if value & 0x04 != 0 {
+                    drive.head_loaded = true;
+                }
+                if value & 0x08 != 0 {
+                    drive.finish_write();
+                    drive.head_loaded = false;
+                }
+                if value & 0x80 != 0 {
+                    drive.write = Some(Vec::with_capacity(SECTOR_SIZE));
+                }
+            }
+            DATA => {
+                let Some(drive) = self.drive() else {
+                    return;
+                };
+                if let Some(write) = &mut drive.write {
+                    write.push(value);
+                    if write.len() == SECTOR_SIZE {
+                        drive.finish_write();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Drive {
+    fn offset(&self) -> usize {
+        (self.track as usize * SECTORS as usize + self.sector as usize) * SECTOR_SIZE
+    }
+
+    /// Writes what has been sent of the current sector, the rest as zeros.
+    fn finish_write(&mut self) {
+        let Some(mut bytes) = self.write.take() else {
+            return;
+        };
+        if self.image.is_read_only() || bytes.is_empty() {
+            return;
+        }
+        bytes.resize(SECTOR_SIZE, 0);
+        let start = self.offset();
+        // An image file that cannot be written loses the sector, as a bad
+        // disk would
+        let _ = self.image.write(start..start + SECTOR_SIZE, &bytes);
+    }
+}
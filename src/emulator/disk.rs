@@ -0,0 +1,450 @@
+#![cfg(not(feature = "wasm"))]
+//! Booting CP/M from 8" floppy disk images.
+//!
+//! [`DiskSystem`] is an 8080 with 64K of RAM, a console port and a simple
+//! floppy controller for up to four IBM 3740 single-sided, single-density
+//! images: 77 tracks of 26 sectors of 128 bytes, 256,256 bytes in all.
+//! Unlike [`Cpm`](super::cpm::Cpm), nothing is trapped; the BIOS on the disk
+//! drives the hardware through these ports, laid out as in z80pack's
+//! `cpmsim` so a BIOS written for it boots unchanged:
+//!
+//! | Port   | Direction | Use |
+//! |--------|-----------|-----|
+//! | 0      | in        | Console status, 0xFF when a key is waiting |
+//! | 1      | in/out    | Console data |
+//! | 10     | in/out    | Drive, 0 to 3 |
+//! | 11     | in/out    | Track, 0 to 76 |
+//! | 12     | in/out    | Sector, 1 to 26 |
+//! | 13     | out       | Command: 0 reads the sector into memory at the DMA address, 1 writes it from there |
+//! | 14     | in        | [`DiskStatus`] of the last command |
+//! | 15, 16 | in/out    | DMA address, low and high byte |
+//!
+//! Booting copies track 0, sector 1 of drive A: to 0x0000 and jumps there;
+//! that loader reads in the rest of the system.
+
+use super::console::{Console, StdConsole};
+use super::cpm::Exit;
+use super::cpu::emulate_8080_op;
+use super::data_types::State8080;
+use super::io::IoBus;
+use std::any::Any;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+pub const TRACKS: usize = 77;
+pub const SECTORS: usize = 26;
+pub const SECTOR_SIZE: usize = 128;
+pub const DRIVES: usize = 4;
+
+pub const CONSOLE_STATUS: u8 = 0;
+pub const CONSOLE_DATA: u8 = 1;
+pub const DRIVE: u8 = 10;
+pub const TRACK: u8 = 11;
+pub const SECTOR: u8 = 12;
+pub const COMMAND: u8 = 13;
+pub const STATUS: u8 = 14;
+pub const DMA_LOW: u8 = 15;
+pub const DMA_HIGH: u8 = 16;
+
+/// What port 14 reads after a command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DiskStatus {
+    Ok = 0,
+    /// No disk in the selected drive, or no such drive.
+    IllegalDrive = 1,
+    IllegalTrack = 2,
+    IllegalSector = 3,
+    ReadError = 5,
+    /// The disk is read-only, or its image file could not be written.
+    WriteError = 6,
+    IllegalCommand = 7,
+}
+
+#[derive(Debug)]
+pub enum DiskError {
+    Io { path: PathBuf, error: io::Error },
+    Size { size: usize, format: DiskFormat },
+    NoBootDisk,
+}
+
+impl fmt::Display for DiskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiskError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            DiskError::Size { size, format } => write!(
+                f,
+                "disk image is {} bytes, a {} image is {}",
+                size,
+                format,
+                format.image_size()
+            ),
+            DiskError::NoBootDisk => write!(f, "no disk in drive A:"),
+        }
+    }
+}
+
+impl std::error::Error for DiskError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DiskError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The two 8" layouts images come in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiskFormat {
+    /// IBM 3740 single density: 77 tracks of 26 sectors of 128 bytes, as
+    /// [`DiskSystem`] reads them.
+    Ibm3740,
+    /// MITS 88-DCDD: 77 tracks of 32 sectors of 137 bytes, as the Altair's
+    /// [`Dcdd`](super::dcdd::Dcdd) reads them.
+    Mits,
+}
+
+impl DiskFormat {
+    pub const fn sectors(self) -> usize {
+        match self {
+            DiskFormat::Ibm3740 => SECTORS,
+            DiskFormat::Mits => 32,
+        }
+    }
+
+    pub const fn sector_size(self) -> usize {
+        match self {
+            DiskFormat::Ibm3740 => SECTOR_SIZE,
+            DiskFormat::Mits => 137,
+        }
+    }
+
+    pub const fn image_size(self) -> usize {
+        TRACKS * self.sectors() * self.sector_size()
+    }
+}
+
+impl fmt::Display for DiskFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiskFormat::Ibm3740 => write!(f, "IBM 3740"),
+            DiskFormat::Mits => write!(f, "MITS 8\""),
+        }
+    }
+}
+
+/// The contents of one floppy. Images opened from a file are written
+/// through to it a sector at a time unless they are read-only.
+#[derive(Clone, Debug)]
+pub struct DiskImage {
+    data: Vec<u8>,
+    format: DiskFormat,
+    path: Option<PathBuf>,
+    read_only: bool,
+}
+
+impl DiskImage {
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        format: DiskFormat,
+        read_only: bool,
+    ) -> Result<Self, DiskError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|error| DiskError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        let mut image = Self::from_bytes(data, format)?;
+        image.path = Some(path.to_path_buf());
+        image.read_only = read_only;
+        Ok(image)
+    }
+
+    /// An image that lives only in memory.
+    pub fn from_bytes(data: Vec<u8>, format: DiskFormat) -> Result<Self, DiskError> {
+        if data.len() != format.image_size() {
+            return Err(DiskError::Size {
+                size: data.len(),
+                format,
+            });
+        }
+        Ok(DiskImage {
+            data,
+            format,
+            path: None,
+            read_only: false,
+        })
+    }
+
+    /// A disk with every byte 0xE5, which CP/M on an IBM 3740 disk reads as
+    /// an empty directory.
+    pub fn blank(format: DiskFormat) -> Self {
+        DiskImage {
+            data: vec![0xE5; format.image_size()],
+            format,
+            path: None,
+            read_only: false,
+        }
+    }
+
+    pub fn format(&self) -> DiskFormat {
+        self.format
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    fn sector(track: u8, sector: u8) -> Result<Range<usize>, DiskStatus> {
+        if track as usize >= TRACKS {
+            return Err(DiskStatus::IllegalTrack);
+        }
+        if sector == 0 || sector as usize > SECTORS {
+            return Err(DiskStatus::IllegalSector);
+        }
+        let start = (track as usize * SECTORS + sector as usize - 1) * SECTOR_SIZE;
+        Ok(start..start + SECTOR_SIZE)
+    }
+
+    /// Stores `bytes` at `range`, and in the image file if there is one.
+    pub(crate) fn write(&mut self, range: Range<usize>, bytes: &[u8]) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            let mut file = OpenOptions::new().write(true).open(path)?;
+            file.seek(SeekFrom::Start(range.start as u64))?;
+            file.write_all(bytes)?;
+        }
+        self.data[range].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// The console and floppy ports. Disk commands need memory, which an
+/// [`IoBus`] cannot reach, so `OUT` to the command port only records the
+/// command and [`DiskSystem::step`] carries it out after the instruction.
+struct DiskBus<C> {
+    console: C,
+    drives: [Option<DiskImage>; DRIVES],
+    drive: u8,
+    track: u8,
+    sector: u8,
+    dma: u16,
+    status: DiskStatus,
+    command: Option<u8>,
+    input_ended: bool,
+}
+
+impl<C: Console + Send + 'static> IoBus for DiskBus<C> {
+    fn input(&mut self, port: u8) -> u8 {
+        match port {
+            CONSOLE_STATUS => {
+                if self.console.ready() {
+                    0xFF
+                } else {
+                    self.input_ended = self.console.at_end();
+                    0x00
+                }
+            }
+            CONSOLE_DATA => self.console.read().unwrap_or_else(|| {
+                self.input_ended = true;
+                0x1A
+            }),
+            DRIVE => self.drive,
+            TRACK => self.track,
+            SECTOR => self.sector,
+            STATUS => self.status as u8,
+            DMA_LOW => self.dma as u8,
+            DMA_HIGH => (self.dma >> 8) as u8,
+            _ => 0xFF,
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        match port {
+            CONSOLE_DATA => self.console.write(value),
+            DRIVE => self.drive = value,
+            TRACK => self.track = value,
+            SECTOR => self.sector = value,
+            COMMAND => self.command = Some(value),
+            DMA_LOW => self.dma = self.dma & 0xFF00 | value as u16,
+            DMA_HIGH => self.dma = self.dma & 0x00FF | (value as u16) << 8,
+            _ => {}
+        }
+    }
+}
+
+impl<C> DiskBus<C> {
+    fn transfer(&mut self, command: u8, memory: &mut [u8]) -> DiskStatus {
+        let Some(Some(disk)) = self.drives.get_mut(self.drive as usize) else {
+            return DiskStatus::IllegalDrive;
+        };
+        let range = match DiskImage::sector(self.track, self.sector) {
+            Ok(range) => range,
+            Err(status) => return status,
+        };
+        let dma = self.dma as usize;
+        let addresses = (dma..dma + SECTOR_SIZE).map(|address| address & 0xFFFF);
+        match command {
+            0 => {
+                for (address, &byte) in addresses.zip(&disk.data[range]) {
+                    memory[address] = byte;
+                }
+                DiskStatus::Ok
+            }
+            1 if disk.read_only => DiskStatus::WriteError,
+            1 => {
+                let bytes: Vec<u8> = addresses.map(|address| memory[address]).collect();
+                match disk.write(range, &bytes) {
+                    Ok(()) => DiskStatus::Ok,
+                    Err(_) => DiskStatus::WriteError,
+                }
+            }
+            _ => DiskStatus::IllegalCommand,
+        }
+    }
+}
+
+/// A CP/M machine that boots from disk images.
+pub struct DiskSystem<C: Console + Send + 'static = StdConsole> {
+    state: State8080,
+    _console: PhantomData<C>,
+}
+
+impl<C: Console + Send + 'static> DiskSystem<C> {
+    /// A machine with empty drives; mount a system disk in drive A: and
+    /// [`boot`](Self::boot) it.
+    pub fn new(console: C) -> Self {
+        let mut state = State8080::default();
+        state.history.set_capacity(0);
+        state.io = Some(Box::new(DiskBus {
+            console,
+            drives: Default::default(),
+            drive: 0,
+            track: 0,
+            sector: 1,
+            dma: 0,
+            status: DiskStatus::Ok,
+            command: None,
+            input_ended: false,
+        }));
+        DiskSystem {
+            state,
+            _console: PhantomData,
+        }
+    }
+
+    fn bus(&self) -> &DiskBus<C> {
+        let io: &dyn Any = self.state.io.as_deref().expect("machine has a bus");
+        io.downcast_ref().expect("machine has a disk bus")
+    }
+
+    fn bus_mut(&mut self) -> &mut DiskBus<C> {
+        let io: &mut dyn Any = self.state.io.as_deref_mut().expect("machine has a bus");
+        io.downcast_mut().expect("machine has a disk bus")
+    }
+
+    /// Puts `image`, an [`DiskFormat::Ibm3740`] disk, in drive `drive` (0
+    /// for A:, up to 3), returning the disk that was there.
+    pub fn mount(&mut self, drive: usize, image: DiskImage) -> Option<DiskImage> {
+        assert_eq!(image.format, DiskFormat::Ibm3740, "not an IBM 3740 image");
+        self.bus_mut().drives[drive].replace(image)
+    }
+
+    pub fn eject(&mut self, drive: usize) -> Option<DiskImage> {
+        self.bus_mut().drives[drive].take()
+    }
+
+    pub fn disk(&self, drive: usize) -> Option<&DiskImage> {
+        self.bus().drives[drive].as_ref()
+    }
+
+    /// Clears memory, loads the boot sector from drive A: to 0x0000 and
+    /// starts the CPU there.
+    pub fn boot(&mut self) -> Result<(), DiskError> {
+        let boot = match &self.bus().drives[0] {
+            Some(disk) => disk.data[..SECTOR_SIZE].to_vec(),
+            None => return Err(DiskError::NoBootDisk),
+        };
+        self.state.memory.fill(0);
+        self.state.memory[..SECTOR_SIZE].copy_from_slice(&boot);
+        self.state.pc = 0;
+        self.state.sp = 0;
+        self.state.int_enable = false;
+        let bus = self.bus_mut();
+        bus.drive = 0;
+        bus.track = 0;
+        bus.sector = 1;
+        bus.dma = 0;
+        bus.status = DiskStatus::Ok;
+        bus.command = None;
+        Ok(())
+    }
+
+    /// Runs until the CPU halts or reads or polls the console after the
+    /// input has ended.
+    pub fn run(&mut self) -> Exit {
+        loop {
+            if let Some(exit) = self.step() {
+                return exit;
+            }
+        }
+    }
+
+    /// Runs at most `limit` instructions, returning None if the machine is
+    /// still going.
+    pub fn run_for(&mut self, limit: u64) -> Option<Exit> {
+        (0..limit).find_map(|_| self.step())
+    }
+
+    /// Executes one instruction, and the disk command it issued, if any.
+    pub fn step(&mut self) -> Option<Exit> {
+        let pc = self.state.pc;
+        if self.state.memory[pc as usize] == 0x76 {
+            return Some(Exit::Halted { pc });
+        }
+        emulate_8080_op(&mut self.state);
+
+        let State8080 { io, memory, .. } = &mut self.state;
+        let io: &mut dyn Any = io.as_deref_mut().expect("machine has a bus");
+        let bus: &mut DiskBus<C> = io.downcast_mut().expect("machine has a disk bus");
+        if let Some(command) = bus.command.take() {
+            bus.status = bus.transfer(command, memory);
+        }
+        if bus.input_ended {
+            bus.input_ended = false;
+            return Some(Exit::EndOfInput);
+        }
+        None
+    }
+
+    pub fn console(&self) -> &C {
+        &self.bus().console
+    }
+
+    pub fn console_mut(&mut self) -> &mut C {
+        &mut self.bus_mut().console
+    }
+
+    pub fn get_state(&self) -> &State8080 {
+        &self.state
+    }
+
+    pub fn get_state_mut(&mut self) -> &mut State8080 {
+        &mut self.state
+    }
+}
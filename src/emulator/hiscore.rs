@@ -0,0 +1,158 @@
+//! Keeps high scores across restarts.
+//!
+//! Games set up their high-score RAM a little after reset, so a saved table
+//! cannot simply be copied in at power-on. Each [`HiscoreSpec`] names the RAM
+//! ranges to save and a few bytes the game is known to write while
+//! initialising them; once those appear, the saved bytes are restored. The
+//! file format is the saved ranges' raw bytes, one after another.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HiscoreSpec {
+    /// `(address, length)` of each range to save.
+    pub ranges: &'static [(u16, u16)],
+    /// Bytes that mark the ranges as initialised, and where they appear.
+    pub ready_addr: u16,
+    pub ready_bytes: &'static [u8],
+}
+
+impl HiscoreSpec {
+    /// Total size of a save file.
+    pub fn len(&self) -> usize {
+        self.ranges.iter().map(|&(_, len)| len as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Space Invaders keeps its BCD high score at 0x20F4, followed by the screen
+/// address it is drawn at, which is copied from ROM during start-up.
+pub const SPACE_INVADERS: HiscoreSpec = HiscoreSpec {
+    ranges: &[(0x20F4, 2)],
+    ready_addr: 0x20F6,
+    ready_bytes: &[0x1C, 0x2F],
+};
+
+/// Where the high scores of `rom_set` are kept natively.
+pub fn default_path(rom_set: &str) -> PathBuf {
+    Path::new("hiscore").join(format!("{}.hi", rom_set))
+}
+
+#[derive(Clone, Debug)]
+pub struct Hiscore {
+    spec: HiscoreSpec,
+    /// Last table loaded; restored each time the game becomes ready.
+    saved: Option<Vec<u8>>,
+    ready: bool,
+}
+
+impl Hiscore {
+    pub fn new(spec: HiscoreSpec) -> Self {
+        Hiscore {
+            spec,
+            saved: None,
+            ready: false,
+        }
+    }
+
+    pub fn spec(&self) -> &HiscoreSpec {
+        &self.spec
+    }
+
+    /// Queues `bytes` (in the save file layout) to be restored once the game
+    /// is ready. Returns false, ignoring them, if the size is wrong.
+    pub fn set_saved(&mut self, bytes: &[u8]) -> bool {
+        if bytes.len() != self.spec.len() {
+            return false;
+        }
+        self.saved = Some(bytes.to_vec());
+        true
+    }
+
+    /// Reads a save file. A missing file is not an error.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        if !self.set_saved(&bytes) {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "high score file has {} bytes, expected {}",
+                    bytes.len(),
+                    self.spec.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// True once the game has initialised its high-score RAM.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// Call after every frame: restores the saved bytes as soon as the game
+    /// has set up its high-score RAM. Returns true on the frame it does so.
+    pub fn update(&mut self, memory: &mut [u8]) -> bool {
+        if self.ready {
+            return false;
+        }
+        let ready = self.spec.ready_addr as usize;
+        if memory[ready..ready + self.spec.ready_bytes.len()] != *self.spec.ready_bytes {
+            return false;
+        }
+        self.ready = true;
+        let Some(saved) = &self.saved else {
+            return false;
+        };
+        let mut bytes = saved.as_slice();
+        for &(addr, len) in self.spec.ranges {
+            let (range, rest) = bytes.split_at(len as usize);
+            memory[addr as usize..][..len as usize].copy_from_slice(range);
+            bytes = rest;
+        }
+        true
+    }
+
+    /// Waits for the game to set up its RAM again, e.g. after a reset,
+    /// restoring whatever was last loaded.
+    pub fn rearm(&mut self) {
+        self.ready = false;
+    }
+
+    /// The current table in save file layout, or `None` before the game has
+    /// initialised it (saving then would overwrite a good file with zeros).
+    pub fn bytes(&self, memory: &[u8]) -> Option<Vec<u8>> {
+        if !self.ready {
+            return None;
+        }
+        Some(
+            self.spec
+                .ranges
+                .iter()
+                .flat_map(|&(addr, len)| &memory[addr as usize..][..len as usize])
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Writes the current table to `path`, creating its directory. Does
+    /// nothing if the game never got far enough to set it up.
+    pub fn save<P: AsRef<Path>>(&self, memory: &[u8], path: P) -> io::Result<()> {
+        let Some(bytes) = self.bytes(memory) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, bytes)
+    }
+}
@@ -0,0 +1,122 @@
+//! Ring buffer of the most recently executed instructions.
+//!
+//! Only the address and raw bytes are stored per instruction; mnemonics are
+//! formatted when the history is read. A capacity of 0 turns recording off,
+//! and building without the `history` feature removes it altogether.
+
+use super::data_types::InstructionInfo;
+use crate::disassembler::{decode, FormatOptions};
+use crate::symbols::SymbolTable;
+
+pub const DEFAULT_CAPACITY: usize = 50;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub address: u16,
+    /// Opcode followed by up to two operand bytes, as fetched.
+    pub bytes: [u8; 3],
+}
+
+#[derive(Clone, Debug)]
+pub struct InstructionHistory {
+    entries: Box<[HistoryEntry]>,
+    /// Slot the next entry is written to.
+    next: usize,
+    len: usize,
+}
+
+impl InstructionHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = if cfg!(feature = "history") {
+            capacity
+        } else {
+            0
+        };
+        InstructionHistory {
+            entries: vec![HistoryEntry::default(); capacity].into_boxed_slice(),
+            next: 0,
+            len: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Resizes the buffer, dropping what was recorded. 0 disables recording.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        *self = Self::new(capacity);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.next = 0;
+        self.len = 0;
+    }
+
+    #[inline(always)]
+    pub fn record(&mut self, address: u16, bytes: [u8; 3]) {
+        let capacity = self.entries.len();
+        if capacity == 0 {
+            return;
+        }
+        self.entries[self.next] = HistoryEntry { address, bytes };
+        self.next = if self.next + 1 == capacity {
+            0
+        } else {
+            self.next + 1
+        };
+        if self.len < capacity {
+            self.len += 1;
+        }
+    }
+
+    /// Recorded entries, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        let start = if self.len == self.capacity() {
+            self.next
+        } else {
+            0
+        };
+        (0..self.len).map(move |i| &self.entries[(start + i) % self.capacity()])
+    }
+
+    /// Decodes the recorded instructions, oldest first, naming addresses
+    /// from `symbols` when given.
+    pub fn instructions(&self, symbols: Option<&SymbolTable>) -> Vec<InstructionInfo> {
+        let options = FormatOptions::default();
+        self.entries()
+            .map(|entry| {
+                let mut instruction = decode(&entry.bytes, 0);
+                instruction.address = entry.address;
+                let mnemonic = match symbols {
+                    Some(symbols) => instruction
+                        .format_with(&options, |addr| symbols.name_of(addr).map(str::to_string)),
+                    None => instruction.format(&options),
+                };
+                InstructionInfo {
+                    address: entry.address,
+                    opcode: entry.bytes[0],
+                    mnemonic,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for InstructionHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
@@ -0,0 +1,251 @@
+//! Decodes Space Invaders game state from work RAM.
+//!
+//! Scores and credits are kept by the game as BCD; each score is followed by
+//! the screen address it is drawn at. Each player has a 256-byte data page
+//! (0x2100 for player 1, 0x2200 for player 2) holding the alien table, wave
+//! count and ships; the page in use is named by the byte at 0x2067.
+
+use serde::Serialize;
+use std::fmt;
+
+const PLAYER_ALIVE: usize = 0x2015;
+const PLAYER_X: usize = 0x201B;
+const REF_ALIEN_Y: usize = 0x2009;
+const REF_ALIEN_X: usize = 0x200A;
+const PLAYER_DATA_PAGE: usize = 0x2067;
+const NUM_ALIENS: usize = 0x2082;
+const SAUCER_ACTIVE: usize = 0x2084;
+const SAUCER_HIT: usize = 0x2085;
+const SAUCER_Y: usize = 0x2089;
+const SAUCER_X: usize = 0x208A;
+const SAUCER_DELTA_X: usize = 0x208C;
+const CREDITS: usize = 0x20EB;
+const GAME_MODE: usize = 0x20EF;
+const HIGH_SCORE: usize = 0x20F4;
+const SCORES: [usize; 2] = [0x20F8, 0x20FC];
+
+// Offsets into a player's data page
+const WAVE: usize = 0xFE;
+const SHIPS: usize = 0xFF;
+
+const ALIEN_ROWS: usize = 5;
+const ALIENS_PER_ROW: usize = 11;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Aliens {
+    /// Position of the reference (bottom-left) alien.
+    pub reference_x: u8,
+    pub reference_y: u8,
+    pub remaining: u8,
+    /// One bit per alien, bit 0 leftmost, bottom row first.
+    pub alive_rows: [u16; ALIEN_ROWS],
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Ufo {
+    pub active: bool,
+    pub hit: bool,
+    pub x: u8,
+    pub y: u8,
+    /// -1 moving left, 1 moving right.
+    pub direction: i8,
+}
+
+/// Everything [`GameInspector::snapshot`] decodes, ready to serialize.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct GameState {
+    /// False while the attract mode demo is running.
+    pub in_game: bool,
+    /// 1 or 2.
+    pub current_player: u8,
+    pub scores: [u32; 2],
+    pub high_score: u32,
+    pub credits: u8,
+    /// Ships in reserve per player, not counting the one in play.
+    pub lives: [u8; 2],
+    /// Waves cleared by the current player.
+    pub wave: u8,
+    pub player_x: u8,
+    pub player_alive: bool,
+    pub aliens: Aliens,
+    pub ufo: Ufo,
+}
+
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{}, player {}  score {} / {}  high {}  credits {}",
+            if self.in_game {
+                "Playing"
+            } else {
+                "Attract mode"
+            },
+            self.current_player,
+            self.scores[0],
+            self.scores[1],
+            self.high_score,
+            self.credits
+        )?;
+        writeln!(
+            f,
+            "Lives {} / {}  wave {}  player x={} {}",
+            self.lives[0],
+            self.lives[1],
+            self.wave,
+            self.player_x,
+            if self.player_alive {
+                "alive"
+            } else {
+                "exploding"
+            }
+        )?;
+        writeln!(
+            f,
+            "Aliens {} at x={} y={}",
+            self.aliens.remaining, self.aliens.reference_x, self.aliens.reference_y
+        )?;
+        for row in self.aliens.alive_rows.iter().rev() {
+            let line: String = (0..ALIENS_PER_ROW)
+                .map(|column| if row & (1 << column) != 0 { '#' } else { '.' })
+                .collect();
+            writeln!(f, "  {}", line)?;
+        }
+        if self.ufo.active {
+            writeln!(
+                f,
+                "UFO x={} y={} moving {}{}",
+                self.ufo.x,
+                self.ufo.y,
+                if self.ufo.direction < 0 {
+                    "left"
+                } else {
+                    "right"
+                },
+                if self.ufo.hit { ", hit" } else { "" }
+            )
+        } else {
+            writeln!(f, "UFO inactive")
+        }
+    }
+}
+
+/// Read-only view of a Space Invaders machine's memory.
+#[derive(Clone, Copy)]
+pub struct GameInspector<'a> {
+    memory: &'a [u8],
+}
+
+impl<'a> GameInspector<'a> {
+    /// `memory` is the full 64K address space.
+    pub fn new(memory: &'a [u8]) -> Self {
+        GameInspector { memory }
+    }
+
+    pub fn in_game(&self) -> bool {
+        self.memory[GAME_MODE] != 0
+    }
+
+    pub fn current_player(&self) -> u8 {
+        if self.memory[PLAYER_DATA_PAGE] == 0x22 {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Score of player 1 or 2.
+    pub fn score(&self, player: u8) -> u32 {
+        self.bcd_word(SCORES[player.clamp(1, 2) as usize - 1])
+    }
+
+    pub fn high_score(&self) -> u32 {
+        self.bcd_word(HIGH_SCORE)
+    }
+
+    pub fn credits(&self) -> u8 {
+        bcd(self.memory[CREDITS]) as u8
+    }
+
+    /// Ships player 1 or 2 has in reserve.
+    pub fn lives(&self, player: u8) -> u8 {
+        self.memory[player_page(player.clamp(1, 2)) + SHIPS]
+    }
+
+    pub fn wave(&self) -> u8 {
+        self.memory[self.current_page() + WAVE]
+    }
+
+    pub fn player_x(&self) -> u8 {
+        self.memory[PLAYER_X]
+    }
+
+    pub fn player_alive(&self) -> bool {
+        self.memory[PLAYER_ALIVE] == 0xFF
+    }
+
+    pub fn aliens(&self) -> Aliens {
+        let table = &self.memory[self.current_page()..][..ALIEN_ROWS * ALIENS_PER_ROW];
+        let mut alive_rows = [0u16; ALIEN_ROWS];
+        for (row, aliens) in alive_rows.iter_mut().zip(table.chunks(ALIENS_PER_ROW)) {
+            *row = aliens
+                .iter()
+                .enumerate()
+                .filter(|(_, &alive)| alive != 0)
+                .fold(0, |bits, (column, _)| bits | 1 << column);
+        }
+        Aliens {
+            reference_x: self.memory[REF_ALIEN_X],
+            reference_y: self.memory[REF_ALIEN_Y],
+            remaining: self.memory[NUM_ALIENS],
+            alive_rows,
+        }
+    }
+
+    pub fn ufo(&self) -> Ufo {
+        Ufo {
+            active: self.memory[SAUCER_ACTIVE] != 0,
+            hit: self.memory[SAUCER_HIT] != 0,
+            x: self.memory[SAUCER_X],
+            y: self.memory[SAUCER_Y],
+            direction: if (self.memory[SAUCER_DELTA_X] as i8) < 0 {
+                -1
+            } else {
+                1
+            },
+        }
+    }
+
+    pub fn snapshot(&self) -> GameState {
+        GameState {
+            in_game: self.in_game(),
+            current_player: self.current_player(),
+            scores: [self.score(1), self.score(2)],
+            high_score: self.high_score(),
+            credits: self.credits(),
+            lives: [self.lives(1), self.lives(2)],
+            wave: self.wave(),
+            player_x: self.player_x(),
+            player_alive: self.player_alive(),
+            aliens: self.aliens(),
+            ufo: self.ufo(),
+        }
+    }
+
+    fn current_page(&self) -> usize {
+        player_page(self.current_player())
+    }
+
+    /// Four BCD digits stored low byte first.
+    fn bcd_word(&self, addr: usize) -> u32 {
+        bcd(self.memory[addr + 1]) * 100 + bcd(self.memory[addr])
+    }
+}
+
+fn player_page(player: u8) -> usize {
+    0x2000 + ((player as usize) << 8)
+}
+
+fn bcd(byte: u8) -> u32 {
+    (byte >> 4) as u32 * 10 + (byte & 0x0F) as u32
+}
@@ -0,0 +1,234 @@
+//! Port I/O outside the CPU.
+//!
+//! An [`IoBus`] installed in [`State8080::io`](super::data_types::State8080)
+//! answers every `IN` and receives every `OUT`. Without one, `IN` reads 0
+//! and `OUT` is ignored.
+
+use super::cpu::InterruptSource;
+use std::any::Any;
+
+pub trait IoBus: Any + Send {
+    fn input(&mut self, port: u8) -> u8;
+    fn output(&mut self, port: u8, value: u8);
+}
+
+/// A peripheral chip decoding a few ports from a base address, for boards
+/// assembled with a [`PortMap`].
+pub trait Device: Any + Send {
+    /// How many ports it decodes: `base` to `base + ports() - 1`.
+    fn ports(&self) -> u8;
+    fn read(&mut self, offset: u8) -> u8;
+    fn write(&mut self, offset: u8, value: u8);
+    /// Advances its clock by this many CPU cycles.
+    fn tick(&mut self, _cycles: u32) {}
+    /// Level of one of its output pins that can be wired to an interrupt.
+    fn pin(&self, _pin: usize) -> bool {
+        false
+    }
+    /// Drives one of its input lines, from a pin joined to it with
+    /// [`PortMap::connect`].
+    fn set_input(&mut self, _input: usize, _level: bool) {}
+    /// True while it wants to answer the CPU's INTA cycles itself, as an
+    /// interrupt controller does.
+    fn requesting(&self) -> bool {
+        false
+    }
+    /// The byte it puts on the bus in INTA cycle `cycle`.
+    fn acknowledge(&mut self, _cycle: usize) -> u8 {
+        0xFF
+    }
+    /// True once a device fed by a script or a pipe has run out of input
+    /// and the program is waiting on it.
+    fn end_of_input(&mut self) -> bool {
+        false
+    }
+}
+
+struct Slot {
+    base: u8,
+    device: Box<dyn Device>,
+}
+
+/// An interrupt pin wired to an `RST`. Rising edges are latched until the
+/// CPU takes the interrupt.
+struct Wire {
+    slot: usize,
+    pin: usize,
+    rst: u8,
+    level: bool,
+    pending: bool,
+}
+
+/// An output pin of one device joined to an input line of another.
+struct Link {
+    from: usize,
+    pin: usize,
+    to: usize,
+    input: usize,
+    level: bool,
+}
+
+/// An [`IoBus`] built from [`Device`]s, each at its own base port. Unclaimed
+/// ports read 0xFF.
+///
+/// It is also the CPU's [`InterruptSource`]. A requesting device, such as an
+/// interrupt controller fed by [`PortMap::connect`], answers the INTA cycles
+/// itself; failing that, the first pin wired with [`PortMap::wire`] with a
+/// pending request supplies its `RST`.
+#[derive(Default)]
+pub struct PortMap {
+    slots: Vec<Slot>,
+    wires: Vec<Wire>,
+    links: Vec<Link>,
+    /// The device answering the INTA cycles under way.
+    responder: Option<usize>,
+}
+
+impl PortMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places `device` at `base`.
+    pub fn attach<D: Device>(&mut self, base: u8, device: D) -> &mut Self {
+        self.slots.push(Slot {
+            base,
+            device: Box::new(device),
+        });
+        self
+    }
+
+    fn slot(&self, base: u8) -> usize {
+        self.slots
+            .iter()
+            .position(|slot| slot.base == base)
+            .expect("no device at that port")
+    }
+
+    /// Wires pin `pin` of the device at `base` to `RST rst`.
+    ///
+    /// # Panics
+    /// If no device is attached at `base`.
+    pub fn wire(&mut self, base: u8, pin: usize, rst: u8) -> &mut Self {
+        let slot = self.slot(base);
+        let level = self.slots[slot].device.pin(pin);
+        self.wires.push(Wire {
+            slot,
+            pin,
+            rst,
+            level,
+            pending: false,
+        });
+        self
+    }
+
+    /// Joins pin `pin` of the device at `from` to input `input` of the
+    /// device at `to`, e.g. a timer output to an interrupt controller.
+    ///
+    /// # Panics
+    /// If no device is attached at `from` or `to`.
+    pub fn connect(&mut self, from: u8, pin: usize, to: u8, input: usize) -> &mut Self {
+        let (from, to) = (self.slot(from), self.slot(to));
+        let level = self.slots[from].device.pin(pin);
+        self.slots[to].device.set_input(input, level);
+        self.links.push(Link {
+            from,
+            pin,
+            to,
+            input,
+            level,
+        });
+        self
+    }
+
+    /// The device of type `D` at `base`.
+    pub fn get<D: Device>(&self, base: u8) -> Option<&D> {
+        let slot = self.slots.iter().find(|slot| slot.base == base)?;
+        let device: &dyn Any = slot.device.as_ref();
+        device.downcast_ref()
+    }
+
+    pub fn get_mut<D: Device>(&mut self, base: u8) -> Option<&mut D> {
+        let slot = self.slots.iter_mut().find(|slot| slot.base == base)?;
+        let device: &mut dyn Any = slot.device.as_mut();
+        device.downcast_mut()
+    }
+
+    fn decode(&mut self, port: u8) -> Option<(&mut dyn Device, u8)> {
+        self.slots.iter_mut().find_map(|slot| {
+            let offset = port.wrapping_sub(slot.base);
+            (offset < slot.device.ports()).then_some((slot.device.as_mut(), offset))
+        })
+    }
+
+    /// Clocks every device and latches the rising edges on wired pins.
+    pub fn tick(&mut self, cycles: u32) {
+        for slot in &mut self.slots {
+            slot.device.tick(cycles);
+        }
+        self.sample();
+    }
+
+    fn sample(&mut self) {
+        for link in &mut self.links {
+            let level = self.slots[link.from].device.pin(link.pin);
+            if level != link.level {
+                self.slots[link.to].device.set_input(link.input, level);
+                link.level = level;
+            }
+        }
+        for wire in &mut self.wires {
+            let level = self.slots[wire.slot].device.pin(wire.pin);
+            if level && !wire.level {
+                wire.pending = true;
+            }
+            wire.level = level;
+        }
+    }
+
+    pub fn end_of_input(&mut self) -> bool {
+        self.slots.iter_mut().any(|slot| slot.device.end_of_input())
+    }
+}
+
+impl InterruptSource for PortMap {
+    fn requesting(&mut self) -> bool {
+        self.slots.iter().any(|slot| slot.device.requesting())
+            || self.wires.iter().any(|wire| wire.pending)
+    }
+
+    fn acknowledge(&mut self, cycle: usize) -> u8 {
+        if cycle == 0 {
+            self.responder = self.slots.iter().position(|slot| slot.device.requesting());
+            if self.responder.is_none() {
+                if let Some(wire) = self.wires.iter_mut().find(|wire| wire.pending) {
+                    wire.pending = false;
+                    return 0xC7 | (wire.rst & 0x07) << 3;
+                }
+            }
+        }
+        match self.responder {
+            Some(slot) => self.slots[slot].device.acknowledge(cycle),
+            // Nothing drives the bus, which floats to RST 7
+            None => 0xFF,
+        }
+    }
+}
+
+impl IoBus for PortMap {
+    fn input(&mut self, port: u8) -> u8 {
+        let value = match self.decode(port) {
+            Some((device, offset)) => device.read(offset),
+            None => 0xFF,
+        };
+        self.sample();
+        value
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        if let Some((device, offset)) = self.decode(port) {
+            device.write(offset, value);
+        }
+        self.sample();
+    }
+}
@@ -1,48 +1,188 @@
 #![cfg(not(feature = "wasm"))]
 
+use super::cheat::CheatEngine;
 use super::cpu::{self, CPU};
+use super::data_types::State8080;
+use super::hiscore::Hiscore;
+use super::inspector::GameInspector;
+use super::midway;
+pub use super::midway::{BoardProfile, Colour, MidwayBoard};
+use super::recompiler::Recompiler;
+use super::romset::{RomError, RomImage};
+use super::video::COLOUR_MAP_SIZE;
+use crate::debugger::DebugTarget;
+use std::any::Any;
+use std::path::Path;
 
+const CYCLES_PER_INTERRUPT: i32 = 16_666;
+
+/// Where [`SpaceInvadersMachine::new`] looks for ROM sets, in order.
+pub const ROM_PATH: &[&str] = &["roms", "roms/space_invaders"];
+
+/// How `do_cpu` executes code. Single-stepping always uses the interpreter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Engine {
+    #[default]
+    Interpreter,
+    Recompiler,
+}
+
+/// A Midway 8080 black-and-white board running the game its
+/// [`BoardProfile`] describes, Space Invaders unless told otherwise.
 pub struct SpaceInvadersMachine {
     cpu: CPU,
+    profile: &'static BoardProfile,
+    proms: Vec<u8>,
     which_interrupt: u8,
+    interrupt_cycles: i32,
+    recompiler: Option<Recompiler>,
+    hiscore: Option<Hiscore>,
+    cheats: CheatEngine,
+}
+
+impl Default for SpaceInvadersMachine {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SpaceInvadersMachine {
+    /// Loads the ROM set from [`ROM_PATH`], panicking if it is missing or
+    /// bad. Use [`SpaceInvadersMachine::from_rom_path`] to handle that.
     pub fn new() -> Self {
+        Self::from_rom_path(ROM_PATH).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Loads and verifies the Space Invaders ROM set from loose files in `dir`.
+    pub fn from_rom_dir<P: AsRef<Path>>(dir: P) -> Result<Self, RomError> {
+        let profile = &midway::INVADERS;
+        Ok(Self::with_rom(profile, &profile.rom.load(dir)?))
+    }
+
+    /// Searches `rom_path` for `invaders.zip`, an `invaders/` directory or
+    /// loose files, see [`RomSet::locate`](super::romset::RomSet::locate).
+    pub fn from_rom_path<P: AsRef<Path>>(rom_path: &[P]) -> Result<Self, RomError> {
+        Self::from_profile(&midway::INVADERS, rom_path)
+    }
+
+    /// Runs another Midway 8080 game, searching `rom_path` for its ROM set.
+    pub fn from_profile<P: AsRef<Path>>(
+        profile: &'static BoardProfile,
+        rom_path: &[P],
+    ) -> Result<Self, RomError> {
+        Ok(Self::with_rom(profile, &profile.rom.locate(rom_path)?))
+    }
+
+    /// Loads the Space Invaders ROM set from a zip archive in memory.
+    pub fn from_zip_bytes(bytes: &[u8]) -> Result<Self, RomError> {
+        let profile = &midway::INVADERS;
+        Ok(Self::with_rom(profile, &profile.rom.load_zip_bytes(bytes)?))
+    }
+
+    fn with_rom(profile: &'static BoardProfile, rom: &RomImage) -> Self {
         let mut machine = SpaceInvadersMachine {
             cpu: CPU::new(),
+            profile,
+            proms: rom.proms.clone(),
             which_interrupt: 1,
+            interrupt_cycles: 0,
+            recompiler: None,
+            hiscore: profile.hiscore.map(Hiscore::new),
+            cheats: CheatEngine::new(),
         };
-        // Initialize ROM by loading files
-        machine.read_multiple_files_into_memory(
-            vec![
-                "roms/space_invaders/invaders.h",
-                "roms/space_invaders/invaders.g",
-                "roms/space_invaders/invaders.f",
-                "roms/space_invaders/invaders.e",
-            ],
-            0,
-        );
-        // machine.read_multiple_files_into_memory(
-        //     vec![
-        //         "roms/gunfight/7609h.bin",
-        //         "roms/gunfight/7609g.bin",
-        //         "roms/gunfight/7609f.bin",
-        //         "roms/gunfight/7609e.bin",
-        //     ],
-        //     0,
-        // );
         machine
+            .cheats
+            .load(profile.cheats)
+            .expect("built-in cheats parse");
+        // Nothing reads the instruction history natively
+        machine.cpu.state.history.set_capacity(0);
+        machine.cpu.state.memory[..rom.program.len()].copy_from_slice(&rom.program);
+        machine.cpu.state.io = Some(Box::new(MidwayBoard::new(profile)));
+        machine
+    }
+
+    pub fn profile(&self) -> &'static BoardProfile {
+        self.profile
+    }
+
+    /// The colour map the video hardware is showing, for
+    /// [`video::render`](super::video::render). None on black-and-white
+    /// boards.
+    pub fn colour_map(&self) -> Option<&[u8]> {
+        match self.profile.colour {
+            Colour::Mono => None,
+            Colour::Prom { select } => {
+                let map = select.map_or(0, |(port, mask)| {
+                    usize::from(self.board().last_output(port) & mask != 0)
+                });
+                self.proms
+                    .get(map * COLOUR_MAP_SIZE..(map + 1) * COLOUR_MAP_SIZE)
+            }
+            Colour::Ram { address } => {
+                let start = address as usize;
+                self.cpu.state.memory.get(start..start + COLOUR_MAP_SIZE)
+            }
+        }
+    }
+
+    /// Controls, DIP switches and shift register.
+    pub fn board(&self) -> &MidwayBoard {
+        let io: &dyn Any = self.cpu.state.io.as_deref().expect("machine has a board");
+        io.downcast_ref().expect("machine has a board")
+    }
+
+    pub fn board_mut(&mut self) -> &mut MidwayBoard {
+        let io: &mut dyn Any = self
+            .cpu
+            .state
+            .io
+            .as_deref_mut()
+            .expect("machine has a board");
+        io.downcast_mut().expect("machine has a board")
+    }
+
+    /// Power-cycles the machine: clears the CPU and RAM but keeps the ROM,
+    /// the board with its controls and DIP switches, the selected engine and
+    /// any attached tracer.
+    pub fn reset(&mut self) {
+        let mut state = State8080::default();
+        for file in self.profile.rom.files {
+            let rom = file.address..file.address + file.size;
+            state.memory[rom.clone()].copy_from_slice(&self.cpu.state.memory[rom]);
+        }
+        state.history.set_capacity(0);
+        state.tracer = self.cpu.state.tracer.take();
+        state.io = self.cpu.state.io.take();
+        self.cpu.state = state;
+        self.which_interrupt = 1;
+        self.interrupt_cycles = 0;
+        if let Some(hiscore) = &mut self.hiscore {
+            hiscore.rearm();
+        }
+    }
+
+    pub fn cheats(&self) -> &CheatEngine {
+        &self.cheats
+    }
+
+    pub fn cheats_mut(&mut self) -> &mut CheatEngine {
+        &mut self.cheats
     }
 
-    fn read_multiple_files_into_memory(&mut self, filenames: Vec<&str>, offset: usize) {
-        // Implement loading multiple binary files into memory starting at 'offset'
-        let mut current_offset = offset;
-        for filename in filenames {
-            let rom = std::fs::read(filename).expect(&format!("Failed to load {}", filename));
-            self.cpu.state.memory[current_offset..current_offset + rom.len()]
-                .copy_from_slice(&rom[..]);
-            current_offset += rom.len();
+    /// High-score persistence, for games whose profile describes it.
+    pub fn hiscore(&self) -> Option<&Hiscore> {
+        self.hiscore.as_ref()
+    }
+
+    pub fn hiscore_mut(&mut self) -> Option<&mut Hiscore> {
+        self.hiscore.as_mut()
+    }
+
+    /// Writes the high-score table to `path`, if the game has set it up.
+    pub fn save_hiscore<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        match &self.hiscore {
+            Some(hiscore) => hiscore.save(&self.cpu.state.memory, path),
+            None => Ok(()),
         }
     }
 
@@ -54,13 +194,79 @@ impl SpaceInvadersMachine {
         &self.cpu.state.memory
     }
 
+    pub fn get_state(&self) -> &State8080 {
+        &self.cpu.state
+    }
+
+    pub fn get_state_mut(&mut self) -> &mut State8080 {
+        &mut self.cpu.state
+    }
+
+    /// Decoded game state (scores, lives, aliens, ...).
+    pub fn inspector(&self) -> GameInspector<'_> {
+        GameInspector::new(&self.cpu.state.memory)
+    }
+
+    pub fn engine(&self) -> Engine {
+        match self.recompiler {
+            Some(_) => Engine::Recompiler,
+            None => Engine::Interpreter,
+        }
+    }
+
+    pub fn set_engine(&mut self, engine: Engine) {
+        self.recompiler = match engine {
+            Engine::Interpreter => None,
+            Engine::Recompiler => Some(Recompiler::new()),
+        };
+    }
+
+    pub fn recompiler(&self) -> Option<&Recompiler> {
+        self.recompiler.as_ref()
+    }
+
+    /// Executes a single instruction, raising the mid-screen/VBLANK interrupt
+    /// once enough cycles have elapsed. Returns the cycles taken by the instruction.
+    pub fn step(&mut self) -> u8 {
+        let op_cycles = cpu::emulate_8080_op(&mut self.cpu.state);
+        self.interrupt_cycles += op_cycles as i32;
+        if self.interrupt_cycles >= CYCLES_PER_INTERRUPT {
+            self.interrupt_cycles = 0;
+            self.interrupt();
+        }
+        op_cycles
+    }
+
+    /// Runs until the next interrupt is due. With the recompiler, whole blocks
+    /// run, so the interrupt can land a few instructions later than it would
+    /// with the interpreter.
     pub fn do_cpu(&mut self) {
-        let mut cycles = 0;
-        let cycles_per_interrupt = 16_666;
+        if let Some(recompiler) = self.recompiler.as_mut() {
+            while self.interrupt_cycles < CYCLES_PER_INTERRUPT {
+                self.interrupt_cycles += recompiler.run_block(&mut self.cpu.state).cycles as i32;
+            }
+        } else {
+            while self.interrupt_cycles < CYCLES_PER_INTERRUPT {
+                self.interrupt_cycles += cpu::emulate_8080_op(&mut self.cpu.state) as i32;
+            }
+        }
+        self.interrupt_cycles = 0;
+        self.interrupt();
+    }
+
+    /// Runs one 60 Hz video frame: up to the mid-screen interrupt and then
+    /// up to the end-of-screen interrupt.
+    pub fn run_frame(&mut self) {
+        self.do_cpu();
+        self.do_cpu();
+    }
 
-        while cycles < cycles_per_interrupt {
-            let op_cycles = cpu::emulate_8080_op(&mut self.cpu.state) as i32;
-            cycles += op_cycles;
+    fn interrupt(&mut self) {
+        if let Some(hiscore) = &mut self.hiscore {
+            hiscore.update(&mut self.cpu.state.memory);
+        }
+        if self.cheats.is_active() {
+            self.cheats.apply(&mut self.cpu.state.memory);
         }
         if self.cpu.state.int_enable {
             if self.which_interrupt == 1 {
@@ -76,12 +282,28 @@ impl SpaceInvadersMachine {
     pub fn start_emulation(&mut self) {
         self.do_cpu();
     }
+}
+
+impl DebugTarget for SpaceInvadersMachine {
+    fn state(&self) -> &State8080 {
+        &self.cpu.state
+    }
+
+    fn state_mut(&mut self) -> &mut State8080 {
+        &mut self.cpu.state
+    }
+
+    fn step(&mut self) -> u8 {
+        SpaceInvadersMachine::step(self)
+    }
 
-    pub fn handle_key_down(&mut self, key: sdl2::keyboard::Keycode) {
-        self.cpu.handle_key_down(key);
+    fn cheats(&mut self) -> Option<&mut CheatEngine> {
+        Some(&mut self.cheats)
     }
 
-    pub fn handle_key_up(&mut self, key: sdl2::keyboard::Keycode) {
-        self.cpu.handle_key_up(key);
+    fn inspector(&self) -> Option<GameInspector<'_>> {
+        self.profile
+            .inspector
+            .then(|| SpaceInvadersMachine::inspector(self))
     }
 }
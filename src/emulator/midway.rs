@@ -0,0 +1,533 @@
+//! The Midway 8080 black-and-white board family.
+//!
+//! Space Invaders, Gun Fight and their siblings share the CPU, the video RAM
+//! at 0x2400 and an MB14241 shift register, and differ only in where their
+//! ROMs load, which ports hold the shifter, the controls and the DIP
+//! switches, and whether the picture is coloured. Taito's colour versions of
+//! the Space Invaders board are the same machine with colour PROMs added. A
+//! [`BoardProfile`] describes those differences as data; [`MidwayBoard`] is
+//! the [`IoBus`] that runs any of them.
+//!
+//! Input ports are computed as `idle | dips ^ pressed`: a control whose bit
+//! is set in `idle` is active low, so pressing it clears the bit.
+//!
+//! Of the black-and-white boards, Space Invaders, Space Invaders Deluxe and
+//! Gun Fight have profiles. Sea Wolf, Boot Hill, Tornado Baseball, Clowns and
+//! Blue Shark do not: no dump of them is at hand to check their ports
+//! against, and Sea Wolf's periscope and the Clowns paddle are analog
+//! controls that [`Input`] cannot describe.
+
+use super::hiscore::{self, HiscoreSpec};
+use super::io::IoBus;
+use super::romset::{self, RomSet};
+use crate::disassembler::DisassemblyProfile;
+
+const PORTS: usize = 8;
+
+/// Ports of the MB14241 shift register.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShiftPorts {
+    /// OUT: shift amount, low three bits.
+    pub count: u8,
+    /// OUT: byte shifted in from the top.
+    pub data: u8,
+    /// IN: shifted result.
+    pub result: u8,
+    /// Boards with the reversing shifter take bit 3 of the count write to
+    /// mean the result is read with its bits in reverse order.
+    pub reversible: bool,
+}
+
+/// A button or switch, named as in MAME (`Coin`, `P1 Start`, `P1 Left`...).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Input {
+    pub name: &'static str,
+    pub port: u8,
+    pub mask: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DipSwitch {
+    pub name: &'static str,
+    pub port: u8,
+    pub mask: u8,
+    /// Setting names and the bits they put under `mask`.
+    pub settings: &'static [(&'static str, u8)],
+    /// Index into `settings` used at power-on.
+    pub default: usize,
+}
+
+/// Where the picture gets its colours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Colour {
+    /// White on black. The cabinet's overlay is not drawn.
+    Mono,
+    /// Colour PROMs hold maps of [`COLOUR_MAP_SIZE`](super::video::COLOUR_MAP_SIZE)
+    /// bytes, one colour per 8x8 cell. With `select`, the output bit
+    /// `(port, mask)` switches to the second map, as Space Invaders Part II
+    /// does between players.
+    Prom { select: Option<(u8, u8)> },
+    /// The game writes the colour of each cell to RAM at `address`, laid
+    /// out like a PROM map. The CPU sees it as ordinary memory.
+    Ram { address: u16 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardProfile {
+    pub rom: &'static RomSet,
+    /// Value read from each input port with nothing pressed and all DIP
+    /// switches off. Ports not listed read 0.
+    pub idle: &'static [(u8, u8)],
+    pub inputs: &'static [Input],
+    pub dips: &'static [DipSwitch],
+    pub shift: ShiftPorts,
+    pub colour: Colour,
+    pub hiscore: Option<HiscoreSpec>,
+    /// Whether the game keeps its state where
+    /// [`GameInspector`](super::inspector::GameInspector) reads it, as Space
+    /// Invaders does.
+    pub inspector: bool,
+    /// Where `--disassemble` looks for code.
+    pub disassembly: &'static DisassemblyProfile,
+    /// Cheat file, see [`super::cheat`].
+    pub cheats: &'static str,
+}
+
+impl BoardProfile {
+    pub fn name(&self) -> &'static str {
+        self.rom.name
+    }
+
+    pub fn input(&self, name: &str) -> Option<&'static Input> {
+        self.inputs
+            .iter()
+            .find(|input| input.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn dip(&self, name: &str) -> Option<&'static DipSwitch> {
+        self.dips
+            .iter()
+            .find(|dip| dip.name.eq_ignore_ascii_case(name))
+    }
+}
+
+pub const INVADERS: BoardProfile = BoardProfile {
+    rom: &romset::INVADERS,
+    idle: &[(0, 0x0F)],
+    inputs: &[
+        Input {
+            name: "Coin",
+            port: 1,
+            mask: 0x01,
+        },
+        Input {
+            name: "P2 Start",
+            port: 1,
+            mask: 0x02,
+        },
+        Input {
+            name: "P1 Start",
+            port: 1,
+            mask: 0x04,
+        },
+        Input {
+            name: "P1 Fire",
+            port: 1,
+            mask: 0x10,
+        },
+        Input {
+            name: "P1 Left",
+            port: 1,
+            mask: 0x20,
+        },
+        Input {
+            name: "P1 Right",
+            port: 1,
+            mask: 0x40,
+        },
+        Input {
+            name: "Tilt",
+            port: 2,
+            mask: 0x04,
+        },
+        Input {
+            name: "P2 Fire",
+            port: 2,
+            mask: 0x10,
+        },
+        Input {
+            name: "P2 Left",
+            port: 2,
+            mask: 0x20,
+        },
+        Input {
+            name: "P2 Right",
+            port: 2,
+            mask: 0x40,
+        },
+    ],
+    dips: &[
+        DipSwitch {
+            name: "Lives",
+            port: 2,
+            mask: 0x03,
+            settings: &[("3", 0x00), ("4", 0x01), ("5", 0x02), ("6", 0x03)],
+            default: 0,
+        },
+        DipSwitch {
+            name: "Bonus life",
+            port: 2,
+            mask: 0x08,
+            settings: &[("1500", 0x00), ("1000", 0x08)],
+            default: 0,
+        },
+        DipSwitch {
+            name: "Coin info",
+            port: 2,
+            mask: 0x80,
+            settings: &[("On", 0x00), ("Off", 0x80)],
+            default: 0,
+        },
+    ],
+    shift: ShiftPorts {
+        count: 2,
+        data: 4,
+        result: 3,
+        reversible: false,
+    },
+    colour: Colour::Mono,
+    hiscore: Some(hiscore::SPACE_INVADERS),
+    inspector: true,
+    disassembly: &DisassemblyProfile::SPACE_INVADERS,
+    cheats: super::cheat::SPACE_INVADERS,
+};
+
+/// Gun Fight reads its controls on edges: each port is compared with the
+/// last value and a handler runs for every bit that changed. Bits 0-1 and
+/// 2-3 move the cowboy along each axis; the gun aim lever (bits 4-6) is not
+/// mapped.
+pub const GUNFIGHT: BoardProfile = BoardProfile {
+    rom: &romset::GUNFIGHT,
+    idle: &[],
+    inputs: &[
+        Input {
+            name: "P1 Up",
+            port: 0,
+            mask: 0x01,
+        },
+        Input {
+            name: "P1 Down",
+            port: 0,
+            mask: 0x02,
+        },
+        Input {
+            name: "P1 Left",
+            port: 0,
+            mask: 0x04,
+        },
+        Input {
+            name: "P1 Right",
+            port: 0,
+            mask: 0x08,
+        },
+        Input {
+            name: "P1 Fire",
+            port: 0,
+            mask: 0x80,
+        },
+        Input {
+            name: "P2 Up",
+            port: 1,
+            mask: 0x01,
+        },
+        Input {
+            name: "P2 Down",
+            port: 1,
+            mask: 0x02,
+        },
+        Input {
+            name: "P2 Left",
+            port: 1,
+            mask: 0x04,
+        },
+        Input {
+            name: "P2 Right",
+            port: 1,
+            mask: 0x08,
+        },
+        Input {
+            name: "P2 Fire",
+            port: 1,
+            mask: 0x80,
+        },
+        Input {
+            name: "Coin",
+            port: 2,
+            mask: 0x40,
+        },
+        Input {
+            name: "P1 Start",
+            port: 2,
+            mask: 0x80,
+        },
+    ],
+    dips: &[
+        DipSwitch {
+            name: "Coinage",
+            port: 2,
+            mask: 0x03,
+            settings: &[
+                ("1 coin", 0x00),
+                ("2 coins", 0x01),
+                ("3 coins", 0x02),
+                ("4 coins", 0x03),
+            ],
+            default: 0,
+        },
+        DipSwitch {
+            name: "Credits",
+            port: 2,
+            mask: 0x0C,
+            settings: &[("1", 0x00), ("2", 0x04), ("3", 0x08), ("4", 0x0C)],
+            default: 0,
+        },
+        DipSwitch {
+            name: "Game time",
+            port: 2,
+            mask: 0x30,
+            settings: &[("60", 0x00), ("70", 0x10), ("80", 0x20), ("90", 0x30)],
+            default: 0,
+        },
+    ],
+    shift: ShiftPorts {
+        count: 2,
+        data: 4,
+        result: 3,
+        reversible: false,
+    },
+    colour: Colour::Mono,
+    hiscore: None,
+    inspector: false,
+    disassembly: &DisassemblyProfile::GUN_FIGHT,
+    cheats: "",
+};
+
+/// Part II and Deluxe keep the Space Invaders controls but have one switch
+/// for 3 or 4 lives.
+const PART_II_DIPS: &[DipSwitch] = &[
+    DipSwitch {
+        name: "Lives",
+        port: 2,
+        mask: 0x01,
+        settings: &[("3", 0x00), ("4", 0x01)],
+        default: 0,
+    },
+    DipSwitch {
+        name: "Coin info",
+        port: 2,
+        mask: 0x80,
+        settings: &[("On", 0x00), ("Off", 0x80)],
+        default: 0,
+    },
+];
+
+/// Space Invaders Part II switches to the second colour map while player 2
+/// is up.
+pub const INVADPT2: BoardProfile = BoardProfile {
+    rom: &romset::INVADPT2,
+    dips: PART_II_DIPS,
+    colour: Colour::Prom {
+        select: Some((5, 0x20)),
+    },
+    hiscore: None,
+    inspector: false,
+    disassembly: &DisassemblyProfile::SPACE_INVADERS_PART_II,
+    cheats: "",
+    ..INVADERS
+};
+
+pub const INVADDLX: BoardProfile = BoardProfile {
+    rom: &romset::INVADDLX,
+    colour: Colour::Mono,
+    disassembly: &DisassemblyProfile::SPACE_INVADERS_DELUXE,
+    ..INVADPT2
+};
+
+/// The Space Invaders program and RAM layout, in colour.
+pub const SICV: BoardProfile = BoardProfile {
+    rom: &romset::SICV,
+    colour: Colour::Prom {
+        select: Some((5, 0x20)),
+    },
+    ..INVADERS
+};
+
+/// Lunar Rescue and Balloon Bomber read the controls where Space Invaders
+/// does, with the lives on the low two switches.
+const TAITO_LIVES: &[DipSwitch] = &[DipSwitch {
+    name: "Lives",
+    port: 2,
+    mask: 0x03,
+    settings: &[("3", 0x00), ("4", 0x01), ("5", 0x02), ("6", 0x03)],
+    default: 0,
+}];
+
+/// Lunar Rescue. Both colour maps hold the same PROM, so the flip bit on
+/// port 5 changes nothing.
+pub const LRESCUE: BoardProfile = BoardProfile {
+    rom: &romset::LRESCUE,
+    dips: TAITO_LIVES,
+    disassembly: &DisassemblyProfile::LUNAR_RESCUE,
+    ..INVADPT2
+};
+
+/// Balloon Bomber switches colour maps on port 5 as Part II does.
+pub const BALLBOMB: BoardProfile = BoardProfile {
+    rom: &romset::BALLBOMB,
+    dips: TAITO_LIVES,
+    disassembly: &DisassemblyProfile::BALLOON_BOMBER,
+    ..INVADPT2
+};
+
+pub const PROFILES: &[BoardProfile] = &[
+    INVADERS, GUNFIGHT, INVADPT2, INVADDLX, SICV, LRESCUE, BALLBOMB,
+];
+
+pub fn find(name: &str) -> Option<&'static BoardProfile> {
+    PROFILES.iter().find(|profile| profile.name() == name)
+}
+
+#[derive(Clone, Debug)]
+pub struct MidwayBoard {
+    profile: &'static BoardProfile,
+    pressed: [u8; PORTS],
+    dips: [u8; PORTS],
+    outputs: [u8; PORTS],
+    shift: u16,
+    shift_count: u8,
+    shift_reversed: bool,
+}
+
+impl MidwayBoard {
+    pub fn new(profile: &'static BoardProfile) -> Self {
+        let mut board = MidwayBoard {
+            profile,
+            pressed: [0; PORTS],
+            dips: [0; PORTS],
+            outputs: [0; PORTS],
+            shift: 0,
+            shift_count: 0,
+            shift_reversed: false,
+        };
+        for dip in profile.dips {
+            board.set_dip_bits(dip, dip.settings[dip.default].1);
+        }
+        board
+    }
+
+    pub fn profile(&self) -> &'static BoardProfile {
+        self.profile
+    }
+
+    /// Presses or releases a named control. Returns false if the profile
+    /// has no such control.
+    pub fn set_input(&mut self, name: &str, pressed: bool) -> bool {
+        let Some(input) = self.profile.input(name) else {
+            return false;
+        };
+        let bits = &mut self.pressed[input.port as usize % PORTS];
+        if pressed {
+            *bits |= input.mask;
+        } else {
+            *bits &= !input.mask;
+        }
+        true
+    }
+
+    /// Sets every control bit of `port` at once, e.g. from an agent's action.
+    pub fn set_pressed(&mut self, port: u8, bits: u8) {
+        self.pressed[port as usize % PORTS] = bits;
+    }
+
+    pub fn pressed(&self, port: u8) -> u8 {
+        self.pressed[port as usize % PORTS]
+    }
+
+    /// Changes a DIP switch by setting name. Returns false if either name is
+    /// unknown. Most games only read their switches at reset.
+    pub fn set_dip(&mut self, name: &str, setting: &str) -> bool {
+        let Some(dip) = self.profile.dip(name) else {
+            return false;
+        };
+        match dip
+            .settings
+            .iter()
+            .find(|(label, _)| label.eq_ignore_ascii_case(setting))
+        {
+            Some(&(_, bits)) => {
+                self.set_dip_bits(dip, bits);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Current setting of a DIP switch.
+    pub fn dip(&self, name: &str) -> Option<&'static str> {
+        let dip = self.profile.dip(name)?;
+        let bits = self.dips[dip.port as usize % PORTS] & dip.mask;
+        dip.settings
+            .iter()
+            .find(|&&(_, value)| value == bits)
+            .map(|&(label, _)| label)
+    }
+
+    /// What an `IN` from `port` returns, apart from the shift register.
+    pub fn port_value(&self, port: u8) -> u8 {
+        let idle = self
+            .profile
+            .idle
+            .iter()
+            .find(|&&(idle_port, _)| idle_port == port)
+            .map_or(0, |&(_, value)| value);
+        let port = port as usize % PORTS;
+        (idle | self.dips[port]) ^ self.pressed[port]
+    }
+
+    /// The last value written to an output port, e.g. sound or colour map
+    /// latches.
+    pub fn last_output(&self, port: u8) -> u8 {
+        self.outputs[port as usize % PORTS]
+    }
+
+    fn set_dip_bits(&mut self, dip: &DipSwitch, bits: u8) {
+        let port = &mut self.dips[dip.port as usize % PORTS];
+        *port = *port & !dip.mask | bits & dip.mask;
+    }
+}
+
+impl IoBus for MidwayBoard {
+    fn input(&mut self, port: u8) -> u8 {
+        if port == self.profile.shift.result {
+            let result = (self.shift >> (8 - self.shift_count)) as u8;
+            if self.shift_reversed {
+                result.reverse_bits()
+            } else {
+                result
+            }
+        } else {
+            self.port_value(port)
+        }
+    }
+
+    fn output(&mut self, port: u8, value: u8) {
+        // Sound and watchdog writes are only latched
+        self.outputs[port as usize % PORTS] = value;
+        if port == self.profile.shift.count {
+            self.shift_count = value & 0x07;
+            self.shift_reversed = self.profile.shift.reversible && value & 0x08 != 0;
+        } else if port == self.profile.shift.data {
+            self.shift = self.shift >> 8 | (value as u16) << 8;
+        }
+    }
+}
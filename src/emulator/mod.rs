@@ -1,5 +1,24 @@
+pub mod altair;
+pub mod batch;
+pub mod board;
+pub mod cheat;
+pub mod console;
+pub mod cpm;
 pub mod cpu;
 pub mod data_types;
+pub mod dcdd;
+pub mod disk;
+pub mod hiscore;
+pub mod history;
 pub mod input;
+pub mod io;
+pub mod inspector;
 pub mod machine;
-pub mod instruction_lookup;
+pub mod midway;
+pub mod pic;
+pub mod pit;
+pub mod recompiler;
+pub mod romset;
+pub mod trace;
+pub mod usart;
+pub mod video;
@@ -0,0 +1,333 @@
+//! The Intel 8259 programmable interrupt controller, in 8080 mode.
+//!
+//! Eight request lines, IR0 to IR7, are driven with [`Pic8259::set_line`] or
+//! joined to device pins with [`PortMap::connect`](super::io::PortMap::connect).
+//! The highest-priority unmasked request not outranked by one in service
+//! interrupts the CPU, and the controller answers the three INTA cycles
+//! with a `CALL` to its vector.
+//!
+//! It decodes two ports. Writes to `base` with bit 4 set start
+//! initialisation with ICW1, followed at `base + 1` by ICW2, then ICW3 and
+//! ICW4 if ICW1 asked for them. After that `base + 1` takes the mask (OCW1)
+//! and `base` the EOI and priority commands (OCW2) and the read and special
+//! mask commands (OCW3). Reads of `base` return the request or in-service
+//! register, or poll, as OCW3 last chose; reads of `base + 1` the mask.
+//!
+//! Cascading is not modelled: ICW3 is accepted and ignored.
+
+use super::io::Device;
+
+/// ICW1 bits.
+pub const ICW1: u8 = 0x10;
+pub const ICW1_IC4: u8 = 0x01;
+pub const ICW1_SINGLE: u8 = 0x02;
+pub const ICW1_INTERVAL_4: u8 = 0x04;
+pub const ICW1_LEVEL: u8 = 0x08;
+
+/// ICW4 bits.
+pub const ICW4_AUTO_EOI: u8 = 0x02;
+
+/// OCW2 commands, in bits 5 to 7; the specific ones take a level in bits 0
+/// to 2.
+pub const EOI: u8 = 0x20;
+pub const SPECIFIC_EOI: u8 = 0x60;
+pub const ROTATE_EOI: u8 = 0xA0;
+pub const ROTATE_SPECIFIC_EOI: u8 = 0xE0;
+pub const SET_PRIORITY: u8 = 0xC0;
+pub const ROTATE_AUTO_EOI_SET: u8 = 0x80;
+pub const ROTATE_AUTO_EOI_CLEAR: u8 = 0x00;
+
+/// OCW3 commands.
+pub const OCW3: u8 = 0x08;
+pub const READ_IRR: u8 = 0x0A;
+pub const READ_ISR: u8 = 0x0B;
+pub const POLL: u8 = 0x0C;
+pub const SET_SPECIAL_MASK: u8 = 0x68;
+pub const CLEAR_SPECIAL_MASK: u8 = 0x48;
+
+/// Output pin, for [`PortMap::wire`](super::io::PortMap::wire).
+pub const INT_PIN: usize = 0;
+
+const CALL: u8 = 0xCD;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Expect {
+    Icw2,
+    Icw3,
+    Icw4,
+    Ready,
+}
+
+#[derive(Clone, Debug)]
+pub struct Pic8259 {
+    expect: Expect,
+    icw1: u8,
+    icw4: u8,
+    /// ICW2: the high byte of every vector.
+    vector_high: u8,
+    /// ICW1 bits 5 to 7: the low byte of the vector table.
+    vector_low: u8,
+    irr: u8,
+    isr: u8,
+    imr: u8,
+    /// Request line levels, for edge detection.
+    lines: u8,
+    /// The level with the lowest priority; IR7 until rotated.
+    lowest: u8,
+    rotate_on_auto_eoi: bool,
+    special_mask: bool,
+    read_isr: bool,
+    poll: bool,
+    /// The level being acknowledged by the INTA cycles under way.
+    acknowledging: u8,
+}
+
+impl Default for Pic8259 {
+    fn default() -> Self {
+        Pic8259 {
+            expect: Expect::Ready,
+            icw1: 0,
+            icw4: 0,
+            vector_high: 0,
+            vector_low: 0,
+            irr: 0,
+            isr: 0,
+            imr: 0,
+            lines: 0,
+            lowest: 7,
+            rotate_on_auto_eoi: false,
+            special_mask: false,
+            read_isr: false,
+            poll: false,
+            acknowledging: 7,
+        }
+    }
+}
+
+impl Pic8259 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drives request line `ir`. In edge-triggered mode a rising edge
+    /// latches a request; in level-triggered mode the request follows the
+    /// line. There are eight lines; any other `ir` is ignored.
+    pub fn set_line(&mut self, ir: usize, level: bool) {
+        if ir >= 8 {
+            return;
+        }
+        let bit = 1 << ir;
+        let rising = level && self.lines & bit == 0;
+        if level {
+            self.lines |= bit;
+        } else {
+            self.lines &= !bit;
+        }
+        if self.icw1 & ICW1_LEVEL != 0 {
+            self.irr = (self.irr & !bit) | (self.lines & bit);
+        } else if rising {
+            self.irr |= bit;
+        }
+    }
+
+    pub fn request_register(&self) -> u8 {
+        self.irr
+    }
+
+    pub fn in_service_register(&self) -> u8 {
+        self.isr
+    }
+
+    pub fn mask(&self) -> u8 {
+        self.imr
+    }
+
+    /// How far `ir` is from the top priority, 0 being the highest.
+    fn rank(&self, ir: u8) -> u8 {
+        ir.wrapping_sub(self.lowest).wrapping_sub(1) & 0x07
+    }
+
+    /// The highest-priority level set in `bits`.
+    fn highest(&self, bits: u8) -> Option<u8> {
+        (0..8)
+            .map(|rank| (self.lowest + 1 + rank) & 0x07)
+            .find(|&ir| bits & (1 << ir) != 0)
+    }
+
+    /// The level that would interrupt the CPU now, if any.
+    fn next_request(&self) -> Option<u8> {
+        let ir = self.highest(self.irr & !self.imr)?;
+        // Special mask mode lets lower levels in while a masked one is in
+        // service
+        let blocking = if self.special_mask {
+            self.isr & !self.imr
+        } else {
+            self.isr
+        };
+        match self.highest(blocking) {
+            Some(service) if self.rank(service) <= self.rank(ir) => None,
+            _ => Some(ir),
+        }
+    }
+
+    fn end_of_interrupt(&mut self, ir: Option<u8>, rotate: bool) {
+        let Some(ir) = ir else { return };
+        self.isr &= !(1 << ir);
+        if rotate {
+            self.lowest = ir;
+        }
+    }
+
+    fn command(&mut self, value: u8) {
+        if value & ICW1 != 0 {
+            self.icw1 = value;
+            self.icw4 = 0;
+            self.vector_low = if value & ICW1_INTERVAL_4 != 0 {
+                value & 0xE0
+            } else {
+                value & 0xC0
+            };
+            self.irr &= if value & ICW1_LEVEL != 0 {
+                self.lines
+            } else {
+                0
+            };
+            self.isr = 0;
+            self.imr = 0;
+            self.lowest = 7;
+            self.rotate_on_auto_eoi = false;
+            self.special_mask = false;
+            self.read_isr = false;
+            self.poll = false;
+            self.expect = Expect::Icw2;
+        } else if value & OCW3 != 0 {
+            if value & 0x40 != 0 {
+                self.special_mask = value & 0x20 != 0;
+            }
+            self.poll = value & 0x04 != 0;
+            if value & 0x02 != 0 {
+                self.read_isr = value & 0x01 != 0;
+            }
+        } else {
+            let level = value & 0x07;
+            match value & 0xE0 {
+                EOI => self.end_of_interrupt(self.highest(self.isr), false),
+                SPECIFIC_EOI => self.end_of_interrupt(Some(level), false),
+                ROTATE_EOI => self.end_of_interrupt(self.highest(self.isr), true),
+                ROTATE_SPECIFIC_EOI => self.end_of_interrupt(Some(level), true),
+                SET_PRIORITY => self.lowest = level,
+                ROTATE_AUTO_EOI_SET => self.rotate_on_auto_eoi = true,
+                ROTATE_AUTO_EOI_CLEAR => self.rotate_on_auto_eoi = false,
+                _ => {}
+            }
+        }
+    }
+
+    fn data(&mut self, value: u8) {
+        self.expect = match self.expect {
+            Expect::Icw2 => {
+                self.vector_high = value;
+                if self.icw1 & ICW1_SINGLE == 0 {
+                    Expect::Icw3
+                } else if self.icw1 & ICW1_IC4 != 0 {
+                    Expect::Icw4
+                } else {
+                    Expect::Ready
+                }
+            }
+            Expect::Icw3 if self.icw1 & ICW1_IC4 != 0 => Expect::Icw4,
+            Expect::Icw3 => Expect::Ready,
+            Expect::Icw4 => {
+                self.icw4 = value;
+                Expect::Ready
+            }
+            Expect::Ready => {
+                self.imr = value;
+                Expect::Ready
+            }
+        };
+    }
+
+    /// Moves a request into service, as the first INTA cycle or a poll
+    /// does. With none left, as when the line dropped too soon, it answers
+    /// as IR7 without putting anything in service.
+    fn take(&mut self) -> Option<u8> {
+        let ir = self.next_request();
+        if let Some(ir) = ir {
+            self.isr |= 1 << ir;
+            if self.icw1 & ICW1_LEVEL == 0 {
+                self.irr &= !(1 << ir);
+            }
+        }
+        self.acknowledging = ir.unwrap_or(7);
+        ir
+    }
+}
+
+impl Device for Pic8259 {
+    fn ports(&self) -> u8 {
+        2
+    }
+
+    fn read(&mut self, offset: u8) -> u8 {
+        if offset == 1 {
+            self.imr
+        } else if self.poll {
+            // The poll is taken as an acknowledge: bit 7 says whether there
+            // was a request, and bits 0 to 2 which
+            self.poll = false;
+            match self.take() {
+                Some(ir) => 0x80 | ir,
+                None => 0,
+            }
+        } else if self.read_isr {
+            self.isr
+        } else {
+            self.irr
+        }
+    }
+
+    fn write(&mut self, offset: u8, value: u8) {
+        if offset == 0 {
+            self.command(value);
+        } else {
+            self.data(value);
+        }
+    }
+
+    fn set_input(&mut self, input: usize, level: bool) {
+        self.set_line(input, level);
+    }
+
+    fn pin(&self, pin: usize) -> bool {
+        pin == INT_PIN && self.next_request().is_some()
+    }
+
+    fn requesting(&self) -> bool {
+        self.expect == Expect::Ready && self.next_request().is_some()
+    }
+
+    fn acknowledge(&mut self, cycle: usize) -> u8 {
+        match cycle {
+            0 => {
+                self.take();
+                CALL
+            }
+            1 => {
+                let ir = self.acknowledging;
+                if self.icw1 & ICW1_INTERVAL_4 != 0 {
+                    self.vector_low | ir << 2
+                } else {
+                    self.vector_low | ir << 3
+                }
+            }
+            _ => {
+                if self.icw4 & ICW4_AUTO_EOI != 0 {
+                    let ir = self.acknowledging;
+                    self.end_of_interrupt(Some(ir), self.rotate_on_auto_eoi);
+                }
+                self.vector_high
+            }
+        }
+    }
+}
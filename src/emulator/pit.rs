@@ -0,0 +1,296 @@
+//! The Intel 8253 programmable interval timer.
+//!
+//! Three 16-bit down counters at `base` to `base + 2`, and the control word
+//! at `base + 3`. Each counter has its own clock input, given in Hz against
+//! the CPU clock, its own gate (high unless set) and an OUT pin that can be
+//! wired to an interrupt. All six modes are modelled a clock at a time:
+//!
+//! | Mode | OUT |
+//! |------|-----|
+//! | 0 | Low from the count being written until it reaches zero |
+//! | 1 | Low from a gate rising edge until the count reaches zero |
+//! | 2 | Low for one clock every N clocks |
+//! | 3 | Square wave: high for N/2 clocks (rounded up), low for N/2 |
+//! | 4 | Low for one clock when the count written reaches zero |
+//! | 5 | As 4, counting from a gate rising edge |
+
+use super::io::Device;
+
+/// Control word fields.
+pub const READ_LOAD_LATCH: u8 = 0x00;
+pub const READ_LOAD_LSB: u8 = 0x10;
+pub const READ_LOAD_MSB: u8 = 0x20;
+pub const READ_LOAD_BOTH: u8 = 0x30;
+
+#[derive(Clone, Debug, Default)]
+struct Counter {
+    mode: u8,
+    access: u8,
+    bcd: bool,
+    /// What was last written, in counts (0 already turned into 65536 or
+    /// 10000).
+    reload: u32,
+    count: u32,
+    /// LSB of a two-byte write, waiting for the MSB.
+    low: Option<u8>,
+    /// MSB of a two-byte read still to come.
+    high_next: bool,
+    latched: Option<u16>,
+    /// Counting, as opposed to waiting for a count or a gate trigger.
+    running: bool,
+    /// Modes 4 and 5 strobe once per count written or trigger.
+    strobed: bool,
+    out: bool,
+    /// OUT went low during the last tick, perhaps for less than a tick.
+    fell: bool,
+    gate: bool,
+    clock: u32,
+    phase: u64,
+}
+
+impl Counter {
+    fn control(&mut self, value: u8) {
+        if value & 0x30 == READ_LOAD_LATCH {
+            self.latched.get_or_insert(self.value());
+            return;
+        }
+        self.access = value & 0x30;
+        self.mode = match (value >> 1) & 0x07 {
+            6 => 2,
+            7 => 3,
+            mode => mode,
+        };
+        self.bcd = value & 0x01 != 0;
+        self.low = None;
+        self.high_next = false;
+        self.latched = None;
+        self.running = false;
+        self.out = self.mode != 0;
+    }
+
+    /// The count as the CPU reads it.
+    fn value(&self) -> u16 {
+        let count = self.count % if self.bcd { 10_000 } else { 0x10000 };
+        if self.bcd {
+            (0..4).fold(0, |bcd, digit| {
+                bcd | (((count / 10u32.pow(digit)) % 10) as u16) << (digit * 4)
+            })
+        } else {
+            count as u16
+        }
+    }
+
+    fn read(&mut self) -> u8 {
+        let value = self.latched.unwrap_or_else(|| self.value());
+        let byte = match self.access {
+            READ_LOAD_MSB => (value >> 8) as u8,
+            READ_LOAD_BOTH if self.high_next => (value >> 8) as u8,
+            READ_LOAD_BOTH => {
+                self.high_next = true;
+                return value as u8;
+            }
+            _ => value as u8,
+        };
+        self.high_next = false;
+        self.latched = None;
+        byte
+    }
+
+    fn write(&mut self, value: u8) {
+        let written = match self.access {
+            READ_LOAD_LSB => value as u16,
+            READ_LOAD_MSB => (value as u16) << 8,
+            _ => match self.low.take() {
+                Some(low) => u16::from_le_bytes([low, value]),
+                None => {
+                    self.low = Some(value);
+                    return;
+                }
+            },
+        };
+        self.reload = if self.bcd {
+            let count = (0..4).fold(0, |count, digit| {
+                count + ((written >> (digit * 4)) & 0x0F) as u32 * 10u32.pow(digit)
+            });
+            if count == 0 {
+                10_000
+            } else {
+                count
+            }
+        } else if written == 0 {
+            0x10000
+        } else {
+            written as u32
+        };
+        match self.mode {
+            0 | 4 => {
+                self.count = self.reload;
+                self.running = true;
+                self.strobed = false;
+                self.out = self.mode == 4;
+            }
+            // A new count takes effect at the next reload
+            2 | 3 if self.running => {}
+            2 | 3 => {
+                self.count = self.reload;
+                self.running = true;
+                self.out = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_gate(&mut self, gate: bool) {
+        let rising = gate && !self.gate;
+        self.gate = gate;
+        match self.mode {
+            1 | 5 if rising && self.reload != 0 => {
+                self.count = self.reload;
+                self.running = true;
+                self.strobed = false;
+                self.out = self.mode == 5;
+            }
+            2 | 3 if rising && self.reload != 0 => {
+                self.count = self.reload;
+                self.running = true;
+            }
+            2 | 3 if !gate => self.out = true,
+            _ => {}
+        }
+    }
+
+    fn clock(&mut self) {
+        let out = self.out;
+        self.count_down();
+        self.fell |= out && !self.out;
+    }
+
+    fn count_down(&mut self) {
+        if !self.running || (!self.gate && matches!(self.mode, 0 | 2 | 3 | 4)) {
+            return;
+        }
+        match self.mode {
+            0 | 1 => {
+                self.count = (self.count + 0xFFFF) % 0x10000;
+                if self.count == 0 {
+                    self.out = true;
+                    if self.mode == 1 {
+                        self.running = false;
+                    }
+                }
+            }
+            2 => {
+                self.count -= 1;
+                if self.count == 1 {
+                    self.out = false;
+                } else if self.count == 0 {
+                    self.out = true;
+                    self.count = self.reload;
+                }
+            }
+            3 => {
+                self.count -= 1;
+                if self.count == 0 {
+                    self.out = true;
+                    self.count = self.reload;
+                } else if self.count == self.reload / 2 {
+                    self.out = false;
+                }
+            }
+            _ => {
+                self.out = true;
+                self.count = (self.count + 0xFFFF) % 0x10000;
+                if self.count == 0 && !self.strobed {
+                    self.out = false;
+                    self.strobed = true;
+                }
+            }
+        }
+    }
+}
+
+pub struct Pit8253 {
+    counters: [Counter; 3],
+    cpu_clock: u32,
+}
+
+impl Pit8253 {
+    /// A timer on a CPU running at `cpu_clock` Hz whose counters are fed
+    /// `clocks` Hz. A clock of 0 leaves that counter unconnected.
+    ///
+    /// # Panics
+    /// If `cpu_clock` is 0.
+    pub fn new(cpu_clock: u32, clocks: [u32; 3]) -> Self {
+        assert_ne!(cpu_clock, 0, "Pit8253::new needs a CPU clock");
+        let mut pit = Pit8253 {
+            counters: Default::default(),
+            cpu_clock,
+        };
+        for (counter, clock) in pit.counters.iter_mut().zip(clocks) {
+            counter.clock = clock;
+            counter.gate = true;
+        }
+        pit
+    }
+
+    pub fn set_gate(&mut self, counter: usize, gate: bool) {
+        self.counters[counter].set_gate(gate);
+    }
+
+    pub fn out(&self, counter: usize) -> bool {
+        self.counters[counter].out
+    }
+
+    /// The count as a latch command would read it.
+    pub fn count(&self, counter: usize) -> u16 {
+        self.counters[counter].value()
+    }
+}
+
+impl Device for Pit8253 {
+    fn ports(&self) -> u8 {
+        4
+    }
+
+    fn read(&mut self, offset: u8) -> u8 {
+        match self.counters.get_mut(offset as usize) {
+            Some(counter) => counter.read(),
+            // The control word cannot be read back
+            None => 0xFF,
+        }
+    }
+
+    fn write(&mut self, offset: u8, value: u8) {
+        match offset {
+            0..=2 => self.counters[offset as usize].write(value),
+            _ => {
+                if let Some(counter) = self.counters.get_mut((value >> 6) as usize) {
+                    counter.control(value);
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        for counter in &mut self.counters {
+            if counter.clock == 0 {
+                continue;
+            }
+            counter.fell = false;
+            counter.phase += cycles as u64 * counter.clock as u64;
+            let counts = counter.phase / self.cpu_clock as u64;
+            counter.phase %= self.cpu_clock as u64;
+            for _ in 0..counts {
+                counter.clock();
+            }
+        }
+    }
+
+    /// OUT reads low for a whole tick after going low, so that a one-clock
+    /// pulse is seen however many clocks the tick covers.
+    fn pin(&self, pin: usize) -> bool {
+        self.counters
+            .get(pin)
+            .is_some_and(|counter| counter.out && !counter.fell)
+    }
+}
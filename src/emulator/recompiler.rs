@@ -0,0 +1,217 @@
+//! Basic-block recompiler.
+//!
+//! Straight-line runs of instructions are translated once into threaded code
+//! (a list of the interpreter's opcode handlers) and then replayed without
+//! fetching or dispatching. A block ends after any branch or any instruction
+//! that writes memory, so a store can never change code later in the same
+//! block. Each block keeps a copy of the bytes it was built from and is
+//! rebuilt if they differ on entry, which catches self-modifying code.
+//!
+//! The history ring buffer and tracer are only fed by the interpreter.
+
+use super::cpu::{emulate_8080_op, OpHandler, OPCODE_TABLE};
+use super::data_types::{State8080, CYCLE_TABLE};
+use crate::disassembler::decode;
+use crate::disassembler::instruction::Flow;
+use std::fmt;
+
+const MAX_BLOCK_INSTRUCTIONS: usize = 32;
+
+struct Block {
+    ops: Vec<OpHandler>,
+    /// Memory the block was compiled from, checked on every entry.
+    bytes: Vec<u8>,
+    cycles: u32,
+}
+
+/// What running one block did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Executed {
+    pub cycles: u32,
+    pub instructions: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecompilerStats {
+    pub blocks_compiled: u64,
+    pub blocks_executed: u64,
+    /// Blocks rebuilt because the code under them changed.
+    pub invalidations: u64,
+}
+
+pub struct Recompiler {
+    /// Compiled block starting at each address.
+    blocks: Box<[Option<Box<Block>>]>,
+    stats: RecompilerStats,
+}
+
+impl Default for Recompiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recompiler {
+    pub fn new() -> Self {
+        Recompiler {
+            blocks: (0..0x10000).map(|_| None).collect(),
+            stats: RecompilerStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> RecompilerStats {
+        self.stats
+    }
+
+    /// Drops every compiled block, e.g. after loading a new program.
+    pub fn invalidate_all(&mut self) {
+        self.blocks.iter_mut().for_each(|block| *block = None);
+    }
+
+    /// Runs the block at `state.pc`, compiling it first if needed.
+    pub fn run_block(&mut self, state: &mut State8080) -> Executed {
+        let pc = state.pc;
+        let start = pc as usize;
+        let valid = match &self.blocks[start] {
+            Some(block) => state.memory[start..start + block.bytes.len()] == block.bytes[..],
+            None => false,
+        };
+        if !valid {
+            if self.blocks[start].is_some() {
+                self.stats.invalidations += 1;
+            }
+            self.blocks[start] = Some(Box::new(compile(&state.memory, pc)));
+            self.stats.blocks_compiled += 1;
+        }
+
+        let block = self.blocks[start].as_ref().unwrap();
+        for op in &block.ops {
+            op(state);
+        }
+        self.stats.blocks_executed += 1;
+        Executed {
+            cycles: block.cycles,
+            instructions: block.ops.len(),
+        }
+    }
+}
+
+fn compile(memory: &[u8], start: u16) -> Block {
+    let mut ops = Vec::new();
+    let mut cycles = 0;
+    let mut length = 0;
+    let mut addr = start;
+    loop {
+        let instruction = decode(memory, addr);
+        ops.push(OPCODE_TABLE[instruction.opcode as usize]);
+        cycles += CYCLE_TABLE[instruction.opcode as usize] as u32;
+        length += instruction.length as usize;
+
+        let next = instruction.next_address();
+        let ends_block = instruction.flow != Flow::Next
+            || !instruction.documented
+            || writes_memory(instruction.opcode)
+            || ops.len() == MAX_BLOCK_INSTRUCTIONS
+            // Never let a block run off the top of memory
+            || next <= addr;
+        if ends_block {
+            break;
+        }
+        addr = next;
+    }
+    let end = (start as usize + length).min(memory.len());
+    Block {
+        ops,
+        bytes: memory[start as usize..end].to_vec(),
+        cycles,
+    }
+}
+
+/// Instructions that store to memory (CALL and RST end blocks anyway).
+fn writes_memory(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        0x02 | 0x12 | 0x22 | 0x32 | 0x34 | 0x35 | 0x36 | 0xC5 | 0xD5 | 0xE5 | 0xF5 | 0xE3
+    ) || (0x70..=0x77).contains(&opcode)
+}
+
+/// First difference found between the interpreter and the recompiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// PC of the block whose execution diverged.
+    pub block: u16,
+    pub description: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Engines diverged in block {:04X}: {}",
+            self.block, self.description
+        )
+    }
+}
+
+/// Differential mode: runs one block on `recompiled` with the recompiler and
+/// the same number of instructions on `reference` with the interpreter, then
+/// checks that both states are identical.
+pub fn run_lockstep(
+    recompiler: &mut Recompiler,
+    reference: &mut State8080,
+    recompiled: &mut State8080,
+) -> Result<Executed, Mismatch> {
+    let block = recompiled.pc;
+    if reference.pc != block {
+        return Err(Mismatch {
+            block,
+            description: format!("PC {:04X} vs {:04X} before the block", reference.pc, block),
+        });
+    }
+    let executed = recompiler.run_block(recompiled);
+    for _ in 0..executed.instructions {
+        emulate_8080_op(reference);
+    }
+    match compare_states(reference, recompiled) {
+        None => Ok(executed),
+        Some(description) => Err(Mismatch { block, description }),
+    }
+}
+
+/// Describes the first difference between two CPU states, if any.
+pub fn compare_states(expected: &State8080, actual: &State8080) -> Option<String> {
+    let registers = |s: &State8080| {
+        [
+            ("A", s.a as u16),
+            ("B", s.b as u16),
+            ("C", s.c as u16),
+            ("D", s.d as u16),
+            ("E", s.e as u16),
+            ("H", s.h as u16),
+            ("L", s.l as u16),
+            ("SP", s.sp),
+            ("PC", s.pc),
+            ("flags", s.get_flags_as_byte() as u16),
+            ("interrupts", s.int_enable as u16),
+        ]
+    };
+    for ((name, left), (_, right)) in registers(expected).into_iter().zip(registers(actual)) {
+        if left != right {
+            return Some(format!("{} {:04X} vs {:04X}", name, left, right));
+        }
+    }
+    if expected.memory[..] == actual.memory[..] {
+        return None;
+    }
+    expected
+        .memory
+        .iter()
+        .zip(actual.memory.iter())
+        .position(|(left, right)| left != right)
+        .map(|addr| {
+            format!(
+                "memory[{:04X}] {:02X} vs {:02X}",
+                addr, expected.memory[addr], actual.memory[addr]
+            )
+        })
+}
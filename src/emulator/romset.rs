@@ -0,0 +1,575 @@
+//! Known ROM sets and their checksums.
+//!
+//! Each [`RomSet`] lists its files with where they load and the size, CRC32
+//! and SHA1 of a good dump. [`RomSet::load`] checks every file
+//! before anything is copied into memory, so a missing or corrupt dump is
+//! reported by name instead of being run. Sets whose checksums have not been
+//! recorded yet are checked for size only; see [`RomFile::verified`].
+//!
+//! Sets are found the way MAME finds them: each directory of a ROM path is
+//! searched for `<set>.zip`, then a `<set>/` directory, then loose files.
+//! Inside a zip, files are matched by name or, failing that, by CRC32, so
+//! renamed dumps still load.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor, ErrorKind, Read, Seek};
+use std::path::{Path, PathBuf};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomFile {
+    pub name: &'static str,
+    /// Where the file loads: a CPU address for program ROMs, an offset into
+    /// [`RomImage::proms`] for colour PROMs.
+    pub address: usize,
+    pub size: usize,
+    pub crc32: u32,
+    /// Lowercase hex, empty if not recorded.
+    pub sha1: &'static str,
+}
+
+impl RomFile {
+    /// A file whose checksums have not been recorded, for sets no verified
+    /// dump has been checked against yet.
+    pub const fn unverified(name: &'static str, address: usize, size: usize) -> Self {
+        RomFile {
+            name,
+            address,
+            size,
+            crc32: 0,
+            sha1: "",
+        }
+    }
+
+    /// Whether the checksums of a good dump are known. Files without them
+    /// load on their size alone and are never identified.
+    pub fn verified(&self) -> bool {
+        !self.sha1.is_empty()
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.verified()
+            && data.len() == self.size
+            && crc32(data) == self.crc32
+            && sha1(data) == self.sha1
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RomSet {
+    /// Short name, also used for the hiscore file.
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Program ROMs, in load order.
+    pub files: &'static [RomFile],
+    /// Colour PROMs, which the video hardware reads and the CPU cannot see.
+    pub proms: &'static [RomFile],
+}
+
+/// A set once loaded and verified.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RomImage {
+    /// The CPU address space from 0 to the end of the last program ROM. Gaps
+    /// between ROMs read 0.
+    pub program: Vec<u8>,
+    pub proms: Vec<u8>,
+}
+
+impl RomImage {
+    fn empty(set: &RomSet) -> Self {
+        RomImage {
+            program: vec![0; set.size()],
+            proms: vec![0; region_size(set.proms)],
+        }
+    }
+
+    fn place(&mut self, set: &RomSet, file: &RomFile, data: &[u8]) {
+        let region = if set.proms.contains(file) {
+            &mut self.proms
+        } else {
+            &mut self.program
+        };
+        region[file.address..file.address + file.size].copy_from_slice(data);
+    }
+}
+
+pub const INVADERS: RomSet = RomSet {
+    name: "invaders",
+    description: "Space Invaders (Midway)",
+    files: &[
+        RomFile {
+            name: "invaders.h",
+            address: 0x0000,
+            size: 0x800,
+            crc32: 0x734f5ad8,
+            sha1: "ff6200af4c9110d8181249cbcef1a8a40fa40b7f",
+        },
+        RomFile {
+            name: "invaders.g",
+            address: 0x0800,
+            size: 0x800,
+            crc32: 0x6bfaca4a,
+            sha1: "16f48649b531bdef8c2d1446c429b5f414524350",
+        },
+        RomFile {
+            name: "invaders.f",
+            address: 0x1000,
+            size: 0x800,
+            crc32: 0x0ccead96,
+            sha1: "537aef03468f63c5b9e11dd61e253f7ae17d9743",
+        },
+        RomFile {
+            name: "invaders.e",
+            address: 0x1800,
+            size: 0x800,
+            crc32: 0x14e538b0,
+            sha1: "1d6ca0c99f9df71e2990b610deb9d7da0125e2d8",
+        },
+    ],
+    proms: &[],
+};
+
+pub const GUNFIGHT: RomSet = RomSet {
+    name: "gunfight",
+    description: "Gun Fight (Midway)",
+    files: &[
+        RomFile {
+            name: "7609h.bin",
+            address: 0x0000,
+            size: 0x400,
+            crc32: 0x0b117d73,
+            sha1: "99d01313e251818d336281700e206d9003c71dae",
+        },
+        RomFile {
+            name: "7609g.bin",
+            address: 0x0400,
+            size: 0x400,
+            crc32: 0x57bc3159,
+            sha1: "c177e3f72db9af17ab99b2481448ca26318184b9",
+        },
+        RomFile {
+            name: "7609f.bin",
+            address: 0x0800,
+            size: 0x400,
+            crc32: 0x8049a6bd,
+            sha1: "215b068663e431582591001cbe028929fa96d49f",
+        },
+        RomFile {
+            name: "7609e.bin",
+            address: 0x0C00,
+            size: 0x400,
+            crc32: 0x773264e2,
+            sha1: "de3f2e6841122bbe6e2fda5b87d37842c072289a",
+        },
+    ],
+    proms: &[],
+};
+
+/// Space Invaders Part II: the Space Invaders board with a fifth ROM at
+/// 0x4000 and two colour PROMs, one map per player.
+pub const INVADPT2: RomSet = RomSet {
+    name: "invadpt2",
+    description: "Space Invaders Part II (Taito)",
+    files: &[
+        RomFile::unverified("pv01", 0x0000, 0x800),
+        RomFile::unverified("pv02", 0x0800, 0x800),
+        RomFile::unverified("pv03", 0x1000, 0x800),
+        RomFile::unverified("pv04", 0x1800, 0x800),
+        RomFile::unverified("pv05", 0x4000, 0x800),
+    ],
+    proms: &[
+        RomFile::unverified("pv06.1", 0x000, 0x400),
+        RomFile::unverified("pv07.2", 0x400, 0x400),
+    ],
+};
+
+/// Midway's black-and-white release of Part II.
+pub const INVADDLX: RomSet = RomSet {
+    name: "invaddlx",
+    description: "Space Invaders Deluxe (Midway)",
+    files: &[
+        RomFile::unverified("invdelux.h", 0x0000, 0x800),
+        RomFile::unverified("invdelux.g", 0x0800, 0x800),
+        RomFile::unverified("invdelux.f", 0x1000, 0x800),
+        RomFile::unverified("invdelux.e", 0x1800, 0x800),
+        RomFile::unverified("invdelux.d", 0x4000, 0x800),
+    ],
+    proms: &[],
+};
+
+/// The original game on Taito's colour board.
+pub const SICV: RomSet = RomSet {
+    name: "sicv",
+    description: "Space Invaders (Taito, CV version)",
+    files: &[
+        RomFile::unverified("cv17.36", 0x0000, 0x800),
+        RomFile::unverified("cv18.35", 0x0800, 0x800),
+        RomFile::unverified("cv19.34", 0x1000, 0x800),
+        RomFile::unverified("cv20.33", 0x1800, 0x800),
+    ],
+    proms: &[
+        RomFile::unverified("cv01.1", 0x000, 0x400),
+        RomFile::unverified("cv02.2", 0x400, 0x400),
+    ],
+};
+
+/// Lunar Rescue: ROMs at 0x4000 and 0x4800, one colour PROM loaded as both
+/// maps.
+pub const LRESCUE: RomSet = RomSet {
+    name: "lrescue",
+    description: "Lunar Rescue (Taito)",
+    files: &[
+        RomFile::unverified("lrescue.1", 0x0000, 0x800),
+        RomFile::unverified("lrescue.2", 0x0800, 0x800),
+        RomFile::unverified("lrescue.3", 0x1000, 0x800),
+        RomFile::unverified("lrescue.4", 0x1800, 0x800),
+        RomFile::unverified("lrescue.5", 0x4000, 0x800),
+        RomFile::unverified("lrescue.6", 0x4800, 0x800),
+    ],
+    proms: &[
+        RomFile::unverified("7643-1.cpu", 0x000, 0x400),
+        RomFile::unverified("7643-1.cpu", 0x400, 0x400),
+    ],
+};
+
+pub const BALLBOMB: RomSet = RomSet {
+    name: "ballbomb",
+    description: "Balloon Bomber (Taito)",
+    files: &[
+        RomFile::unverified("tn01", 0x0000, 0x800),
+        RomFile::unverified("tn02", 0x0800, 0x800),
+        RomFile::unverified("tn03", 0x1000, 0x800),
+        RomFile::unverified("tn04", 0x1800, 0x800),
+        RomFile::unverified("tn05-1", 0x4000, 0x800),
+    ],
+    proms: &[
+        RomFile::unverified("tn06", 0x000, 0x400),
+        RomFile::unverified("tn07", 0x400, 0x400),
+    ],
+};
+
+/// Every set [`find`] knows. Checksums come from verified dumps only: a set
+/// is listed with [`RomFile::unverified`] files until one has been checked,
+/// and [`identify`] skips it until then.
+pub const KNOWN_SETS: &[RomSet] = &[
+    INVADERS, GUNFIGHT, INVADPT2, INVADDLX, SICV, LRESCUE, BALLBOMB,
+];
+
+#[derive(Debug)]
+pub enum RomError {
+    Missing {
+        set: &'static str,
+        path: PathBuf,
+    },
+    Io {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Zip {
+        path: PathBuf,
+        error: ZipError,
+    },
+    /// No directory of the ROM path has the set at all.
+    NotFound {
+        set: &'static str,
+        searched: Vec<PathBuf>,
+    },
+    WrongSize {
+        set: &'static str,
+        path: PathBuf,
+        expected: usize,
+        actual: usize,
+    },
+    BadChecksum {
+        set: &'static str,
+        path: PathBuf,
+        expected: u32,
+        actual: u32,
+    },
+    /// No known set has a file or image with these checksums.
+    Unknown {
+        size: usize,
+        crc32: u32,
+        sha1: String,
+    },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Missing { set, path } => {
+                write!(f, "{}: missing {}", set, path.display())
+            }
+            RomError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            RomError::Zip { path, error } => write!(f, "{}: {}", path.display(), error),
+            RomError::NotFound { set, searched } => {
+                let searched: Vec<String> = searched
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect();
+                write!(f, "{}: not found in {}", set, searched.join(", "))
+            }
+            RomError::WrongSize {
+                set,
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: {} is {} bytes, expected {}",
+                set,
+                path.display(),
+                actual,
+                expected
+            ),
+            RomError::BadChecksum {
+                set,
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: {} has CRC32 {:08x}, expected {:08x} (bad dump?)",
+                set,
+                path.display(),
+                actual,
+                expected
+            ),
+            RomError::Unknown { size, crc32, sha1 } => write!(
+                f,
+                "unknown ROM: {} bytes, CRC32 {:08x}, SHA1 {}",
+                size, crc32, sha1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RomError::Io { error, .. } => Some(error),
+            RomError::Zip { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+/// SHA1 as lowercase hex.
+pub fn sha1(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
+
+/// Bytes from 0 to the end of the last of `files`.
+fn region_size(files: &[RomFile]) -> usize {
+    files
+        .iter()
+        .map(|file| file.address + file.size)
+        .max()
+        .unwrap_or(0)
+}
+
+impl RomSet {
+    /// Size of [`RomImage::program`] once loaded.
+    pub fn size(&self) -> usize {
+        region_size(self.files)
+    }
+
+    /// Whether every file has recorded checksums.
+    pub fn verified(&self) -> bool {
+        self.all_files().all(RomFile::verified)
+    }
+
+    /// Program ROMs, then colour PROMs.
+    pub fn all_files(&self) -> impl Iterator<Item = &'static RomFile> {
+        self.files.iter().chain(self.proms)
+    }
+
+    /// Reads and verifies every file of the set from `dir`.
+    pub fn load<P: AsRef<Path>>(&self, dir: P) -> Result<RomImage, RomError> {
+        let mut image = RomImage::empty(self);
+        for file in self.all_files() {
+            let path = dir.as_ref().join(file.name);
+            let data = match fs::read(&path) {
+                Ok(data) => data,
+                Err(error) if error.kind() == ErrorKind::NotFound => {
+                    return Err(RomError::Missing {
+                        set: self.name,
+                        path,
+                    })
+                }
+                Err(error) => return Err(RomError::Io { path, error }),
+            };
+            self.verify(file, &path, &data)?;
+            image.place(self, file, &data);
+        }
+        Ok(image)
+    }
+
+    /// Reads and verifies every file of the set from a zip archive. `path`
+    /// only names the archive in errors.
+    pub fn load_zip<R: Read + Seek>(&self, reader: R, path: &Path) -> Result<RomImage, RomError> {
+        let zip_error = |error| RomError::Zip {
+            path: path.to_path_buf(),
+            error,
+        };
+        let mut archive = ZipArchive::new(reader).map_err(zip_error)?;
+        let mut image = RomImage::empty(self);
+        for file in self.all_files() {
+            let entry_path = path.join(file.name);
+            let Some(index) = find_entry(&mut archive, file) else {
+                return Err(RomError::Missing {
+                    set: self.name,
+                    path: entry_path,
+                });
+            };
+            let mut data = Vec::new();
+            archive
+                .by_index(index)
+                .map_err(zip_error)?
+                .read_to_end(&mut data)
+                .map_err(|error| RomError::Io {
+                    path: entry_path.clone(),
+                    error,
+                })?;
+            self.verify(file, &entry_path, &data)?;
+            image.place(self, file, &data);
+        }
+        Ok(image)
+    }
+
+    /// [`RomSet::load_zip`] for an archive already in memory.
+    pub fn load_zip_bytes(&self, bytes: &[u8]) -> Result<RomImage, RomError> {
+        self.load_zip(Cursor::new(bytes), Path::new(&format!("{}.zip", self.name)))
+    }
+
+    /// Searches each directory of `rom_path` in turn for `<set>.zip`, a
+    /// `<set>/` directory or the loose files. The first copy found is used,
+    /// even if it turns out to be bad.
+    pub fn locate<P: AsRef<Path>>(&self, rom_path: &[P]) -> Result<RomImage, RomError> {
+        for dir in rom_path {
+            let dir = dir.as_ref();
+            let zip_path = dir.join(format!("{}.zip", self.name));
+            match fs::File::open(&zip_path) {
+                Ok(zip) => return self.load_zip(io::BufReader::new(zip), &zip_path),
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                Err(error) => {
+                    return Err(RomError::Io {
+                        path: zip_path,
+                        error,
+                    })
+                }
+            }
+            for dir in [dir.join(self.name), dir.to_path_buf()] {
+                if dir.join(self.files[0].name).is_file() {
+                    return self.load(dir);
+                }
+            }
+        }
+        Err(RomError::NotFound {
+            set: self.name,
+            searched: rom_path
+                .iter()
+                .map(|dir| dir.as_ref().to_path_buf())
+                .collect(),
+        })
+    }
+
+    /// Checks a combined program image, e.g. the single-file `invaders`
+    /// dump, reporting the first part that does not match.
+    pub fn verify_image(&self, image: &[u8]) -> Result<(), RomError> {
+        if image.len() != self.size() {
+            return Err(RomError::WrongSize {
+                set: self.name,
+                path: PathBuf::from(self.name),
+                expected: self.size(),
+                actual: image.len(),
+            });
+        }
+        for file in self.files {
+            let data = &image[file.address..file.address + file.size];
+            self.verify(file, Path::new(file.name), data)?;
+        }
+        Ok(())
+    }
+
+    fn verify(&self, file: &RomFile, path: &Path, data: &[u8]) -> Result<(), RomError> {
+        if data.len() != file.size {
+            return Err(RomError::WrongSize {
+                set: self.name,
+                path: path.to_path_buf(),
+                expected: file.size,
+                actual: data.len(),
+            });
+        }
+        if !file.verified() {
+            return Ok(());
+        }
+        let actual = crc32(data);
+        if actual != file.crc32 || sha1(data) != file.sha1 {
+            return Err(RomError::BadChecksum {
+                set: self.name,
+                path: path.to_path_buf(),
+                expected: file.crc32,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Index of the entry holding `file`: by name, ignoring case and any
+/// directory, else by size and CRC32 if that is known.
+fn find_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, file: &RomFile) -> Option<usize> {
+    let mut by_crc = None;
+    for index in 0..archive.len() {
+        let Ok(entry) = archive.by_index_raw(index) else {
+            continue;
+        };
+        let name = entry.name().rsplit('/').next().unwrap_or_default();
+        if name.eq_ignore_ascii_case(file.name) {
+            return Some(index);
+        }
+        if by_crc.is_none()
+            && file.verified()
+            && entry.crc32() == file.crc32
+            && entry.size() == file.size as u64
+        {
+            by_crc = Some(index);
+        }
+    }
+    by_crc
+}
+
+/// Splits a ROM path such as `roms:/opt/mame/roms` into directories.
+pub fn split_rom_path(rom_path: &str) -> Vec<PathBuf> {
+    std::env::split_paths(rom_path).collect()
+}
+
+pub fn find(name: &str) -> Option<&'static RomSet> {
+    KNOWN_SETS.iter().find(|set| set.name == name)
+}
+
+/// Names the set `data` belongs to, either one of its files or a combined
+/// image of the whole set. Returns the file's name too when it is a single
+/// file.
+pub fn identify(data: &[u8]) -> Result<(&'static RomSet, Option<&'static RomFile>), RomError> {
+    for set in KNOWN_SETS.iter().filter(|set| set.verified()) {
+        if let Some(file) = set.all_files().find(|file| file.matches(data)) {
+            return Ok((set, Some(file)));
+        }
+        if set.verify_image(data).is_ok() {
+            return Ok((set, None));
+        }
+    }
+    Err(RomError::Unknown {
+        size: data.len(),
+        crc32: crc32(data),
+        sha1: sha1(data),
+    })
+}
@@ -0,0 +1,276 @@
+//! Per-instruction execution traces and a tool to compare them.
+//!
+//! Each line holds the state before the instruction runs, in the layout most
+//! 8080 reference emulators print:
+//!
+//! ```text
+//! PC: 0100, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 0    (31 00 07 CD)  LXI SP,$0700
+//! ```
+//!
+//! A tab separates the cycle count from the opcode bytes, and `AF` has bit 1
+//! set as the real PSW does. The disassembly after the opcode bytes is
+//! informational and ignored when diffing.
+
+use super::data_types::State8080;
+use crate::disassembler::{decode, FormatOptions};
+use crate::symbols::SymbolTable;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+const TRACE_BYTES: u16 = 4;
+const REGISTER_FIELDS: [&str; 7] = ["PC", "AF", "BC", "DE", "HL", "SP", "CYC"];
+
+/// Writes a trace line for every instruction `emulate_8080_op` executes.
+/// Attach one with `state.tracer = Some(Box::new(tracer))`.
+pub struct Tracer {
+    writer: Box<dyn Write + Send>,
+    /// Only instructions whose PC is in this range are logged.
+    range: Option<RangeInclusive<u16>>,
+    /// Logging starts the first time PC reaches this address.
+    trigger: Option<u16>,
+    /// Stop logging after this many lines.
+    limit: Option<u64>,
+    symbols: SymbolTable,
+    triggered: bool,
+    lines: u64,
+    /// Total cycles executed since the tracer was attached.
+    cycles: u64,
+    error: Option<io::Error>,
+}
+
+impl Tracer {
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Tracer {
+            writer: Box::new(writer),
+            range: None,
+            trigger: None,
+            limit: None,
+            symbols: SymbolTable::new(),
+            triggered: true,
+            lines: 0,
+            cycles: 0,
+            error: None,
+        }
+    }
+
+    pub fn to_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn with_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn with_trigger(mut self, addr: u16) -> Self {
+        self.trigger = Some(addr);
+        self.triggered = false;
+        self
+    }
+
+    pub fn with_limit(mut self, lines: u64) -> Self {
+        self.limit = Some(lines);
+        self
+    }
+
+    /// Names shown in the disassembly column.
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Flushes the output, reporting the first write error if there was one.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.writer.flush()
+    }
+
+    /// Logs the instruction at `state.pc`, which is about to execute.
+    pub fn record(&mut self, state: &State8080) {
+        if !self.triggered {
+            if Some(state.pc) != self.trigger {
+                return;
+            }
+            self.triggered = true;
+        }
+        if self.error.is_some()
+            || self.limit.is_some_and(|limit| self.lines >= limit)
+            || self
+                .range
+                .as_ref()
+                .is_some_and(|range| !range.contains(&state.pc))
+        {
+            return;
+        }
+
+        let line = format_line(state, self.cycles, &self.symbols);
+        if let Err(error) = writeln!(self.writer, "{}", line) {
+            self.error = Some(error);
+        }
+        self.lines += 1;
+    }
+
+    /// Adds the cycles taken by the instruction just executed.
+    pub fn add_cycles(&mut self, cycles: u8) {
+        self.cycles += cycles as u64;
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Formats one trace line for the instruction at `state.pc`.
+pub fn format_line(state: &State8080, cycles: u64, symbols: &SymbolTable) -> String {
+    let af = u16::from_be_bytes([state.a, state.get_flags_as_byte() | 0x02]);
+    let pair = |high: u8, low: u8| u16::from_be_bytes([high, low]);
+    let bytes: Vec<String> = (0..TRACE_BYTES)
+        .map(|i| format!("{:02X}", state.memory[state.pc.wrapping_add(i) as usize]))
+        .collect();
+    let instruction = decode(&state.memory, state.pc);
+    let text = instruction.format_with(&FormatOptions::default(), |addr| {
+        symbols.name_of(addr).map(str::to_string)
+    });
+    format!(
+        "PC: {:04X}, AF: {:04X}, BC: {:04X}, DE: {:04X}, HL: {:04X}, SP: {:04X}, CYC: {}\t({})  {}",
+        state.pc,
+        af,
+        pair(state.b, state.c),
+        pair(state.d, state.e),
+        pair(state.h, state.l),
+        state.sp,
+        cycles,
+        bytes.join(" "),
+        text
+    )
+}
+
+/// Where two traces first disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// 1-based line number.
+    pub line: usize,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    /// Names of the fields that differ, e.g. `["AF", "HL"]`.
+    pub fields: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Traces diverge at line {}", self.line)?;
+        if !self.fields.is_empty() {
+            writeln!(f, "Differing: {}", self.fields.join(", "))?;
+        }
+        writeln!(
+            f,
+            "expected: {}",
+            self.expected.as_deref().unwrap_or("<end of trace>")
+        )?;
+        write!(
+            f,
+            "actual:   {}",
+            self.actual.as_deref().unwrap_or("<end of trace>")
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Skip the `CYC` field, for references that count cycles differently.
+    pub ignore_cycles: bool,
+    /// Compare the opcode bytes as well as the registers.
+    pub compare_bytes: bool,
+}
+
+/// Compares two traces line by line and returns the first difference.
+/// Lines are compared by their register fields, so extra columns after the
+/// opcode bytes (such as disassembly) never cause a mismatch.
+pub fn diff<A: BufRead, B: BufRead>(
+    expected: A,
+    actual: B,
+    options: DiffOptions,
+) -> io::Result<Option<Divergence>> {
+    let mut expected = expected.lines();
+    let mut actual = actual.lines();
+    let mut line = 0;
+    loop {
+        line += 1;
+        let (left, right) = match (expected.next().transpose()?, actual.next().transpose()?) {
+            (None, None) => return Ok(None),
+            (left, right) => (left, right),
+        };
+        let fields = match (&left, &right) {
+            (Some(left), Some(right)) => differing_fields(left, right, options),
+            _ => Vec::new(),
+        };
+        if left.is_none() || right.is_none() || !fields.is_empty() {
+            return Ok(Some(Divergence {
+                line,
+                expected: left,
+                actual: right,
+                fields,
+            }));
+        }
+    }
+}
+
+fn differing_fields(left: &str, right: &str, options: DiffOptions) -> Vec<String> {
+    let (left_fields, left_bytes) = parse_fields(left);
+    let (right_fields, right_bytes) = parse_fields(right);
+    let mut fields: Vec<String> = REGISTER_FIELDS
+        .iter()
+        .filter(|&&name| !(options.ignore_cycles && name == "CYC"))
+        .filter(|&&name| field(&left_fields, name) != field(&right_fields, name))
+        .map(|name| name.to_string())
+        .collect();
+    if options.compare_bytes && left_bytes != right_bytes {
+        fields.push("bytes".to_string());
+    }
+    fields
+}
+
+/// Splits `PC: 0100, AF: 0002, ... (31 00 07 CD) ...` into its named fields
+/// (upper-cased) and the opcode bytes.
+fn parse_fields(line: &str) -> (Vec<(String, String)>, Option<String>) {
+    let (registers, rest) = match line.find('(') {
+        Some(open) => (&line[..open], Some(&line[open + 1..])),
+        None => (line, None),
+    };
+    let fields = registers
+        .split(',')
+        .filter_map(|field| field.split_once(':'))
+        .map(|(name, value)| {
+            (
+                name.trim().to_ascii_uppercase(),
+                value.trim().to_ascii_uppercase(),
+            )
+        })
+        .collect();
+    let bytes = rest
+        .and_then(|rest| rest.split_once(')'))
+        .map(|(bytes, _)| bytes.to_ascii_uppercase());
+    (fields, bytes)
+}
+
+fn field<'a>(fields: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    fields
+        .iter()
+        .find(|(field, _)| field == name)
+        .map(|(_, value)| value.as_str())
+}
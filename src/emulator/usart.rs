@@ -0,0 +1,164 @@
+#![cfg(not(feature = "wasm"))]
+//! The Intel 8251 USART, bridged to a [`Console`].
+//!
+//! It decodes two ports: data at `base` and control/status at `base + 1`.
+//! After a reset, the first control write is the mode instruction (followed
+//! by one or two sync characters in synchronous mode) and every later one a
+//! command instruction, until a command sets the internal reset bit.
+//!
+//! Characters go out to the console as soon as they are written, so the
+//! transmitter is always ready. One received character is held at a time;
+//! the next is taken from the console once it has been read.
+
+use super::console::Console;
+use super::io::Device;
+
+/// Status bits.
+pub const TX_READY: u8 = 0x01;
+pub const RX_READY: u8 = 0x02;
+pub const TX_EMPTY: u8 = 0x04;
+pub const DSR: u8 = 0x80;
+
+/// Command bits.
+pub const TX_ENABLE: u8 = 0x01;
+pub const RX_ENABLE: u8 = 0x04;
+pub const ERROR_RESET: u8 = 0x10;
+pub const INTERNAL_RESET: u8 = 0x40;
+
+/// Output pins, for [`PortMap::wire`](super::io::PortMap::wire).
+pub const TX_READY_PIN: usize = 0;
+pub const RX_READY_PIN: usize = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Expect {
+    Mode,
+    Sync(u8),
+    Command,
+}
+
+pub struct Usart8251<C> {
+    console: C,
+    expect: Expect,
+    mode: u8,
+    command: u8,
+    received: Option<u8>,
+    input_ended: bool,
+}
+
+impl<C: Console> Usart8251<C> {
+    pub fn new(console: C) -> Self {
+        Usart8251 {
+            console,
+            expect: Expect::Mode,
+            mode: 0,
+            command: 0,
+            received: None,
+            input_ended: false,
+        }
+    }
+
+    pub fn console(&self) -> &C {
+        &self.console
+    }
+
+    pub fn console_mut(&mut self) -> &mut C {
+        &mut self.console
+    }
+
+    /// The last mode instruction: baud factor, character length, parity
+    /// and stop bits, none of which the console needs.
+    pub fn mode(&self) -> u8 {
+        self.mode
+    }
+
+    pub fn command(&self) -> u8 {
+        self.command
+    }
+
+    /// Takes the next character from the console if the receiver is on and
+    /// has room.
+    fn receive(&mut self) {
+        if self.command & RX_ENABLE == 0 || self.received.is_some() {
+            return;
+        }
+        if self.console.ready() {
+            self.received = self.console.read();
+        }
+    }
+
+    fn status(&mut self) -> u8 {
+        self.receive();
+        if self.received.is_none() && self.console.at_end() {
+            self.input_ended = true;
+        }
+        let mut status = TX_READY | TX_EMPTY | DSR;
+        if self.received.is_some() {
+            status |= RX_READY;
+        }
+        status
+    }
+
+    fn control(&mut self, value: u8) {
+        match self.expect {
+            Expect::Mode => {
+                self.mode = value;
+                // A baud factor of zero selects synchronous mode, with one
+                // sync character or two
+                self.expect = match value & 0x03 {
+                    0 if value & 0x80 != 0 => Expect::Sync(1),
+                    0 => Expect::Sync(2),
+                    _ => Expect::Command,
+                };
+            }
+            Expect::Sync(1) => self.expect = Expect::Command,
+            Expect::Sync(count) => self.expect = Expect::Sync(count - 1),
+            Expect::Command if value & INTERNAL_RESET != 0 => {
+                self.command = 0;
+                self.expect = Expect::Mode;
+            }
+            Expect::Command => self.command = value,
+        }
+    }
+}
+
+impl<C: Console + Send + 'static> Device for Usart8251<C> {
+    fn ports(&self) -> u8 {
+        2
+    }
+
+    fn read(&mut self, offset: u8) -> u8 {
+        if offset == 0 {
+            self.receive();
+            self.received.take().unwrap_or(0)
+        } else {
+            self.status()
+        }
+    }
+
+    fn write(&mut self, offset: u8, value: u8) {
+        if offset == 0 {
+            if self.command & TX_ENABLE != 0 {
+                self.console.write(value);
+            }
+        } else {
+            self.control(value);
+        }
+    }
+
+    fn tick(&mut self, _cycles: u32) {
+        // Typed characters raise RxRDY without the program polling
+        self.receive();
+    }
+
+    fn pin(&self, pin: usize) -> bool {
+        match pin {
+            TX_READY_PIN => self.command & TX_ENABLE != 0,
+            RX_READY_PIN => self.received.is_some(),
+            _ => false,
+        }
+    }
+
+    fn end_of_input(&mut self) -> bool {
+        std::mem::take(&mut self.input_ended)
+    }
+}
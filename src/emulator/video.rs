@@ -0,0 +1,55 @@
+//! Turns video RAM into pixels, for every frontend.
+//!
+//! The Midway and Taito boards scan 224 lines of 256 pixels from 0x2400, 32
+//! bytes a line with the lowest bit first, on a monitor mounted on its side.
+//! [`render`] produces the upright 224x256 picture as RGBA. Colour boards
+//! give each 8x8 cell one of eight colours from a colour map.
+
+use std::ops::Range;
+
+pub const WIDTH: usize = 224;
+pub const HEIGHT: usize = 256;
+pub const VIDEO_RAM: Range<usize> = 0x2400..0x4000;
+
+/// Bytes in one colour map, one per cell. Only the first 0x380 are shown.
+pub const COLOUR_MAP_SIZE: usize = 0x400;
+
+/// The 3-bit palette of Taito's colour boards: bit 0 red, bit 1 blue, bit 2
+/// green.
+pub fn palette(index: u8) -> [u8; 3] {
+    let level = |bit: u8| if index >> bit & 1 != 0 { 0xFF } else { 0x00 };
+    [level(0), level(2), level(1)]
+}
+
+/// Colour of the lit pixels of the byte at `offset` into video RAM. Cells
+/// past the end of a short map are white.
+pub fn colour_at(colours: Option<&[u8]>, offset: usize) -> [u8; 3] {
+    match colours.and_then(|map| map.get((offset >> 8 << 5) | (offset & 0x1F))) {
+        Some(&colour) => palette(colour & 0x07),
+        None => [0xFF; 3],
+    }
+}
+
+/// Draws `video` upright into `rgba`, which holds `WIDTH * HEIGHT` pixels.
+/// Lit pixels are white, or the colour of their cell when a colour map is
+/// given; the rest are black.
+pub fn render(video: &[u8], colours: Option<&[u8]>, rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel.copy_from_slice(&[0, 0, 0, 0xFF]);
+    }
+    for (offset, &byte) in video.iter().enumerate() {
+        if byte == 0 {
+            continue;
+        }
+        let [r, g, b] = colour_at(colours, offset);
+        for bit in 0..8 {
+            if byte >> bit & 1 != 0 {
+                // Rotate 90° counter-clockwise into portrait orientation
+                let index = offset * 8 + bit;
+                let (x, y) = (index % HEIGHT, index / HEIGHT);
+                let start = ((HEIGHT - 1 - x) * WIDTH + y) * 4;
+                rgba[start..start + 4].copy_from_slice(&[r, g, b, 0xFF]);
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! C ABI for [`Env`], usable from Python through `ctypes` or `cffi`.
+//!
+//! Every function takes the handle returned by `si_env_new`. Observations
+//! are copied into caller-owned buffers of `width * height` bytes.
+
+use super::{Action, Env, EnvConfig};
+use std::panic;
+use std::ptr;
+
+/// Creates an environment, or returns null if the ROM could not be loaded.
+/// Pass a negative `sticky_action_probability` or zero for the other values
+/// to use the defaults.
+#[no_mangle]
+pub extern "C" fn si_env_new(
+    frame_skip: u32,
+    sticky_action_probability: f32,
+    downscale: u32,
+) -> *mut Env {
+    let defaults = EnvConfig::default();
+    let config = EnvConfig {
+        frame_skip: if frame_skip == 0 {
+            defaults.frame_skip
+        } else {
+            frame_skip
+        },
+        sticky_action_probability: if sticky_action_probability < 0.0 {
+            defaults.sticky_action_probability
+        } else {
+            sticky_action_probability
+        },
+        downscale: if downscale == 0 {
+            defaults.downscale
+        } else {
+            downscale as usize
+        },
+        ..defaults
+    };
+    // Loading the ROM panics on failure, which must not cross the ABI
+    match panic::catch_unwind(|| Env::new(config)) {
+        Ok(env) => Box::into_raw(Box::new(env)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `env` must come from `si_env_new` and not have been freed; null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn si_env_free(env: *mut Env) {
+    if !env.is_null() {
+        drop(Box::from_raw(env));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn si_env_action_count() -> u32 {
+    Action::COUNT as u32
+}
+
+/// # Safety
+/// `env` must be a live handle; `width` and `height` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn si_env_observation_shape(
+    env: *const Env,
+    width: *mut u32,
+    height: *mut u32,
+) {
+    let (w, h) = (*env).observation_shape();
+    *width = w as u32;
+    *height = h as u32;
+}
+
+/// Starts a new episode.
+///
+/// # Safety
+/// `env` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn si_env_reset(env: *mut Env, seed: u64) {
+    (*env).reset(seed);
+}
+
+/// Runs one step. Returns 1 when the episode is over, 0 when it is not and
+/// -1 for an unknown action. The points scored are written to `reward`
+/// unless it is null.
+///
+/// # Safety
+/// `env` must be a live handle; `reward` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn si_env_step(env: *mut Env, action: u32, reward: *mut i32) -> i32 {
+    let Some(action) = Action::from_index(action) else {
+        return -1;
+    };
+    let (_, points, done) = (*env).step(action);
+    if !reward.is_null() {
+        *reward = points;
+    }
+    done as i32
+}
+
+/// Copies the current observation into `buffer`, up to `len` bytes, and
+/// returns the full observation size.
+///
+/// # Safety
+/// `env` must be a live handle; `buffer` must be valid for `len` bytes of writes.
+#[no_mangle]
+pub unsafe extern "C" fn si_env_observation(env: *const Env, buffer: *mut u8, len: usize) -> usize {
+    let observation = (*env).observation();
+    let count = observation.len().min(len);
+    ptr::copy_nonoverlapping(observation.as_ptr(), buffer, count);
+    observation.len()
+}
@@ -0,0 +1,245 @@
+#![cfg(not(feature = "wasm"))]
+//! Gym-style reinforcement-learning environment for Space Invaders.
+//!
+//! An episode starts from a freshly reset machine with a coin inserted and
+//! player 1 started, followed by a seeded number of idle frames so episodes
+//! with different seeds do not all begin in the same state. Each step repeats
+//! the action for `frame_skip` frames, and the reward is the points scored
+//! meanwhile. The episode ends when the game returns to attract mode.
+
+pub mod ffi;
+
+use crate::emulator::input::{COIN, PLAYER1_FIRE, PLAYER1_LEFT, PLAYER1_RIGHT, PLAYER1_START};
+use crate::emulator::machine::SpaceInvadersMachine;
+
+const SCREEN_WIDTH: usize = 224;
+const SCREEN_HEIGHT: usize = 256;
+const VIDEO_RAM: usize = 0x2400;
+
+// Frames spent in attract mode before the game is started
+const BOOT_FRAMES: u32 = 60;
+const BUTTON_FRAMES: u32 = 5;
+const SETTLE_FRAMES: u32 = 30;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Action {
+    #[default]
+    Noop,
+    Fire,
+    Left,
+    Right,
+    LeftFire,
+    RightFire,
+}
+
+impl Action {
+    pub const COUNT: usize = 6;
+
+    pub fn from_index(index: u32) -> Option<Action> {
+        Some(match index {
+            0 => Action::Noop,
+            1 => Action::Fire,
+            2 => Action::Left,
+            3 => Action::Right,
+            4 => Action::LeftFire,
+            5 => Action::RightFire,
+            _ => return None,
+        })
+    }
+
+    /// Bits to put on input port 1 while the action is held.
+    pub fn port_bits(self) -> u8 {
+        match self {
+            Action::Noop => 0,
+            Action::Fire => PLAYER1_FIRE,
+            Action::Left => PLAYER1_LEFT,
+            Action::Right => PLAYER1_RIGHT,
+            Action::LeftFire => PLAYER1_LEFT | PLAYER1_FIRE,
+            Action::RightFire => PLAYER1_RIGHT | PLAYER1_FIRE,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvConfig {
+    /// Frames each action is held for.
+    pub frame_skip: u32,
+    /// Chance, per frame, that the previous action is repeated instead.
+    pub sticky_action_probability: f32,
+    /// Observations are shrunk by this factor in both directions.
+    pub downscale: usize,
+    /// Upper bound on the idle frames run after the game starts.
+    pub max_noop_frames: u32,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            frame_skip: 4,
+            sticky_action_probability: 0.25,
+            downscale: 2,
+            max_noop_frames: 30,
+        }
+    }
+}
+
+pub struct Env {
+    machine: SpaceInvadersMachine,
+    config: EnvConfig,
+    rng: SplitMix64,
+    last_action: Action,
+    score: u32,
+    done: bool,
+    observation: Vec<u8>,
+}
+
+impl Env {
+    pub fn new(config: EnvConfig) -> Self {
+        Self::with_machine(SpaceInvadersMachine::new(), config)
+    }
+
+    pub fn with_machine(machine: SpaceInvadersMachine, config: EnvConfig) -> Self {
+        let config = EnvConfig {
+            frame_skip: config.frame_skip.max(1),
+            downscale: config.downscale.max(1),
+            ..config
+        };
+        let (width, height) = observation_shape(config.downscale);
+        Env {
+            machine,
+            config,
+            rng: SplitMix64(0),
+            last_action: Action::Noop,
+            score: 0,
+            done: true,
+            observation: vec![0; width * height],
+        }
+    }
+
+    pub fn config(&self) -> &EnvConfig {
+        &self.config
+    }
+
+    /// Width and height of an observation in pixels, one byte per pixel.
+    pub fn observation_shape(&self) -> (usize, usize) {
+        observation_shape(self.config.downscale)
+    }
+
+    pub fn machine(&self) -> &SpaceInvadersMachine {
+        &self.machine
+    }
+
+    /// Points scored so far this episode.
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    /// Ships left in reserve, not counting the one in play.
+    pub fn lives(&self) -> u8 {
+        self.machine.inspector().lives(1)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Starts a new one-player game and returns the first observation.
+    pub fn reset(&mut self, seed: u64) -> &[u8] {
+        self.rng = SplitMix64(seed);
+        self.machine.reset();
+        self.run_frames(0, BOOT_FRAMES);
+        self.run_frames(COIN, BUTTON_FRAMES);
+        self.run_frames(0, SETTLE_FRAMES);
+        self.run_frames(PLAYER1_START, BUTTON_FRAMES);
+        let noops = (self.rng.next() % (self.config.max_noop_frames as u64 + 1)) as u32;
+        self.run_frames(0, noops);
+
+        self.last_action = Action::Noop;
+        self.score = self.machine.inspector().score(1);
+        self.done = false;
+        self.render();
+        &self.observation
+    }
+
+    /// Holds `action` for `frame_skip` frames and returns the new
+    /// observation, the points scored and whether the game is over. Once
+    /// the game is over, stepping does nothing until the next `reset`.
+    pub fn step(&mut self, action: Action) -> (&[u8], i32, bool) {
+        if self.done {
+            return (&self.observation, 0, true);
+        }
+        for _ in 0..self.config.frame_skip {
+            if self.rng.next_f32() >= self.config.sticky_action_probability {
+                self.last_action = action;
+            }
+            self.run_frames(self.last_action.port_bits(), 1);
+            if !self.machine.inspector().in_game() {
+                self.done = true;
+                break;
+            }
+        }
+
+        let score = self.machine.inspector().score(1);
+        let reward = score as i32 - self.score as i32;
+        self.score = score;
+        self.render();
+        (&self.observation, reward, self.done)
+    }
+
+    pub fn observation(&self) -> &[u8] {
+        &self.observation
+    }
+
+    fn run_frames(&mut self, input: u8, frames: u32) {
+        self.machine.board_mut().set_pressed(1, input);
+        for _ in 0..frames {
+            self.machine.run_frame();
+        }
+    }
+
+    /// Draws the upright screen into `observation`, averaging each
+    /// `downscale` x `downscale` block into one grey level.
+    fn render(&mut self) {
+        let scale = self.config.downscale;
+        let (width, height) = observation_shape(scale);
+        let video = &self.machine.get_memory()[VIDEO_RAM..];
+        let mut lit = vec![0u16; width * height];
+        for x in 0..SCREEN_WIDTH {
+            for y in 0..SCREEN_HEIGHT {
+                // Video RAM holds columns bottom to top, 8 pixels per byte
+                let bit = SCREEN_HEIGHT - 1 - y;
+                if video[x * SCREEN_HEIGHT / 8 + bit / 8] & (1 << (bit % 8)) != 0 {
+                    lit[(y / scale) * width + x / scale] += 1;
+                }
+            }
+        }
+        let area = (scale * scale) as u32;
+        for (pixel, &count) in self.observation.iter_mut().zip(&lit) {
+            *pixel = (count as u32 * 255 / area) as u8;
+        }
+    }
+}
+
+fn observation_shape(downscale: usize) -> (usize, usize) {
+    (
+        SCREEN_WIDTH.div_ceil(downscale),
+        SCREEN_HEIGHT.div_ceil(downscale),
+    )
+}
+
+/// Small seedable generator for sticky actions and start-up jitter.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_f32(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
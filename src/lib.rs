@@ -1,7 +1,11 @@
+pub mod assembler;
+pub mod debugger;
 pub mod disassembler;
 pub mod emulator;
+pub mod env;
 pub mod space_invaders_wasm;
+pub mod symbols;
 pub mod utils;
 
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests;
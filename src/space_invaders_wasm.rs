@@ -1,7 +1,14 @@
 #![cfg(feature = "wasm")]
 
+use crate::emulator::cheat::{self, CheatEngine, RamSearch, SearchCondition};
 use crate::emulator::cpu;
 use crate::emulator::cpu::CPU;
+use crate::emulator::hiscore::Hiscore;
+use crate::emulator::inspector::GameInspector;
+use crate::emulator::midway::{self, BoardProfile, Colour, MidwayBoard};
+use crate::emulator::video;
+use crate::symbols::{SymbolFormat, SymbolTable};
+use std::any::Any;
 // use crate::emulator::machine;
 // use crate::emulator::machine::SpaceInvadersMachine;
 use std::time::Duration;
@@ -107,17 +114,25 @@ pub fn draw_screen(ptr: *const u8) {
 #[wasm_bindgen]
 pub struct SpaceInvadersMachine {
     cpu: CPU,
+    profile: &'static BoardProfile,
+    /// Colour PROMs from the last ROM zip, for `Colour::Prom` profiles.
+    proms: Vec<u8>,
     which_interrupt: u8,
     context: CanvasRenderingContext2d,
     image_data: ImageData,
+    symbols: SymbolTable,
+    hiscore: Option<Hiscore>,
+    cheats: CheatEngine,
+    search: Option<RamSearch>,
+    colour_map: Option<Vec<u8>>,
+    colour_ram: Option<usize>,
 }
 
 #[wasm_bindgen]
 impl SpaceInvadersMachine {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Result<SpaceInvadersMachine, JsValue> {
-        // Initialize CPU and interrupts
-        let cpu = CPU::new();
+        let profile = &midway::INVADERS;
         let which_interrupt = 1;
 
         // Access the canvas and context
@@ -140,12 +155,59 @@ impl SpaceInvadersMachine {
             (SCREEN_HEIGHT as f64).into(),
         )?;
 
-        Ok(SpaceInvadersMachine {
-            cpu,
+        let mut machine = SpaceInvadersMachine {
+            cpu: CPU::new(),
+            profile,
+            proms: Vec::new(),
             which_interrupt,
             context,
             image_data,
-        })
+            symbols: SymbolTable::new(),
+            hiscore: None,
+            cheats: CheatEngine::new(),
+            search: None,
+            colour_map: None,
+            colour_ram: None,
+        };
+        machine.install(profile);
+        Ok(machine)
+    }
+
+    /// Powers up an empty board for `profile`, with its high-score table
+    /// and cheats.
+    fn install(&mut self, profile: &'static BoardProfile) {
+        self.cpu = CPU::new();
+        self.cpu.state.io = Some(Box::new(MidwayBoard::new(profile)));
+        self.profile = profile;
+        self.proms.clear();
+        self.which_interrupt = 1;
+        self.hiscore = profile.hiscore.map(Hiscore::new);
+        self.cheats = CheatEngine::new();
+        self.cheats
+            .load(profile.cheats)
+            .expect("built-in cheats parse");
+        self.search = None;
+    }
+
+    /// Switches to the game of that name, e.g. `gunfight`, on a freshly
+    /// powered-up board. Its ROMs are loaded next with `load_rom_zip`.
+    #[wasm_bindgen]
+    pub fn set_game(&mut self, name: &str) -> Result<(), JsValue> {
+        let profile = midway::find(name).ok_or_else(|| {
+            let names: Vec<&str> = midway::PROFILES.iter().map(|p| p.name()).collect();
+            JsValue::from_str(&format!(
+                "Unknown game {}; known: {}",
+                name,
+                names.join(", ")
+            ))
+        })?;
+        self.install(profile);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn game_name(&self) -> String {
+        self.profile.name().to_string()
     }
 
     #[wasm_bindgen]
@@ -154,6 +216,50 @@ impl SpaceInvadersMachine {
         self.cpu.state.memory[offset..end].copy_from_slice(rom_data);
     }
 
+    /// Loads and verifies the current game's set from a MAME-style zip,
+    /// e.g. one the user dropped on the page. Errors name the missing or bad
+    /// file.
+    #[wasm_bindgen]
+    pub fn load_rom_zip(&mut self, zip_data: &[u8]) -> Result<(), JsValue> {
+        let rom = self
+            .profile
+            .rom
+            .load_zip_bytes(zip_data)
+            .map_err(|error| JsValue::from_str(&error.to_string()))?;
+        self.cpu.state.memory[..rom.program.len()].copy_from_slice(&rom.program);
+        self.proms = rom.proms;
+        Ok(())
+    }
+
+    /// Colours the picture from a colour PROM map, one byte per 8x8 cell as
+    /// on Taito's colour boards. An empty map goes back to black and white;
+    /// anything else shorter than `video::COLOUR_MAP_SIZE` is refused.
+    #[wasm_bindgen]
+    pub fn set_colour_map(&mut self, map: &[u8]) -> Result<(), JsValue> {
+        if !map.is_empty() && map.len() < video::COLOUR_MAP_SIZE {
+            return Err(JsValue::from_str(&format!(
+                "A colour map needs {} bytes, not {}",
+                video::COLOUR_MAP_SIZE,
+                map.len()
+            )));
+        }
+        self.colour_map = (!map.is_empty()).then(|| map.to_vec());
+        Ok(())
+    }
+
+    /// Colours the picture from the game's colour RAM at `address`, for
+    /// boards where the game writes cell colours itself. Takes precedence
+    /// over a colour map; `clear_colour_ram` turns it off.
+    #[wasm_bindgen]
+    pub fn set_colour_ram(&mut self, address: u16) {
+        self.colour_ram = Some(address as usize);
+    }
+
+    #[wasm_bindgen]
+    pub fn clear_colour_ram(&mut self) {
+        self.colour_ram = None;
+    }
+
     #[wasm_bindgen]
     pub fn get_memory(&self) -> *const u8 {
         self.cpu.state.memory.as_ptr()
@@ -179,10 +285,114 @@ impl SpaceInvadersMachine {
 
     #[wasm_bindgen]
     pub fn get_last_instructions(&self) -> JsValue {
-        let instructions = self.cpu.state.get_instructions_in_order();
+        let symbols = (!self.symbols.is_empty()).then_some(&self.symbols);
+        let instructions = self.cpu.state.history.instructions(symbols);
         serde_wasm_bindgen::to_value(&instructions).unwrap()
     }
 
+    /// Loads a symbol map (`ADDR NAME` lines, `.sym` or `.lst` text) used by
+    /// the instruction history.
+    #[wasm_bindgen]
+    pub fn load_symbols(&mut self, text: &str, listing: bool) {
+        let format = if listing {
+            SymbolFormat::Listing
+        } else {
+            SymbolFormat::Simple
+        };
+        self.symbols.merge_text(text, format);
+    }
+
+    /// Address of a symbol or hex number typed by the user.
+    #[wasm_bindgen]
+    pub fn resolve_address(&self, text: &str) -> Option<u16> {
+        self.symbols.resolve(text)
+    }
+
+    /// Scores, lives, aliens and UFO decoded from RAM, as a JSON string.
+    /// `null` for games the inspector does not know.
+    #[wasm_bindgen]
+    pub fn get_game_state_json(&self) -> String {
+        if !self.profile.inspector {
+            return "null".to_string();
+        }
+        let state = GameInspector::new(&self.cpu.state.memory).snapshot();
+        serde_json::to_string(&state).unwrap()
+    }
+
+    /// Queues a high-score table saved earlier by `get_hiscore`, e.g. from
+    /// localStorage. It is written into RAM once the game has set it up.
+    /// False if it does not fit, or the game keeps no table.
+    #[wasm_bindgen]
+    pub fn restore_hiscore(&mut self, bytes: &[u8]) -> bool {
+        self.hiscore
+            .as_mut()
+            .is_some_and(|hiscore| hiscore.set_saved(bytes))
+    }
+
+    /// The high-score table to persist, or `undefined` if the game has not
+    /// initialised it yet or keeps none.
+    #[wasm_bindgen]
+    pub fn get_hiscore(&self) -> Option<Vec<u8>> {
+        self.hiscore.as_ref()?.bytes(&self.cpu.state.memory)
+    }
+
+    /// Starts a RAM search over work RAM; returns the number of candidates.
+    #[wasm_bindgen]
+    pub fn search_start(&mut self) -> usize {
+        let search = RamSearch::new(&self.cpu.state.memory, cheat::WORK_RAM);
+        let count = search.candidates().len();
+        self.search = Some(search);
+        count
+    }
+
+    /// Narrows the running search, `kind` being one of eq, ne, gt, lt (which
+    /// compare against `value`), changed, unchanged, increased or decreased.
+    /// Returns the candidates left, or `undefined` for a bad kind or no search.
+    #[wasm_bindgen]
+    pub fn search_filter(&mut self, kind: &str, value: u8) -> Option<usize> {
+        let condition = SearchCondition::parse(kind, None)
+            .or_else(|| SearchCondition::with_value(kind, value))?;
+        let search = self.search.as_mut()?;
+        Some(search.filter(&self.cpu.state.memory, condition))
+    }
+
+    #[wasm_bindgen]
+    pub fn search_results(&self) -> Vec<u16> {
+        self.search
+            .as_ref()
+            .map_or_else(Vec::new, |search| search.candidates().to_vec())
+    }
+
+    /// Writes `value` to `addr` every frame until unfrozen.
+    #[wasm_bindgen]
+    pub fn freeze(&mut self, addr: u16, value: u8) {
+        self.cheats.freeze(addr, value);
+    }
+
+    #[wasm_bindgen]
+    pub fn unfreeze(&mut self, addr: u16) -> bool {
+        self.cheats.unfreeze(addr)
+    }
+
+    /// Adds the cheats in a cheat file; returns how many were read.
+    #[wasm_bindgen]
+    pub fn load_cheats(&mut self, text: &str) -> Result<usize, JsValue> {
+        self.cheats
+            .load(text)
+            .map_err(|error| JsValue::from_str(&error.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn set_cheat_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        self.cheats.set_enabled(name, enabled)
+    }
+
+    /// Named cheats with their codes and whether they are on, as JSON.
+    #[wasm_bindgen]
+    pub fn get_cheats_json(&self) -> String {
+        serde_json::to_string(self.cheats.cheats()).unwrap()
+    }
+
     // #[wasm_bindgen]
     // pub fn get_last_instructions(&self) -> js_sys::Array {}
 
@@ -226,6 +436,12 @@ impl SpaceInvadersMachine {
             let op_cycles = cpu::emulate_8080_op(&mut self.cpu.state) as i32;
             cycles += op_cycles;
         }
+        if let Some(hiscore) = &mut self.hiscore {
+            hiscore.update(&mut self.cpu.state.memory);
+        }
+        if self.cheats.is_active() {
+            self.cheats.apply(&mut self.cpu.state.memory);
+        }
         if self.cpu.state.int_enable {
             if self.which_interrupt == 1 {
                 cpu::generate_interrupt(&mut self.cpu.state, 1);
@@ -237,28 +453,39 @@ impl SpaceInvadersMachine {
         }
     }
 
-    pub fn get_frame_image_data(&self) -> Result<ImageData, JsValue> {
-        let mut pixels = vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT * 4];
-
-        for addr in VIDEO_MEM_START..VIDEO_MEM_END {
-            let byte = self.cpu.state.memory[addr];
-            if byte == 0 {
-                continue;
+    /// The colour map to draw with: colour RAM or a map set from the page
+    /// first, then whatever the game's profile says.
+    fn colours(&self) -> Option<&[u8]> {
+        let memory = &self.cpu.state.memory;
+        if let Some(start) = self.colour_ram {
+            return memory.get(start..start + video::COLOUR_MAP_SIZE);
+        }
+        if let Some(map) = &self.colour_map {
+            return Some(map);
+        }
+        match self.profile.colour {
+            Colour::Mono => None,
+            Colour::Prom { select } => {
+                let map = select.map_or(0, |(port, mask)| {
+                    usize::from(self.board().last_output(port) & mask != 0)
+                });
+                self.proms
+                    .get(map * video::COLOUR_MAP_SIZE..(map + 1) * video::COLOUR_MAP_SIZE)
             }
-
-            // Each byte represents 8 vertical pixels
-            for bit in 0..8 {
-                if (byte >> bit) & 1 != 0 {
-                    // Rotate the framebuffer 90° CCW into portrait orientation
-                    let pixel_index = (addr - VIDEO_MEM_START) * 8 + bit;
-                    let x = pixel_index % SCREEN_HEIGHT;
-                    let y = pixel_index / SCREEN_HEIGHT;
-
-                    let idx = ((SCREEN_HEIGHT - 1 - x) * SCREEN_WIDTH + y) * 4;
-                    pixels[idx..idx + 4].copy_from_slice(&[255, 255, 255, 255]);
-                }
+            Colour::Ram { address } => {
+                let start = address as usize;
+                memory.get(start..start + video::COLOUR_MAP_SIZE)
             }
         }
+    }
+
+    pub fn get_frame_image_data(&self) -> Result<ImageData, JsValue> {
+        let mut pixels = vec![0u8; SCREEN_WIDTH * SCREEN_HEIGHT * 4];
+        video::render(
+            &self.cpu.state.memory[VIDEO_MEM_START..VIDEO_MEM_END],
+            self.colours(),
+            &mut pixels,
+        );
 
         ImageData::new_with_u8_clamped_array_and_sh(
             wasm_bindgen::Clamped(&pixels),
@@ -267,13 +494,46 @@ impl SpaceInvadersMachine {
         )
     }
 
+    fn board(&self) -> &MidwayBoard {
+        let io: &dyn Any = self.cpu.state.io.as_deref().expect("machine has a board");
+        io.downcast_ref().expect("machine has a board")
+    }
+
+    fn board_mut(&mut self) -> &mut MidwayBoard {
+        let io: &mut dyn Any = self
+            .cpu
+            .state
+            .io
+            .as_deref_mut()
+            .expect("machine has a board");
+        io.downcast_mut().expect("machine has a board")
+    }
+
+    /// Presses the controls whose bits are set in `key`, as laid out in
+    /// input port 1 (see `emulator::input`).
     #[wasm_bindgen]
     pub fn handle_key_down(&mut self, key: u8) {
-        self.cpu.handle_key_down(key);
+        let board = self.board_mut();
+        board.set_pressed(1, board.pressed(1) | key);
     }
 
     #[wasm_bindgen]
     pub fn handle_key_up(&mut self, key: u8) {
-        self.cpu.handle_key_up(key);
+        let board = self.board_mut();
+        board.set_pressed(1, board.pressed(1) & !key);
+    }
+
+    /// Presses or releases a control by name, e.g. `P2 Fire`. Returns false
+    /// if the board has no such control.
+    #[wasm_bindgen]
+    pub fn set_input(&mut self, name: &str, pressed: bool) -> bool {
+        self.board_mut().set_input(name, pressed)
+    }
+
+    /// Sets a DIP switch by name, e.g. `Lives` to `5`. Returns false if
+    /// either name is unknown.
+    #[wasm_bindgen]
+    pub fn set_dip(&mut self, name: &str, setting: &str) -> bool {
+        self.board_mut().set_dip(name, setting)
     }
 }
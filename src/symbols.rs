@@ -0,0 +1,184 @@
+//! Symbol maps naming ROM routines and RAM variables.
+//!
+//! Understands plain `ADDR NAME` (or `NAME ADDR`) lines, `NAME EQU value` /
+//! `NAME = value` definitions as written by most assemblers' `.sym` output,
+//! and labels picked out of `.lst` listings.
+
+use rustc_hash::FxHashMap;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SymbolFormat {
+    /// `ADDR NAME`, `NAME ADDR`, `NAME EQU ADDR` or `NAME = ADDR` per line.
+    Simple,
+    /// An assembler listing: `ADDR  bytes  LABEL: source`.
+    Listing,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    names: BTreeMap<u16, String>,
+    addresses: FxHashMap<String, u16>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a symbol file, treating `.lst` files as listings.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let is_listing = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("lst"));
+        let format = if is_listing {
+            SymbolFormat::Listing
+        } else {
+            SymbolFormat::Simple
+        };
+        Ok(Self::parse(&text, format))
+    }
+
+    /// Parses symbol text, skipping lines that do not define a symbol.
+    pub fn parse(text: &str, format: SymbolFormat) -> Self {
+        let mut table = Self::new();
+        table.merge_text(text, format);
+        table
+    }
+
+    pub fn merge_text(&mut self, text: &str, format: SymbolFormat) {
+        for line in text.lines() {
+            let parsed = match format {
+                SymbolFormat::Simple => parse_definition(strip_comment(line)),
+                SymbolFormat::Listing => parse_listing_line(line),
+            };
+            if let Some((addr, name)) = parsed {
+                self.insert(addr, name);
+            }
+        }
+    }
+
+    /// Adds a symbol. The first name given to an address is the one shown
+    /// in disassembly; every name can still be looked up.
+    pub fn insert(&mut self, addr: u16, name: &str) {
+        self.names.entry(addr).or_insert_with(|| name.to_string());
+        self.addresses.insert(name.to_ascii_lowercase(), addr);
+    }
+
+    pub fn name_of(&self, addr: u16) -> Option<&str> {
+        self.names.get(&addr).map(String::as_str)
+    }
+
+    /// Looks a name up, ignoring case.
+    pub fn address_of(&self, name: &str) -> Option<u16> {
+        self.addresses.get(&name.to_ascii_lowercase()).copied()
+    }
+
+    /// Turns user input into an address: a hex number (`15D3`, `$15D3`,
+    /// `0x15D3`, `15D3H`), a symbol name, or `name+offset`.
+    pub fn resolve(&self, text: &str) -> Option<u16> {
+        let text = text.trim();
+        if let Some((base, offset)) = text.split_once('+') {
+            return Some(self.resolve(base)?.wrapping_add(parse_number(offset)?));
+        }
+        self.address_of(text).or_else(|| parse_number(text))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.names.iter().map(|(&addr, name)| (addr, name.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Parses a hex address in any of the usual spellings.
+pub fn parse_number(text: &str) -> Option<u16> {
+    let text = text.trim();
+    let digits = if let Some(rest) = text.strip_prefix('$') {
+        rest
+    } else if let Some(rest) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        rest
+    } else if let Some(rest) = text.strip_suffix(['H', 'h']) {
+        rest
+    } else {
+        text
+    };
+    if digits.is_empty() || digits.len() > 8 {
+        return None;
+    }
+    u32::from_str_radix(digits, 16)
+        .ok()
+        .filter(|&value| value <= 0xFFFF)
+        .map(|value| value as u16)
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split([';', '#']).next().unwrap_or("")
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.' || c == '@')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '@' | '$'))
+}
+
+fn parse_definition(line: &str) -> Option<(u16, &str)> {
+    let tokens: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == '=')
+        .filter(|token| !token.is_empty())
+        .collect();
+    match tokens.as_slice() {
+        // NAME EQU value, NAME: EQU value
+        [name, equ, value] if equ.eq_ignore_ascii_case("equ") => {
+            let name = name.trim_end_matches(':');
+            is_identifier(name).then_some(())?;
+            Some((parse_number(value)?, name))
+        }
+        [first, second] => {
+            let second = second.trim_end_matches(':');
+            let first_name = first.trim_end_matches(':');
+            // ADDR NAME is preferred when both would parse as hex ("ADD 1234" style
+            // names are rare, "15D3 BEEF" addresses are not)
+            match (parse_number(first), is_identifier(second)) {
+                (Some(addr), true) => Some((addr, second)),
+                _ if is_identifier(first_name) => Some((parse_number(second)?, first_name)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn parse_listing_line(line: &str) -> Option<(u16, &str)> {
+    let mut tokens = line.split_whitespace();
+    let addr = tokens.next()?;
+    if addr.len() != 4 {
+        return None;
+    }
+    let addr = u16::from_str_radix(addr, 16).ok()?;
+
+    // Listings show EQUs with the value in the address column
+    let source: Vec<&str> = strip_comment(line).split_whitespace().collect();
+    if let Some(pos) = source.iter().position(|t| t.eq_ignore_ascii_case("equ")) {
+        let name = source.get(pos.checked_sub(1)?)?.trim_end_matches(':');
+        let value = parse_number(source.get(pos + 1)?)?;
+        return is_identifier(name).then_some((value, name));
+    }
+
+    tokens
+        .find_map(|token| token.strip_suffix(':'))
+        .filter(|name| is_identifier(name))
+        .map(|name| (addr, name))
+}
@@ -0,0 +1,143 @@
+use crate::assembler::assemble;
+use crate::emulator::altair::{self, Altair, PanelLights, TapeError};
+use crate::emulator::console::ScriptConsole;
+use crate::emulator::cpm::Exit;
+
+// Prints 'H', then whatever the sense switches spell
+const HELLO: &[u8] = &[
+    0x3E, b'H', // MVI A,'H'
+    0xD3, 0x01, // OUT 1
+    0xDB, 0xFF, // IN 0FFH
+    0xD3, 0x01, // OUT 1
+    0x76, // HLT
+];
+
+#[test]
+fn test_front_panel_deposit_and_run() {
+    let mut altair = Altair::new(0x1000, ScriptConsole::new(""));
+    altair.set_switches(0x0000);
+    altair.examine();
+    altair.set_switches(HELLO[0] as u16);
+    altair.deposit();
+    for &byte in &HELLO[1..] {
+        altair.set_switches(byte as u16);
+        altair.deposit_next();
+    }
+    altair.set_switches(0x0004);
+    altair.examine();
+    assert_eq!(altair.lights().data, 0xDB);
+    assert!(altair.lights().wait);
+
+    altair.set_switches((b'I' as u16) << 8);
+    altair.reset();
+    assert_eq!(altair.run(), Exit::Halted { pc: 8 });
+    assert_eq!(altair.console().output_text(), "HI");
+    assert_eq!(
+        altair.lights(),
+        PanelLights {
+            address: 8,
+            data: 0x76,
+            inte: false,
+            hlta: true,
+            wait: true,
+        }
+    );
+}
+
+// Echoes the terminal through the 2SIO with the parity bit set
+const ECHO: &str = "
+        ORG     0
+LOOP:   IN      10H
+        RRC
+        JNC     LOOP
+        IN      11H
+        ORI     80H
+        OUT     11H
+        JMP     LOOP
+";
+
+#[test]
+fn test_2sio_echo_stops_when_input_ends() {
+    let mut altair = Altair::new(0x1000, ScriptConsole::new("print 1\r"));
+    altair.load(0, &assemble(ECHO).unwrap().to_bytes());
+    assert_eq!(altair.run_for(10_000), Some(Exit::EndOfInput));
+    assert_eq!(altair.console().output_text(), "print 1\r");
+    assert!(!altair.is_running());
+}
+
+// Walks up memory a page at a time until a byte does not hold what was
+// written, the way BASIC sizes memory
+const SIZE: &str = "
+        ORG     0
+        LXI     H,0100H
+LOOP:   MOV     A,M
+        CMA
+        MOV     M,A
+        CMP     M
+        JNZ     DONE
+        CMA
+        MOV     M,A
+        INR     H
+        JNZ     LOOP
+DONE:   LXI     SP,1001H
+        PUSH    H
+        HLT
+";
+
+#[test]
+fn test_memory_above_ram_is_absent() {
+    let mut altair = Altair::new(0x1000, ScriptConsole::new(""));
+    altair.load(0x0F00, &[0x55; 0x200]);
+    altair.load(0, &assemble(SIZE).unwrap().to_bytes());
+    assert!(matches!(altair.run(), Exit::Halted { .. }));
+
+    let state = altair.get_state();
+    assert_eq!(state.h, 0x10);
+    assert_eq!(state.memory[0x0F00], 0x55);
+    // PUSH H put H at 0x1000, which is not there, and L at 0x0FFF
+    assert_eq!(state.memory[0x0FFF], 0x00);
+    assert_eq!(state.memory[0x1000], 0xFF);
+    assert!(state.memory[0x1000..].iter().all(|&byte| byte == 0xFF));
+}
+
+fn record(address: u16, data: &[u8]) -> Vec<u8> {
+    let mut record = vec![0x3C, data.len() as u8];
+    record.extend(address.to_le_bytes());
+    record.extend(data);
+    let sum = record[2..]
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+    record.push(sum);
+    record
+}
+
+#[test]
+fn test_paper_tape_loads_and_starts() {
+    // Leader, then loader code that merely looks like a record
+    let mut tape = vec![0x00; 10];
+    tape.extend([0x3C, 0x02, 0x00, 0x00, 0x11, 0x22, 0x99]);
+    tape.extend(record(0x0100, &[0x3E, b'T', 0xD3]));
+    tape.extend([0x00; 3]);
+    tape.extend(record(0x0103, &[0x01, 0x76]));
+    tape.extend([0x78, 0x00, 0x01]);
+
+    let parsed = altair::parse_tape(&tape).unwrap();
+    assert_eq!(parsed.records.len(), 2);
+    assert_eq!(parsed.start, Some(0x0100));
+    let mut altair = Altair::new(0x1000, ScriptConsole::new(""));
+    altair.load_tape(&parsed);
+    assert_eq!(altair.run(), Exit::Halted { pc: 0x0104 });
+    assert_eq!(altair.console().output_text(), "T");
+
+    let mut bad = tape.clone();
+    bad[30] ^= 1;
+    assert_eq!(
+        altair::parse_tape(&bad),
+        Err(TapeError::Checksum { offset: 28 })
+    );
+    assert_eq!(
+        altair::parse_tape(&tape[..31]),
+        Err(TapeError::Truncated { offset: 28 })
+    );
+    assert_eq!(altair::parse_tape(&[0; 16]), Err(TapeError::NoRecords));
+}
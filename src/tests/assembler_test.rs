@@ -0,0 +1,165 @@
+use crate::assembler::{assemble, Assembler};
+use crate::disassembler::{
+    decode, DisassemblyProfile, FormatOptions, HexStyle, RecursiveDisassembler,
+};
+use crate::emulator::cpu::{emulate_8080_op, CPU};
+use crate::utils::read_bin_file;
+
+#[test]
+fn test_every_documented_opcode_round_trips() {
+    let options = FormatOptions {
+        hex: HexStyle::Suffix,
+        ..FormatOptions::default()
+    };
+    for opcode in 0..=255u8 {
+        let instruction = decode(&[opcode, 0x34, 0x12], 0);
+        if !instruction.documented {
+            continue;
+        }
+        let source = format!("        {}", instruction.format(&options));
+        let assembly = assemble(&source).unwrap_or_else(|e| panic!("{}: {}", source, e));
+        assert_eq!(assembly.to_bytes(), instruction.bytes(), "{}", source);
+    }
+}
+
+#[test]
+fn test_labels_expressions_and_directives() {
+    let source = "\
+; forward references, EQU and data
+COUNT   EQU     END_TABLE-TABLE
+        ORG     100H
+start:  LXI     H,TABLE
+        MVI     B,COUNT
+        MVI     C,LOW (TABLE+1)
+        MVI     D,HIGH TABLE
+        JMP     $+3
+        CPI     'a'
+        DS      2
+TABLE:  DB      'Hi',0,1 SHL 4,-1
+        DW      start,0BEEFH
+END_TABLE
+        END     start
+        DB      99
+";
+    let assembly = assemble(source).unwrap();
+    assert_eq!(assembly.origin(), 0x100);
+    assert_eq!(assembly.entry, Some(0x100));
+    assert_eq!(assembly.symbols.address_of("table"), Some(0x110));
+    assert_eq!(assembly.symbols.address_of("COUNT"), Some(9));
+    assert_eq!(assembly.segments().len(), 2);
+    assert_eq!(
+        assembly.to_bytes(),
+        [
+            0x21, 0x10, 0x01, 0x06, 0x09, 0x0E, 0x11, 0x16, 0x01, 0xC3, 0x0C, 0x01, 0xFE, 0x61,
+            0x00, 0x00, // DS 2
+            0x48, 0x69, 0x00, 0x10, 0xFF, 0x00, 0x01, 0xEF, 0xBE,
+        ]
+    );
+
+    let listing = assembly.listing();
+    assert!(listing.contains("0100    21 10 01    start:  LXI     H,TABLE"));
+    assert!(listing.contains("0009  =             COUNT   EQU     END_TABLE-TABLE"));
+}
+
+#[test]
+fn test_reports_errors_with_line_numbers() {
+    let error = assemble("        NOP\n        JMP nowhere\n").unwrap_err();
+    assert_eq!(error.line, 2);
+    assert!(error.message.contains("nowhere"));
+
+    assert_eq!(assemble("        FOO A\n").unwrap_err().line, 1);
+    assert_eq!(assemble("        MVI A,256\n").unwrap_err().line, 1);
+    assert_eq!(assemble("x: NOP\nX: NOP\n").unwrap_err().line, 2);
+    assert!(assemble("        MOV M,M\n").is_err());
+
+    // Only SET symbols can be set again
+    assert_eq!(assemble("N SET 1\nN EQU 2\n").unwrap_err().line, 2);
+    assert_eq!(assemble("N EQU 1\nN SET 2\n").unwrap_err().line, 2);
+    assert_eq!(assemble("N: NOP\nN SET 2\n").unwrap_err().line, 2);
+
+    // Words and code must fit in 16 bits
+    assert_eq!(
+        assemble("        DW 1\n        DW 10000H\n")
+            .unwrap_err()
+            .line,
+        2
+    );
+    assert!(assemble("        DW -1,0FFFFH\n").is_ok());
+    let top = "        ORG 0FFFEH\n        DW 1234H\n";
+    assert!(assemble(top).is_ok());
+    assert_eq!(
+        assemble(&format!("{}        NOP\n", top)).unwrap_err().line,
+        3
+    );
+
+    let mut assembler = Assembler::new();
+    assembler.define("DrawSprite", 0x15D3);
+    let patch = assembler.assemble("        CALL drawsprite\n").unwrap();
+    assert_eq!(patch.to_bytes(), [0xCD, 0xD3, 0x15]);
+}
+
+#[test]
+fn test_set_symbols_can_be_redefined() {
+    let source = "
+        MVI     A,N
+N       SET     1
+        MVI     B,N
+N       SET     N+1
+        MVI     C,N
+        MVI     D,N*2
+";
+    let assembly = assemble(source).unwrap();
+    assert_eq!(
+        assembly.to_bytes(),
+        [0x3E, 0x02, 0x06, 0x01, 0x0E, 0x02, 0x16, 0x04]
+    );
+    assert_eq!(assembly.symbols.address_of("N"), Some(2));
+    assert!(assembly
+        .listing()
+        .contains("0002  =             N       SET     N+1"));
+}
+
+#[test]
+fn test_reassembles_disassembled_rom() {
+    let mut memory = vec![0u8; 0x10000];
+    let mut offset = 0;
+    for file in ["h", "g", "f", "e"] {
+        let rom = read_bin_file(&format!("roms/space_invaders/invaders.{}", file));
+        memory[offset..offset + rom.len()].copy_from_slice(&rom);
+        offset += rom.len();
+    }
+
+    let profile = DisassemblyProfile::SPACE_INVADERS;
+    let listing = RecursiveDisassembler::from_profile(&memory, &profile).listing(profile.name);
+    let assembly = assemble(&listing).unwrap();
+    assert_eq!(assembly.to_bytes(), &memory[profile.rom]);
+}
+
+#[test]
+fn test_runs_assembled_program() {
+    // Sums 10 + 9 + ... + 1 into A
+    let program = assemble(
+        "
+        ORG     0
+        MVI     B,10
+        XRA     A
+loop:   ADD     B
+        DCR     B
+        JNZ     loop
+        STA     result
+done:   JMP     done
+result: DS      1
+",
+    )
+    .unwrap();
+
+    let mut cpu = CPU::new();
+    program.load_into(&mut cpu.state.memory);
+    let done = program.symbols.address_of("done").unwrap();
+    while cpu.state.pc != done {
+        emulate_8080_op(&mut cpu.state);
+    }
+    let result = program.symbols.address_of("result").unwrap();
+    assert_eq!(cpu.state.a, 55);
+    assert_eq!(cpu.state.memory[result as usize], 55);
+}
@@ -0,0 +1,35 @@
+use crate::emulator::batch::BatchRunner;
+use crate::emulator::input::COIN;
+use crate::emulator::machine::SpaceInvadersMachine;
+
+// Credit count in BCD, relative to the start of work RAM
+const CREDITS: usize = 0xEB;
+
+#[test]
+fn test_batch_matches_single_machine() {
+    let mut batch = BatchRunner::new(4).with_threads(3);
+    let mut single = SpaceInvadersMachine::new();
+    for _ in 0..60 {
+        batch.step(&[0; 4]);
+        single.run_frame();
+    }
+    for observation in batch.observations() {
+        assert_eq!(
+            observation.framebuffer,
+            &single.get_memory()[0x2400..0x4000]
+        );
+        assert_eq!(observation.ram, &single.get_memory()[0x2000..0x2400]);
+    }
+}
+
+#[test]
+fn test_batch_inputs_are_per_instance() {
+    let mut batch = BatchRunner::new(2).with_threads(2);
+    for frame in 0..120 {
+        let coin = if (60..65).contains(&frame) { COIN } else { 0 };
+        batch.step(&[coin, 0]);
+    }
+    let observations = batch.observations();
+    assert_eq!(observations[0].ram[CREDITS], 0x01);
+    assert_eq!(observations[1].ram[CREDITS], 0x00);
+}
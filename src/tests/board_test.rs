@@ -0,0 +1,239 @@
+use crate::assembler::assemble;
+use crate::emulator::board::Board;
+use crate::emulator::console::ScriptConsole;
+use crate::emulator::cpm::Exit;
+use crate::emulator::io::{Device, PortMap};
+use crate::emulator::pit::Pit8253;
+use crate::emulator::usart::{self, Usart8251};
+
+const USART: u8 = 0xEC;
+const PIT: u8 = 0xDC;
+
+fn board(source: &str, ports: PortMap) -> Board {
+    let mut board = Board::new(ports);
+    board.load(0, &assemble(source).unwrap().to_bytes());
+    board
+}
+
+fn usart_board(source: &str, input: &str) -> Board {
+    let mut ports = PortMap::new();
+    ports
+        .attach(USART, Usart8251::new(ScriptConsole::new(input)))
+        .wire(USART, usart::RX_READY_PIN, 7);
+    board(source, ports)
+}
+
+fn output(board: &Board) -> String {
+    let usart = board.ports().get::<Usart8251<ScriptConsole>>(USART);
+    usart.unwrap().console().output_text()
+}
+
+// Sets the USART up for 8N1 at x16, then echoes each character from the
+// RxRDY interrupt while the main program sits halted
+const INTERRUPT_ECHO: &str = "
+        ORG     0
+        LXI     SP,1000H
+        MVI     A,4EH
+        OUT     0EDH
+        MVI     A,15H
+        OUT     0EDH
+        EI
+IDLE:   HLT
+        JMP     IDLE
+
+        ORG     38H
+        PUSH    PSW
+        IN      0ECH
+        OUT     0ECH
+        POP     PSW
+        EI
+        RET
+";
+
+#[test]
+fn test_usart_echoes_from_receive_interrupt() {
+    let mut board = usart_board(INTERRUPT_ECHO, "hello\r");
+    assert_eq!(board.run_for(1000), None);
+    assert_eq!(output(&board), "hello\r");
+    let idle = assemble(INTERRUPT_ECHO).unwrap().symbols.address_of("IDLE");
+    assert_eq!(Some(board.get_state().pc), idle);
+
+    let usart = board
+        .ports()
+        .get::<Usart8251<ScriptConsole>>(USART)
+        .unwrap();
+    assert_eq!(usart.mode(), 0x4E);
+    assert_eq!(usart.command(), 0x15);
+}
+
+// Polls RxRDY, and echoes upper case. The transmitter is only enabled by
+// the second command, after an internal reset.
+const POLLED_ECHO: &str = "
+        ORG     0
+        MVI     A,4EH
+        OUT     0EDH
+        MVI     A,04H
+        OUT     0EDH
+        MVI     A,40H
+        OUT     0EDH
+        MVI     A,4EH
+        OUT     0EDH
+        MVI     A,05H
+        OUT     0EDH
+LOOP:   IN      0EDH
+        ANI     02H
+        JZ      LOOP
+        IN      0ECH
+        ANI     0DFH
+        OUT     0ECH
+        JMP     LOOP
+";
+
+#[test]
+fn test_usart_polling_stops_when_input_ends() {
+    let mut board = usart_board(POLLED_ECHO, "abc");
+    assert_eq!(board.run(), Exit::EndOfInput);
+    assert_eq!(output(&board), "ABC");
+}
+
+/// Clocks the timer one count at a time, collecting OUT of `counter`.
+fn outs(pit: &mut Pit8253, counter: usize, counts: usize) -> String {
+    (0..counts)
+        .map(|_| {
+            pit.tick(1);
+            if pit.out(counter) {
+                '1'
+            } else {
+                '0'
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_pit_modes() {
+    let mut pit = Pit8253::new(1, [1, 1, 1]);
+
+    // Mode 0, LSB then MSB: OUT rises after the count runs out
+    pit.write(3, 0x30);
+    pit.write(0, 4);
+    pit.write(0, 0);
+    assert!(!pit.out(0));
+    assert_eq!(outs(&mut pit, 0, 6), "000111");
+
+    // Mode 2, LSB only: one low count in every four
+    pit.write(3, 0x54);
+    pit.write(1, 4);
+    assert_eq!(outs(&mut pit, 1, 8), "11011101");
+
+    // Mode 3 with an odd count: high for three, low for two
+    pit.write(3, 0x96);
+    pit.write(2, 5);
+    assert_eq!(outs(&mut pit, 2, 10), "1100111001");
+}
+
+#[test]
+fn test_pit_gates_and_one_shots() {
+    let mut pit = Pit8253::new(1, [1, 1, 1]);
+
+    // Mode 1 waits for the gate to rise, then goes low for the count
+    pit.write(3, 0x12);
+    pit.write(0, 3);
+    assert_eq!(outs(&mut pit, 0, 2), "11");
+    pit.set_gate(0, false);
+    pit.set_gate(0, true);
+    assert_eq!(outs(&mut pit, 0, 4), "0011");
+
+    // Mode 4 strobes once, then keeps counting down through zero
+    pit.write(3, 0x58);
+    pit.write(1, 2);
+    assert_eq!(outs(&mut pit, 1, 5), "10111");
+    assert_eq!(pit.count(1), 0xFFFD);
+
+    // A low gate holds a mode 0 count
+    pit.write(3, 0x90);
+    pit.write(2, 3);
+    pit.set_gate(2, false);
+    assert_eq!(outs(&mut pit, 2, 5), "00000");
+    pit.set_gate(2, true);
+    assert_eq!(outs(&mut pit, 2, 3), "001");
+}
+
+#[test]
+fn test_pit_latch_and_bcd_reads() {
+    let mut pit = Pit8253::new(2_000_000, [1_000_000, 0, 0]);
+
+    // BCD 1000 counts down at half the CPU clock
+    pit.write(3, 0x31);
+    pit.write(0, 0x00);
+    pit.write(0, 0x10);
+    pit.tick(20);
+
+    // The latch holds 0990 however long the reads take
+    pit.write(3, 0x00);
+    pit.tick(100);
+    assert_eq!(pit.read(0), 0x90);
+    pit.tick(100);
+    assert_eq!(pit.read(0), 0x09);
+    assert_eq!(pit.read(0), 0x90);
+    assert_eq!(pit.read(0), 0x08);
+
+    // Counter 1 has no clock
+    pit.write(3, 0x50);
+    pit.write(1, 0x42);
+    pit.tick(1000);
+    assert_eq!(pit.read(1), 0x42);
+}
+
+// Counts timer interrupts at 0100H, halting between them, up to 5
+const TICKS: &str = "
+        ORG     0
+        LXI     SP,1000H
+        MVI     A,34H
+        OUT     0DFH
+        MVI     A,0E8H
+        OUT     0DCH
+        MVI     A,03H
+        OUT     0DCH
+        EI
+WAIT:   HLT
+        LDA     100H
+        CPI     5
+        JNZ     WAIT
+        DI
+DONE:   HLT
+
+        ORG     30H
+        PUSH    PSW
+        LDA     100H
+        INR     A
+        STA     100H
+        POP     PSW
+        EI
+        RET
+";
+
+#[test]
+fn test_pit_interrupts_halted_cpu() {
+    let mut ports = PortMap::new();
+    ports
+        .attach(PIT, Pit8253::new(2_000_000, [2_000_000, 0, 0]))
+        .wire(PIT, 0, 6);
+    let mut board = board(TICKS, ports);
+    let done = assemble(TICKS).unwrap().symbols.address_of("DONE");
+
+    assert_eq!(board.run(), Exit::Halted { pc: done.unwrap() });
+    assert_eq!(board.get_state().memory[0x100], 5);
+}
+
+#[test]
+#[should_panic(expected = "needs a CPU clock")]
+fn test_pit_refuses_zero_cpu_clock() {
+    Pit8253::new(0, [1_000_000, 0, 0]);
+}
+
+#[test]
+#[should_panic(expected = "no device at that port")]
+fn test_wiring_an_empty_port_panics() {
+    PortMap::new().wire(PIT, 0, 6);
+}
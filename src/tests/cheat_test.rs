@@ -0,0 +1,67 @@
+use crate::emulator::cheat::{self, CheatEngine, RamSearch, SearchCondition, WORK_RAM};
+use crate::emulator::machine::SpaceInvadersMachine;
+
+#[test]
+fn test_parse_cheat_file() {
+    let cheats = cheat::parse(cheat::SPACE_INVADERS).unwrap();
+    let names: Vec<&str> = cheats.iter().map(|cheat| cheat.name.as_str()).collect();
+    assert_eq!(names, ["Infinite lives", "Invincibility"]);
+    assert_eq!(cheats[0].codes, [(0x21FF, 0x03), (0x22FF, 0x03)]);
+    assert!(cheats.iter().all(|cheat| !cheat.enabled));
+
+    let error = cheat::parse("[Lives]\n21FF=03\n21FF\n").unwrap_err();
+    assert_eq!(error.line, 3);
+    let error = cheat::parse("; comment\n2015=FF").unwrap_err();
+    assert_eq!(error.to_string(), "line 2: code before the first [name]");
+    assert!(cheat::parse("[Bad]\n2015=100").is_err());
+
+    let mut engine = CheatEngine::new();
+    engine.load("[Lives]\n21FF=05").unwrap();
+    engine.load("[Lives]\n21FF=09\n[Other]\n2000=01").unwrap();
+    assert_eq!(engine.cheats().len(), 2);
+    assert_eq!(engine.cheats()[0].codes, [(0x21FF, 0x09)]);
+    assert!(engine.set_enabled("lives", true));
+    assert!(!engine.set_enabled("missing", true));
+}
+
+#[test]
+fn test_ram_search_narrows_candidates() {
+    let mut memory = vec![0u8; 0x10000];
+    memory[0x2010] = 3;
+    memory[0x2020] = 3;
+    let mut search = RamSearch::new(&memory, WORK_RAM);
+    assert_eq!(search.candidates().len(), 0x400);
+
+    assert_eq!(search.filter(&memory, SearchCondition::Equal(3)), 2);
+    memory[0x2010] = 2;
+    assert_eq!(search.filter(&memory, SearchCondition::Decreased), 1);
+    assert_eq!(search.candidates(), [0x2010]);
+    assert_eq!(search.previous(0x2010), Some(2));
+    assert_eq!(search.filter(&memory, SearchCondition::Unchanged), 1);
+    assert_eq!(search.filter(&memory, SearchCondition::Changed), 0);
+
+    assert_eq!(
+        SearchCondition::parse("gt", Some("1F")),
+        Some(SearchCondition::Greater(0x1F))
+    );
+    assert_eq!(SearchCondition::parse("eq", None), None);
+}
+
+#[test]
+fn test_machine_applies_cheats_every_frame() {
+    let mut machine = SpaceInvadersMachine::new();
+    assert!(machine.cheats_mut().set_enabled("Infinite lives", true));
+    machine.cheats_mut().freeze(0x2015, 0xFF);
+    machine.get_state_mut().memory[0x21FF] = 0;
+    machine.get_state_mut().memory[0x2015] = 0;
+    machine.run_frame();
+    assert_eq!(machine.inspector().lives(1), 3);
+    assert!(machine.inspector().player_alive());
+
+    machine.cheats_mut().set_enabled("Infinite lives", false);
+    assert!(machine.cheats_mut().unfreeze(0x2015));
+    machine.get_state_mut().memory[0x21FF] = 0;
+    machine.run_frame();
+    assert_eq!(machine.inspector().lives(1), 0);
+    assert!(!machine.cheats().is_active());
+}
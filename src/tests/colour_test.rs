@@ -0,0 +1,221 @@
+use crate::debugger::DebugTarget;
+use crate::disassembler::DisassemblyProfile;
+use crate::emulator::machine::SpaceInvadersMachine;
+use crate::emulator::midway::{self, BoardProfile, Colour, ShiftPorts};
+use crate::emulator::romset::{self, RomFile, RomSet};
+use crate::emulator::video::{self, HEIGHT, VIDEO_RAM, WIDTH};
+use std::fs;
+
+// A test board shaped like Space Invaders Part II: code at 0x4000 beyond the
+// usual 8K, and two colour maps switched by bit 5 of port 5.
+const BOOT: &[u8] = &[0xC3, 0x00, 0x40]; // JMP 4000H
+const MAIN: &[u8] = &[
+    0x21, 0x00, 0x24, // LXI H,2400H
+    0x36, 0xFF, // MVI M,0FFH
+    0x3E, 0x20, // MVI A,20H
+    0xD3, 0x05, // OUT 5
+    0xC3, 0x09, 0x40, // JMP 4009H
+];
+
+const COLOUR_SET: RomSet = RomSet {
+    name: "colourtest",
+    description: "Colour board test",
+    files: &[
+        RomFile {
+            name: "boot.bin",
+            address: 0x0000,
+            size: 0x400,
+            crc32: 0x7d3cbcd4,
+            sha1: "ccd42e3febe5b692b76fae668dece738434a4a4b",
+        },
+        RomFile {
+            name: "main.bin",
+            address: 0x4000,
+            size: 0x400,
+            crc32: 0x2747fd77,
+            sha1: "58d64c5a95a4712b5aee5f2e7aa98e06ceea0166",
+        },
+    ],
+    proms: &[
+        RomFile {
+            name: "red.prom",
+            address: 0x000,
+            size: 0x400,
+            crc32: 0xfa82241d,
+            sha1: "376f19001dc171e2eb9c56962ca32478caaa7e39",
+        },
+        RomFile {
+            name: "green.prom",
+            address: 0x400,
+            size: 0x400,
+            crc32: 0xbb6b83e2,
+            sha1: "4f9b8f91d39f7ed9aef002550151e9691d0ada16",
+        },
+    ],
+};
+
+static COLOUR_BOARD: BoardProfile = BoardProfile {
+    rom: &COLOUR_SET,
+    idle: &[],
+    inputs: &[],
+    dips: &[],
+    shift: ShiftPorts {
+        count: 2,
+        data: 4,
+        result: 3,
+        reversible: false,
+    },
+    colour: Colour::Prom {
+        select: Some((5, 0x20)),
+    },
+    hiscore: None,
+    inspector: false,
+    disassembly: &DisassemblyProfile::SPACE_INVADERS,
+    cheats: "",
+};
+
+fn padded(code: &[u8]) -> Vec<u8> {
+    let mut data = code.to_vec();
+    data.resize(0x400, 0);
+    data
+}
+
+#[test]
+fn test_colour_board_loads_extra_rom_and_proms() {
+    let dir = std::env::temp_dir().join(format!("colour_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("boot.bin"), padded(BOOT)).unwrap();
+    fs::write(dir.join("main.bin"), padded(MAIN)).unwrap();
+    fs::write(dir.join("red.prom"), [0x01; 0x400]).unwrap();
+    fs::write(dir.join("green.prom"), [0x04; 0x400]).unwrap();
+
+    let image = COLOUR_SET.load(&dir).unwrap();
+    assert_eq!(image.program.len(), 0x4400);
+    assert_eq!(image.program[0x4000..0x4003], MAIN[..3]);
+    assert!(image.program[0x400..0x4000].iter().all(|&byte| byte == 0));
+    assert_eq!(image.proms.len(), 0x800);
+    assert_eq!((image.proms[0], image.proms[0x400]), (0x01, 0x04));
+    assert!(COLOUR_SET.verify_image(&image.program).is_ok());
+    let prom = romset::crc32(&image.proms[0x400..]);
+    assert_eq!(prom, COLOUR_SET.proms[1].crc32);
+
+    let mut machine = SpaceInvadersMachine::from_profile(&COLOUR_BOARD, &[&dir]).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(machine.colour_map().unwrap()[0], 0x01);
+    machine.run_frame();
+    assert_eq!(machine.get_memory()[0x2400], 0xFF);
+    assert_eq!(machine.board().last_output(5), 0x20);
+    assert_eq!(machine.colour_map().unwrap()[0], 0x04);
+
+    machine.reset();
+    assert_eq!(machine.get_memory()[0x2400], 0x00);
+    assert_eq!(machine.get_memory()[0x4000..0x4003], MAIN[..3]);
+}
+
+#[test]
+fn test_render_rotates_and_colours_cells() {
+    let mut memory = vec![0; 0x4000];
+    // The first byte of video RAM is the bottom-left corner, lowest bit lowest
+    memory[0x2400] = 0x01;
+    // Byte 0x0120 is line 9, in the second row of colour cells
+    memory[0x2400 + 0x0120] = 0x80;
+    let mut colours = [0x00; video::COLOUR_MAP_SIZE];
+    colours[0] = 0x01;
+    colours[0x20] = 0x06;
+
+    let mut rgba = vec![0; WIDTH * HEIGHT * 4];
+    video::render(&memory[VIDEO_RAM], None, &mut rgba);
+    let pixel = |rgba: &[u8], x: usize, y: usize| rgba[(y * WIDTH + x) * 4..][..4].to_vec();
+    assert_eq!(pixel(&rgba, 0, HEIGHT - 1), [0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(pixel(&rgba, 0, HEIGHT - 2), [0x00, 0x00, 0x00, 0xFF]);
+    assert_eq!(pixel(&rgba, 9, HEIGHT - 8), [0xFF, 0xFF, 0xFF, 0xFF]);
+
+    video::render(&memory[VIDEO_RAM], Some(&colours), &mut rgba);
+    assert_eq!(pixel(&rgba, 0, HEIGHT - 1), [0xFF, 0x00, 0x00, 0xFF]);
+    assert_eq!(pixel(&rgba, 9, HEIGHT - 8), [0x00, 0xFF, 0xFF, 0xFF]);
+    assert_eq!(video::palette(2), [0x00, 0x00, 0xFF]);
+
+    // Cells a short map does not reach are white
+    video::render(&memory[VIDEO_RAM], Some(&colours[..0x20]), &mut rgba);
+    assert_eq!(pixel(&rgba, 0, HEIGHT - 1), [0xFF, 0x00, 0x00, 0xFF]);
+    assert_eq!(pixel(&rgba, 9, HEIGHT - 8), [0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+// Writes a pixel at the bottom-left corner and colours its cell blue
+const PAINT: &[u8] = &[
+    0x21, 0x00, 0x24, // LXI H,2400H
+    0x36, 0x01, // MVI M,01H
+    0x3E, 0x02, // MVI A,02H
+    0x32, 0x00, 0xC0, // STA 0C000H
+    0xC3, 0x0A, 0x00, // JMP 000AH
+];
+
+const COLOUR_RAM_SET: RomSet = RomSet {
+    name: "colourram",
+    description: "Colour RAM board test",
+    files: &[RomFile {
+        name: "paint.bin",
+        address: 0x0000,
+        size: 0x400,
+        crc32: 0x09a35305,
+        sha1: "82117989ef633e02445b1e78d8d95a6b10fc2fcf",
+    }],
+    proms: &[],
+};
+
+static COLOUR_RAM_BOARD: BoardProfile = BoardProfile {
+    rom: &COLOUR_RAM_SET,
+    colour: Colour::Ram { address: 0xC000 },
+    ..COLOUR_BOARD
+};
+
+#[test]
+fn test_colour_ram_board_renders_game_colours() {
+    let dir = std::env::temp_dir().join(format!("colour_ram_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("paint.bin"), padded(PAINT)).unwrap();
+    let mut machine = SpaceInvadersMachine::from_profile(&COLOUR_RAM_BOARD, &[&dir]).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(machine.colour_map().unwrap()[0], 0x00);
+    // Not Space Invaders, so the debugger has no game state to show
+    assert!(DebugTarget::inspector(&machine).is_none());
+    machine.run_frame();
+    assert_eq!(machine.colour_map().unwrap()[0], 0x02);
+    let mut rgba = vec![0; WIDTH * HEIGHT * 4];
+    video::render(
+        &machine.get_memory()[VIDEO_RAM],
+        machine.colour_map(),
+        &mut rgba,
+    );
+    let corner = (HEIGHT - 1) * WIDTH * 4;
+    assert_eq!(rgba[corner..corner + 4], [0x00, 0x00, 0xFF, 0xFF]);
+}
+
+#[test]
+fn test_balloon_bomber_switches_colour_maps() {
+    // Stand-ins for the set: the test program in the ROMs at 0 and 0x4000,
+    // and a different colour in each PROM
+    let dir = std::env::temp_dir().join(format!("ballbomb_test_{}", std::process::id()));
+    let set_dir = dir.join("ballbomb");
+    fs::create_dir_all(&set_dir).unwrap();
+    for file in romset::BALLBOMB.all_files() {
+        let data = match file.name {
+            "tn01" => BOOT,
+            "tn05-1" => MAIN,
+            "tn06" => &[0x03; 0x400],
+            "tn07" => &[0x05; 0x400],
+            _ => &[],
+        };
+        let mut data = data.to_vec();
+        data.resize(file.size, 0);
+        fs::write(set_dir.join(file.name), data).unwrap();
+    }
+    let mut machine = SpaceInvadersMachine::from_profile(&midway::BALLBOMB, &[&dir]).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(machine.colour_map().unwrap()[0], 0x03);
+    machine.run_frame();
+    assert_eq!(machine.board().last_output(5), 0x20);
+    assert_eq!(machine.colour_map().unwrap()[0], 0x05);
+}
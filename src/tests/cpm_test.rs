@@ -0,0 +1,290 @@
+use crate::assembler::assemble;
+use crate::emulator::console::ScriptConsole;
+use crate::emulator::cpm::{self, Cpm, CpmError, Exit};
+use std::fs;
+use std::path::PathBuf;
+
+// Copies the file named by the first argument to the second, then lists the
+// directory one name per line
+const COPY: &str = "
+BDOS    EQU     5
+FCB1    EQU     5CH
+FCB2    EQU     6CH
+        ORG     100H
+        LXI     SP,STACK
+        LXI     H,FCB2          ; The CCP puts the second name where the
+        LXI     D,OUTFCB        ; first FCB's records go, so move it out
+        MVI     B,16
+SAVE:   MOV     A,M
+        STAX    D
+        INX     H
+        INX     D
+        DCR     B
+        JNZ     SAVE
+        MVI     C,15
+        LXI     D,FCB1
+        CALL    BDOS
+        INR     A
+        JZ      FAIL
+        MVI     C,22
+        LXI     D,OUTFCB
+        CALL    BDOS
+COPY:   MVI     C,20
+        LXI     D,FCB1
+        CALL    BDOS
+        ORA     A
+        JNZ     DONE
+        MVI     C,21
+        LXI     D,OUTFCB
+        CALL    BDOS
+        JMP     COPY
+DONE:   MVI     C,16
+        LXI     D,OUTFCB
+        CALL    BDOS
+        MVI     C,17
+        LXI     D,ALL
+LIST:   CALL    BDOS
+        INR     A
+        RZ
+        LXI     H,81H
+        MVI     B,11
+NAME:   MOV     E,M
+        MVI     C,2
+        PUSH    H
+        PUSH    B
+        CALL    BDOS
+        POP     B
+        POP     H
+        INX     H
+        DCR     B
+        JNZ     NAME
+        MVI     C,9
+        LXI     D,CRLF
+        CALL    BDOS
+        MVI     C,18
+        JMP     LIST
+FAIL:   MVI     C,9
+        LXI     D,NOFILE
+        CALL    BDOS
+        JMP     0
+NOFILE: DB      'NO FILE$'
+CRLF:   DB      13,10,'$'
+ALL:    DB      0,'???????????',0,0,0,0
+        DS      20
+OUTFCB: DS      36
+        DS      32
+STACK:
+";
+
+// Reads a line and prints it back reversed, until an empty line
+const REVERSE: &str = "
+BDOS    EQU     5
+        ORG     100H
+AGAIN:  MVI     C,10
+        LXI     D,BUFFER
+        CALL    BDOS
+        LDA     BUFFER+1
+        ORA     A
+        RZ
+        MOV     B,A
+        LXI     H,BUFFER+1
+        MOV     E,B
+        MVI     D,0
+        DAD     D
+NEXT:   PUSH    H
+        PUSH    B
+        MOV     E,M
+        MVI     C,2
+        CALL    BDOS
+        POP     B
+        POP     H
+        DCX     H
+        DCR     B
+        JNZ     NEXT
+        MVI     C,9
+        LXI     D,CRLF
+        CALL    BDOS
+        JMP     AGAIN
+CRLF:   DB      13,10,'$'
+BUFFER: DB      20
+";
+
+// Writes record 3 of a new file at random, then reads the size back
+const RANDOM: &str = "
+BDOS    EQU     5
+FCB     EQU     5CH
+        ORG     100H
+        MVI     C,22
+        LXI     D,FCB
+        CALL    BDOS
+        MVI     A,3
+        STA     FCB+33
+        MVI     C,34
+        LXI     D,FCB
+        CALL    BDOS
+        STA     RESULT
+        XRA     A
+        STA     FCB+33
+        MVI     C,35
+        LXI     D,FCB
+        CALL    BDOS
+        HLT
+RESULT: DB      0FFH
+";
+
+// Puts the directory entry from a search at the top of memory, so that it
+// runs on into page zero, then opens and reads the file it names using the
+// entry as the FCB. Last, reads a line into a buffer at FFFEH. Page zero
+// gets overwritten, so the BDOS is called directly.
+const TOP_OF_MEMORY: &str = "
+BDOS    EQU     0EC06H
+        ORG     100H
+        MVI     C,26
+        LXI     D,0FFF8H
+        CALL    BDOS
+        MVI     C,17
+        LXI     D,ALL
+        CALL    BDOS
+        STA     RESULT
+        MVI     C,26
+        LXI     D,80H
+        CALL    BDOS
+        MVI     C,15
+        LXI     D,0FFF8H
+        CALL    BDOS
+        STA     RESULT+1
+        XRA     A
+        STA     18H             ; The current record, FFF8H+32
+        MVI     C,20
+        LXI     D,0FFF8H
+        CALL    BDOS
+        STA     RESULT+2
+        MVI     A,4
+        STA     0FFFEH
+        MVI     C,10
+        LXI     D,0FFFEH
+        CALL    BDOS
+        HLT
+ALL:    DB      0,'???????????',0,0,0,0
+RESULT: DB      0FFH,0FFH,0FFH
+";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cpm_test_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn program(source: &str) -> Vec<u8> {
+    assemble(source).unwrap().to_bytes()
+}
+
+#[test]
+fn test_cpudiag_runs_as_a_com_file() {
+    let mut cpm = Cpm::new(".", ScriptConsole::new(""));
+    cpm.load_com_file("roms/cpu_diag/cpudiag.bin", "").unwrap();
+    assert_eq!(cpm.run(), Exit::WarmBoot);
+    assert!(cpm.console().output_text().contains("CPU IS OPERATIONAL"));
+}
+
+#[test]
+fn test_files_copy_and_list() {
+    let dir = temp_dir("copy");
+    let text: Vec<u8> = (0..300).map(|i| b'A' + (i % 26) as u8).collect();
+    fs::write(dir.join("source.txt"), &text).unwrap();
+    fs::write(dir.join("not a cpm name.txt"), b"").unwrap();
+
+    let mut cpm = Cpm::new(&dir, ScriptConsole::new(""));
+    cpm.load_com(&program(COPY), "source.txt b:copy.txt")
+        .unwrap();
+    cpm.mount(1, &dir);
+    assert_eq!(cpm.run_for(1_000_000), Some(Exit::WarmBoot));
+
+    let copy = fs::read(dir.join("COPY.TXT")).unwrap();
+    assert_eq!(copy.len(), 384);
+    assert_eq!(copy[..300], text[..]);
+    assert!(copy[300..].iter().all(|&byte| byte == 0x1A));
+    assert_eq!(
+        cpm.console().output_text(),
+        "COPY    TXT\r\nSOURCE  TXT\r\n"
+    );
+
+    let mut cpm = Cpm::new(&dir, ScriptConsole::new(""));
+    cpm.load_com(&program(COPY), "missing.txt out.txt").unwrap();
+    assert_eq!(cpm.run(), Exit::WarmBoot);
+    assert_eq!(cpm.console().output_text(), "NO FILE");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_console_lines() {
+    let mut cpm = Cpm::new(".", ScriptConsole::new("hello\rabx\x08c\r\r"));
+    cpm.load_com(&program(REVERSE), "").unwrap();
+    assert_eq!(cpm.run(), Exit::WarmBoot);
+    assert_eq!(
+        cpm.console().output_text(),
+        "hello\rolleh\r\nabx\x08 \x08c\rcba\r\n\r"
+    );
+
+    // Running out of input stops the program instead of blocking
+    let mut cpm = Cpm::new(".", ScriptConsole::new("abc\r"));
+    cpm.load_com(&program(REVERSE), "").unwrap();
+    assert_eq!(cpm.run(), Exit::EndOfInput);
+}
+
+#[test]
+fn test_random_records() {
+    let dir = temp_dir("random");
+    let mut cpm = Cpm::new(&dir, ScriptConsole::new(""));
+    let image = program(RANDOM);
+    cpm.load_com(&image, "data.bin").unwrap();
+    assert!(matches!(cpm.run(), Exit::Halted { .. }));
+
+    let result = 0x100 + image.len() - 1;
+    assert_eq!(cpm.get_state().memory[result], 0);
+    // The size is one past the last record written
+    assert_eq!(cpm.get_state().memory[0x5C + 33], 4);
+    assert_eq!(fs::metadata(dir.join("DATA.BIN")).unwrap().len(), 512);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_names_and_command_tail() {
+    assert_eq!(cpm::parse_file_name("B:FOO.BAS"), (2, *b"FOO     BAS"));
+    assert_eq!(cpm::parse_file_name("*.C*"), (0, *b"????????C??"));
+    assert_eq!(cpm::parse_file_name(""), (0, *b"           "));
+    assert_eq!(cpm::cpm_name("mbasic.com"), Some(*b"MBASIC  COM"));
+    assert_eq!(cpm::cpm_name("README"), Some(*b"README     "));
+    assert_eq!(cpm::cpm_name("toolongname.com"), None);
+    assert_eq!(cpm::cpm_name("a.b.c"), None);
+
+    let mut cpm = Cpm::new(".", ScriptConsole::new(""));
+    cpm.load_com(&[0xC9], "one.asm b:two").unwrap();
+    let memory = &cpm.get_state().memory;
+    assert_eq!(memory[0x80], 14);
+    assert_eq!(&memory[0x81..0x8F], b" ONE.ASM B:TWO");
+    assert_eq!(&memory[0x5D..0x68], b"ONE     ASM");
+    assert_eq!(memory[0x6C], 2);
+    assert_eq!(cpm.run(), Exit::WarmBoot);
+
+    let error = cpm.load_com(&vec![0; 0xF000], "").unwrap_err();
+    assert!(matches!(error, CpmError::TooLarge { size: 0xF000 }));
+}
+
+#[test]
+fn test_fcbs_and_buffers_wrap_at_top_of_memory() {
+    let dir = temp_dir("wrap");
+    fs::write(dir.join("WRAP.TXT"), "hello").unwrap();
+    let mut cpm = Cpm::new(&dir, ScriptConsole::new("abc\r"));
+    let image = program(TOP_OF_MEMORY);
+    cpm.load_com(&image, "").unwrap();
+    assert!(matches!(cpm.run(), Exit::Halted { .. }));
+
+    let memory = &cpm.get_state().memory;
+    let result = 0x100 + image.len() - 3;
+    assert_eq!(memory[result..result + 3], [0, 0, 0]);
+    assert_eq!(&memory[0x80..0x85], b"hello");
+    assert_eq!(memory[0xFFFF], 3);
+    assert_eq!(&memory[0..3], b"abc");
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,154 @@
+use crate::assembler::assemble;
+use crate::emulator::cpu::emulate_8080_op;
+use crate::emulator::data_types::State8080;
+
+/// A CPU with `source` assembled at 0 and the stack at 1000H.
+fn state_with(source: &str) -> State8080 {
+    let mut state = State8080 {
+        sp: 0x1000,
+        ..Default::default()
+    };
+    let program = assemble(source).unwrap().to_bytes();
+    state.memory[..program.len()].copy_from_slice(&program);
+    state
+}
+
+#[test]
+fn test_jp_and_cp_follow_the_sign_flag() {
+    // JP and CP are taken when the sign is clear (positive)
+    for (sign, jumps) in [(false, true), (true, false)] {
+        let mut state = state_with("JP 1234H");
+        state.cc.s = sign;
+        emulate_8080_op(&mut state);
+        assert_eq!(state.pc == 0x1234, jumps, "JP with S={}", sign);
+
+        let mut state = state_with("CP 1234H");
+        state.cc.s = sign;
+        emulate_8080_op(&mut state);
+        assert_eq!(state.pc == 0x1234, jumps, "CP with S={}", sign);
+        assert_eq!(state.sp == 0x0FFE, jumps, "CP with S={}", sign);
+        if !jumps {
+            assert_eq!(state.pc, 3);
+        }
+    }
+
+    // And JM and CM when it is set
+    for (sign, jumps) in [(false, false), (true, true)] {
+        let mut state = state_with("JM 1234H");
+        state.cc.s = sign;
+        emulate_8080_op(&mut state);
+        assert_eq!(state.pc == 0x1234, jumps, "JM with S={}", sign);
+
+        let mut state = state_with("CM 1234H");
+        state.cc.s = sign;
+        emulate_8080_op(&mut state);
+        assert_eq!(state.pc == 0x1234, jumps, "CM with S={}", sign);
+    }
+}
+
+#[test]
+fn test_rm_returns_when_minus() {
+    for (sign, returns) in [(false, false), (true, true)] {
+        let mut state = state_with("RM");
+        state.memory[0x1000..0x1002].copy_from_slice(&[0x34, 0x12]);
+        state.cc.s = sign;
+        emulate_8080_op(&mut state);
+        let expected = if returns { 0x1234 } else { 1 };
+        assert_eq!(state.pc, expected, "RM with S={}", sign);
+    }
+}
+
+/// Runs `source` with A, B and the carry set, returning A and the carry
+/// and auxiliary carry flags.
+fn arithmetic(source: &str, a: u8, b: u8, carry: bool) -> (u8, bool, bool) {
+    let mut state = state_with(source);
+    state.a = a;
+    state.b = b;
+    state.cc.cy = carry;
+    emulate_8080_op(&mut state);
+    (state.a, state.cc.cy, state.cc.ac)
+}
+
+#[test]
+fn test_additions_set_auxiliary_carry() {
+    assert_eq!(arithmetic("ADD B", 0x0F, 0x01, false), (0x10, false, true));
+    assert_eq!(arithmetic("ADD B", 0x0E, 0x01, false), (0x0F, false, false));
+    assert_eq!(arithmetic("ADI 01H", 0x0F, 0, false), (0x10, false, true));
+    assert_eq!(arithmetic("ADC B", 0x0E, 0x01, true), (0x10, false, true));
+    assert_eq!(arithmetic("ACI 01H", 0x0E, 0, true), (0x10, false, true));
+    assert_eq!(arithmetic("ACI 01H", 0xFE, 0, false), (0xFF, false, false));
+}
+
+#[test]
+fn test_subtractions_set_auxiliary_carry_without_a_nibble_borrow() {
+    // The 8080 adds the complement, so AC is the carry out of bit 3: set
+    // unless the low nibble borrows
+    assert_eq!(arithmetic("SUB B", 0x12, 0x01, false), (0x11, false, true));
+    assert_eq!(arithmetic("SUB B", 0x10, 0x01, false), (0x0F, false, false));
+    assert_eq!(arithmetic("SUB A", 0x3E, 0, false), (0x00, false, true));
+    assert_eq!(arithmetic("SUI 01H", 0x10, 0, false), (0x0F, false, false));
+    assert_eq!(arithmetic("SUI 01H", 0x00, 0, false), (0xFF, true, false));
+    assert_eq!(arithmetic("SBB B", 0x12, 0x01, true), (0x10, false, true));
+    assert_eq!(arithmetic("SBB B", 0x11, 0x01, true), (0x0F, false, false));
+    assert_eq!(arithmetic("SBI 01H", 0x12, 0, true), (0x10, false, true));
+    assert_eq!(arithmetic("SBI 01H", 0x11, 0, true), (0x0F, false, false));
+
+    // Compares leave A alone but set the flags as SUB does
+    assert_eq!(arithmetic("CMP B", 0x12, 0x01, false), (0x12, false, true));
+    assert_eq!(arithmetic("CMP B", 0x10, 0x01, false), (0x10, false, false));
+    assert_eq!(arithmetic("CPI 01H", 0x12, 0, false), (0x12, false, true));
+    assert_eq!(arithmetic("CPI 01H", 0x10, 0, false), (0x10, false, false));
+}
+
+#[test]
+fn test_sbb_borrows_against_a_before_the_subtraction() {
+    for (a, b, borrow, result, carry) in [
+        (0x05, 0x03, false, 0x02, false),
+        (0x03, 0x03, true, 0xFF, true),
+        (0x10, 0x01, true, 0x0E, false),
+        (0x00, 0xFF, true, 0x00, true),
+    ] {
+        let (after, cy, _) = arithmetic("SBB B", a, b, borrow);
+        assert_eq!((after, cy), (result, carry), "SBB {:02X} - {:02X}", a, b);
+    }
+}
+
+#[test]
+fn test_decrements_set_auxiliary_carry() {
+    for (value, ac) in [(0x11, true), (0x10, false), (0x00, false)] {
+        let mut state = state_with("DCR B");
+        state.b = value;
+        emulate_8080_op(&mut state);
+        assert_eq!(state.cc.ac, ac, "DCR B from {:02X}", value);
+
+        let mut state = state_with("DCR M");
+        state.memory[0x2000] = value;
+        (state.h, state.l) = (0x20, 0x00);
+        emulate_8080_op(&mut state);
+        assert_eq!(state.cc.ac, ac, "DCR M from {:02X}", value);
+    }
+}
+
+#[test]
+fn test_daa_adjusts_and_keeps_carry() {
+    // 99 + 01 = 00 carry 1
+    let mut state = state_with("ADI 01H\nDAA");
+    state.a = 0x99;
+    emulate_8080_op(&mut state);
+    emulate_8080_op(&mut state);
+    assert_eq!((state.a, state.cc.cy), (0x00, true));
+
+    // 90 + 90 = 80 carry 1: the carry from the addition survives DAA
+    let mut state = state_with("ADI 90H\nDAA");
+    state.a = 0x90;
+    emulate_8080_op(&mut state);
+    emulate_8080_op(&mut state);
+    assert_eq!((state.a, state.cc.cy), (0x80, true));
+
+    // 19 + 08 = 27, adjusted for the auxiliary carry though 21 is valid BCD
+    let mut state = state_with("ADI 08H\nDAA");
+    state.a = 0x19;
+    emulate_8080_op(&mut state);
+    emulate_8080_op(&mut state);
+    assert_eq!((state.a, state.cc.cy), (0x27, false));
+}
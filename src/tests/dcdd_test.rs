@@ -0,0 +1,159 @@
+use crate::assembler::assemble;
+use crate::emulator::altair::Altair;
+use crate::emulator::console::ScriptConsole;
+use crate::emulator::cpm::Exit;
+use crate::emulator::dcdd::{self, Dcdd};
+use crate::emulator::disk::{DiskFormat, DiskImage};
+use std::fs;
+
+const SECTOR: usize = 137;
+
+fn sector_offset(track: usize, sector: usize) -> usize {
+    (track * 32 + sector) * SECTOR
+}
+
+// Copies track 1 sector 3 of drive 0 to track 2 sector 5 of drive 1 through
+// the controller's ports, the way a disk BIOS does
+const COPY: &str = "
+        ORG     0
+        LXI     SP,0800H
+        XRA     A
+        OUT     8
+        MVI     A,04H
+        OUT     9
+        MVI     A,01H
+        OUT     9
+        MVI     B,3
+        CALL    FIND
+        LXI     H,1000H
+        MVI     C,137
+READ:   IN      8
+        ORA     A
+        JM      READ
+        IN      0AH
+        MOV     M,A
+        INX     H
+        DCR     C
+        JNZ     READ
+        MVI     A,1
+        OUT     8
+        MVI     A,04H
+        OUT     9
+        MVI     A,01H
+        OUT     9
+        OUT     9
+        IN      8
+        STA     STATUS
+        MVI     B,5
+        CALL    FIND
+        MVI     A,80H
+        OUT     9
+        LXI     H,1000H
+        MVI     C,137
+WRITE:  IN      8
+        RRC
+        JC      WRITE
+        MOV     A,M
+        OUT     0AH
+        INX     H
+        DCR     C
+        JNZ     WRITE
+        HLT
+FIND:   IN      9
+        RRC
+        JC      FIND
+        ANI     1FH
+        CMP     B
+        JNZ     FIND
+        RET
+STATUS: DB      0
+";
+
+#[test]
+fn test_sectors_copy_between_drives() {
+    let mut source = vec![0; DiskFormat::Mits.image_size()];
+    let offset = sector_offset(1, 3);
+    for (i, byte) in source[offset..offset + SECTOR].iter_mut().enumerate() {
+        *byte = i as u8 ^ 0x5A;
+    }
+    let dir = std::env::temp_dir().join(format!("dcdd_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let target = dir.join("target.dsk");
+    fs::write(&target, vec![0; DiskFormat::Mits.image_size()]).unwrap();
+
+    let mut altair = Altair::new(0x2000, ScriptConsole::new(""));
+    let source = DiskImage::from_bytes(source, DiskFormat::Mits).unwrap();
+    altair.mount(0, source.clone());
+    altair.mount(
+        1,
+        DiskImage::open(&target, DiskFormat::Mits, false).unwrap(),
+    );
+    let program = assemble(COPY).unwrap();
+    altair.load(0, &program.to_bytes());
+    assert!(matches!(altair.run_for(100_000), Some(Exit::Halted { .. })));
+
+    let copied = sector_offset(2, 5);
+    let written = fs::read(&target).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        written[copied..copied + SECTOR],
+        source.data()[offset..offset + SECTOR]
+    );
+    assert_eq!(altair.disk(1).unwrap().data(), &written[..]);
+    // Head loaded and away from track 0, with a byte ready to read
+    let status = program.symbols.address_of("STATUS").unwrap() as usize;
+    assert_eq!(altair.get_state().memory[status], 0x61);
+}
+
+#[test]
+fn test_read_only_disks_and_deselect() {
+    let mut dcdd = Dcdd::new();
+    assert_eq!(dcdd.input(dcdd::STATUS), 0xFF);
+    let mut image = DiskImage::blank(DiskFormat::Mits);
+    image.set_read_only(true);
+    dcdd.mount(0, image);
+    dcdd.output(dcdd::STATUS, 0x00);
+    // At track 0 with the head up
+    assert_eq!(dcdd.input(dcdd::STATUS), 0xA5);
+    assert_eq!(dcdd.input(dcdd::SECTOR_POSITION), 0xFF);
+    dcdd.output(dcdd::SECTOR_POSITION, 0x04);
+    assert_eq!(dcdd.input(dcdd::SECTOR_POSITION), 0xC2);
+    assert_eq!(dcdd.input(dcdd::SECTOR_POSITION), 0xC3);
+    assert_eq!(dcdd.input(dcdd::SECTOR_POSITION), 0xC4);
+
+    dcdd.output(dcdd::SECTOR_POSITION, 0x80);
+    assert_eq!(dcdd.input(dcdd::STATUS) & 0x01, 0);
+    for _ in 0..SECTOR {
+        dcdd.output(dcdd::DATA, 0x00);
+    }
+    assert!(dcdd
+        .disk(0)
+        .unwrap()
+        .data()
+        .iter()
+        .all(|&byte| byte == 0xE5));
+
+    dcdd.output(dcdd::STATUS, 0x80);
+    assert_eq!(dcdd.input(dcdd::STATUS), 0xFF);
+    assert!(dcdd.eject(0).is_some());
+}
+
+// A boot ROM cannot overwrite itself
+const BOOT: &str = "
+        ORG     0FF00H
+        MVI     A,0
+        STA     0FF00H
+        LDA     0FF00H
+        OUT     1
+        HLT
+";
+
+#[test]
+fn test_boot_rom_is_read_only() {
+    let mut altair = Altair::new(0x10000, ScriptConsole::new(""));
+    altair.load_rom(0xFF00, &assemble(BOOT).unwrap().to_bytes());
+    altair.set_switches(0xFF00);
+    altair.examine();
+    assert!(matches!(altair.run(), Exit::Halted { .. }));
+    assert_eq!(altair.console().output_text(), ">");
+}
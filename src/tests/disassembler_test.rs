@@ -0,0 +1,80 @@
+use crate::disassembler::instruction::Flow;
+use crate::disassembler::{decode, FormatOptions, HexStyle, Syntax};
+
+fn format(bytes: &[u8], options: &FormatOptions) -> String {
+    decode(bytes, 0).format(options)
+}
+
+#[test]
+fn test_decodes_every_opcode() {
+    for opcode in 0..=255u8 {
+        let instruction = decode(&[opcode, 0x34, 0x12], 0);
+        let expected_length = match opcode {
+            0x01 | 0x11 | 0x21 | 0x31 | 0x22 | 0x2A | 0x32 | 0x3A => 3,
+            op if op & 0xC7 == 0xC2 || op & 0xC7 == 0xC4 => 3,
+            0xC3 | 0xCB | 0xCD | 0xDD | 0xED | 0xFD => 3,
+            op if op & 0xC7 == 0x06 || op & 0xC7 == 0xC6 => 2,
+            0xD3 | 0xDB => 2,
+            _ => 1,
+        };
+        assert_eq!(instruction.length, expected_length, "opcode {:02X}", opcode);
+        assert!(!instruction.to_string().starts_with("DB"));
+    }
+}
+
+#[test]
+fn test_intel_formatting() {
+    let options = FormatOptions::default();
+    assert_eq!(format(&[0x01, 0x34, 0x12], &options), "LXI B,$1234");
+    assert_eq!(format(&[0x7E], &options), "MOV A,M");
+    assert_eq!(format(&[0x96], &options), "SUB M");
+    assert_eq!(format(&[0xF5], &options), "PUSH PSW");
+    assert_eq!(format(&[0xEF], &options), "RST 5");
+    assert_eq!(format(&[0xDE, 0x0F], &options), "SBI $0F");
+
+    let suffix = FormatOptions {
+        hex: HexStyle::Suffix,
+        mnemonic_width: 6,
+        ..FormatOptions::default()
+    };
+    assert_eq!(format(&[0xC3, 0xD3, 0x15], &suffix), "JMP   15D3H");
+    assert_eq!(format(&[0x3E, 0xFF], &suffix), "MVI   A,0FFH");
+}
+
+#[test]
+fn test_zilog_formatting() {
+    let options = FormatOptions {
+        syntax: Syntax::Zilog,
+        hex: HexStyle::CStyle,
+        ..FormatOptions::default()
+    };
+    assert_eq!(format(&[0x7E], &options), "LD A,(HL)");
+    assert_eq!(format(&[0x1A], &options), "LD A,(DE)");
+    assert_eq!(format(&[0xC2, 0x00, 0x20], &options), "JP NZ,0x2000");
+    assert_eq!(format(&[0xF1], &options), "POP AF");
+    assert_eq!(format(&[0x29], &options), "ADD HL,HL");
+    assert_eq!(format(&[0xFF], &options), "RST 0x38");
+    assert_eq!(format(&[0xD8], &options), "RET C");
+}
+
+#[test]
+fn test_branch_targets_and_cycles() {
+    let call = decode(&[0xCC, 0xD3, 0x15], 0);
+    assert_eq!(call.flow, Flow::ConditionalCall);
+    assert_eq!(call.branch_target, Some(0x15D3));
+    assert_eq!((call.cycles, call.cycles_taken), (11, Some(17)));
+
+    let rst = decode(&[0xD7], 0);
+    assert_eq!(rst.branch_target, Some(0x10));
+
+    let alias = decode(&[0xDD, 0x00, 0x01], 0);
+    assert_eq!(alias.mnemonic, "CALL");
+    assert!(!alias.documented);
+
+    // Operands wrap around the end of the address space
+    let mut memory = vec![0u8; 0x10000];
+    memory[0xFFFF] = 0xC3;
+    memory[0] = 0x00;
+    memory[1] = 0x20;
+    assert_eq!(decode(&memory, 0xFFFF).branch_target, Some(0x2000));
+}
@@ -0,0 +1,160 @@
+use crate::assembler::assemble;
+use crate::emulator::console::ScriptConsole;
+use crate::emulator::cpm::Exit;
+use crate::emulator::disk::{
+    DiskError, DiskFormat, DiskImage, DiskStatus, DiskSystem, SECTOR_SIZE,
+};
+use std::fs;
+
+const IMAGE_SIZE: usize = DiskFormat::Ibm3740.image_size();
+
+// Track 0, sector 1: loads the next two sectors to 0x0100 and runs them, the
+// way a CP/M cold boot loader brings in the CCP, BDOS and BIOS
+const LOADER: &str = "
+        ORG     0
+        LXI     SP,100H
+        LXI     H,100H
+        MVI     B,2
+NEXT:   MOV     A,B
+        OUT     12
+        MOV     A,L
+        OUT     15
+        MOV     A,H
+        OUT     16
+        XRA     A
+        OUT     13
+        IN      14
+        ORA     A
+        JNZ     FAIL
+        LXI     D,128
+        DAD     D
+        INR     B
+        MOV     A,B
+        CPI     4
+        JNZ     NEXT
+        JMP     100H
+FAIL:   HLT
+";
+
+// Echoes a line from the console, saves it to the last sector of drive B:,
+// then tries a read-only drive and a missing sector
+const SYSTEM: &str = "
+        ORG     100H
+        LXI     H,HELLO
+        CALL    PRINT
+        LXI     H,LINE
+KEY:    IN      0
+        ORA     A
+        JZ      KEY
+        IN      1
+        OUT     1
+        MOV     M,A
+        INX     H
+        CPI     13
+        JNZ     KEY
+        MVI     A,1
+        OUT     10
+        MVI     A,76
+        OUT     11
+        MVI     A,26
+        OUT     12
+        LXI     H,LINE
+        CALL    DMA
+        MVI     A,1
+        OUT     13
+        IN      14
+        STA     STATUS
+        MVI     A,2
+        OUT     10
+        MVI     A,1
+        OUT     13
+        IN      14
+        STA     STATUS+1
+        MVI     A,27
+        OUT     12
+        XRA     A
+        OUT     13
+        IN      14
+        STA     STATUS+2
+        IN      1
+        HLT
+DMA:    MOV     A,L
+        OUT     15
+        MOV     A,H
+        OUT     16
+        RET
+PRINT:  MOV     A,M
+        ORA     A
+        RZ
+        OUT     1
+        INX     H
+        JMP     PRINT
+HELLO:  DB      'A>',0
+STATUS: DB      0FFH,0FFH,0FFH
+LINE:   DS      128
+";
+
+fn system_disk() -> DiskImage {
+    let mut data = vec![0xE5; IMAGE_SIZE];
+    let loader = assemble(LOADER).unwrap().to_bytes();
+    data[..loader.len()].copy_from_slice(&loader);
+    let system = assemble(SYSTEM).unwrap().to_bytes();
+    assert!(system.len() <= 2 * SECTOR_SIZE);
+    data[SECTOR_SIZE..SECTOR_SIZE + system.len()].copy_from_slice(&system);
+    DiskImage::from_bytes(data, DiskFormat::Ibm3740).unwrap()
+}
+
+#[test]
+fn test_boots_and_writes_through_to_image_files() {
+    let dir = std::env::temp_dir().join(format!("disk_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (b, c) = (dir.join("b.dsk"), dir.join("c.dsk"));
+    fs::write(&b, vec![0xE5; IMAGE_SIZE]).unwrap();
+    fs::write(&c, vec![0xE5; IMAGE_SIZE]).unwrap();
+
+    let mut system = DiskSystem::new(ScriptConsole::new("dir\r"));
+    assert!(matches!(system.boot(), Err(DiskError::NoBootDisk)));
+    system.mount(0, system_disk());
+    system.mount(1, DiskImage::open(&b, DiskFormat::Ibm3740, false).unwrap());
+    system.mount(2, DiskImage::open(&c, DiskFormat::Ibm3740, true).unwrap());
+    system.boot().unwrap();
+    // The last IN 1 finds the input has run out
+    assert_eq!(system.run_for(100_000), Some(Exit::EndOfInput));
+    assert_eq!(system.console().output_text(), "A>dir\r");
+
+    let last = IMAGE_SIZE - SECTOR_SIZE;
+    assert_eq!(&fs::read(&b).unwrap()[last..last + 4], b"dir\r");
+    assert_eq!(&system.disk(1).unwrap().data()[last..last + 4], b"dir\r");
+    assert!(fs::read(&c).unwrap().iter().all(|&byte| byte == 0xE5));
+    fs::remove_dir_all(&dir).unwrap();
+
+    let memory = &system.get_state().memory;
+    let status = assemble(SYSTEM)
+        .unwrap()
+        .symbols
+        .address_of("STATUS")
+        .unwrap() as usize;
+    assert_eq!(
+        memory[status..status + 3],
+        [
+            DiskStatus::Ok as u8,
+            DiskStatus::WriteError as u8,
+            DiskStatus::IllegalSector as u8
+        ]
+    );
+    assert!(matches!(system.run(), Exit::Halted { .. }));
+}
+
+#[test]
+fn test_image_size_is_checked() {
+    assert_eq!(DiskFormat::Ibm3740.image_size(), 256_256);
+    assert_eq!(DiskFormat::Mits.image_size(), 337_568);
+    let error = DiskImage::from_bytes(vec![0; IMAGE_SIZE], DiskFormat::Mits).unwrap_err();
+    assert!(matches!(error, DiskError::Size { size: 256_256, .. }));
+    assert_eq!(
+        error.to_string(),
+        "disk image is 256256 bytes, a MITS 8\" image is 337568"
+    );
+    let blank = DiskImage::blank(DiskFormat::Ibm3740);
+    assert!(blank.data().iter().all(|&byte| byte == 0xE5));
+}
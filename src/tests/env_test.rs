@@ -0,0 +1,62 @@
+use crate::env::ffi::{si_env_free, si_env_new, si_env_observation, si_env_reset, si_env_step};
+use crate::env::{Action, Env, EnvConfig};
+
+#[test]
+fn test_reset_is_deterministic_per_seed() {
+    let mut env = Env::new(EnvConfig::default());
+    assert_eq!(env.observation_shape(), (112, 128));
+    let first = env.reset(7).to_vec();
+    assert_eq!(first.len(), 112 * 128);
+    assert!(first.iter().any(|&pixel| pixel > 0));
+    assert_eq!(env.reset(7), &first[..]);
+    assert_eq!(env.score(), 0);
+    assert_eq!(env.lives(), 2);
+    assert!(!env.is_done());
+}
+
+#[test]
+fn test_firing_scores_points() {
+    let config = EnvConfig {
+        sticky_action_probability: 0.0,
+        ..EnvConfig::default()
+    };
+    let mut env = Env::new(config);
+    env.reset(1);
+    let mut total = 0;
+    for step in 0..400 {
+        let action = if step % 4 == 0 {
+            Action::Fire
+        } else {
+            Action::Noop
+        };
+        let (_, reward, done) = env.step(action);
+        assert!(reward >= 0);
+        total += reward;
+        if done {
+            break;
+        }
+    }
+    assert!(total > 0);
+    assert_eq!(total as u32, env.score());
+}
+
+#[test]
+fn test_ffi_round_trip() {
+    unsafe {
+        let env = si_env_new(0, -1.0, 4);
+        assert!(!env.is_null());
+        si_env_reset(env, 3);
+        let mut reward = -1;
+        assert_eq!(si_env_step(env, 1, &mut reward), 0);
+        assert_eq!(reward, 0);
+        assert_eq!(si_env_step(env, 99, &mut reward), -1);
+
+        let mut buffer = vec![0u8; 56 * 64];
+        assert_eq!(
+            si_env_observation(env, buffer.as_mut_ptr(), buffer.len()),
+            56 * 64
+        );
+        assert!(buffer.iter().any(|&pixel| pixel > 0));
+        si_env_free(env);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::debugger::gdb_stub::GdbStub;
+use crate::emulator::cpu::CPU;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+// LXI SP,$2400 / MVI A,$42 / STA $2000 / loop: INR A / JMP loop
+const PROGRAM: [u8; 12] = [
+    0x31, 0x00, 0x24, 0x3E, 0x42, 0x32, 0x00, 0x20, 0x3C, 0xC3, 0x08, 0x00,
+];
+
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    fn request(&mut self, body: &str) -> String {
+        let checksum = body.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.stream, "${}#{:02x}", body, checksum).unwrap();
+        assert_eq!(self.read_byte(), b'+', "stub did not ack {}", body);
+
+        while self.read_byte() != b'$' {}
+        let mut reply = Vec::new();
+        loop {
+            match self.read_byte() {
+                b'#' => break,
+                byte => reply.push(byte),
+            }
+        }
+        self.read_byte();
+        self.read_byte();
+        self.stream.write_all(b"+").unwrap();
+        String::from_utf8(reply).unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let mut byte = [0u8];
+        self.stream.read_exact(&mut byte).unwrap();
+        byte[0]
+    }
+}
+
+fn start_stub() -> (Client, thread::JoinHandle<CPU>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let mut cpu = CPU::new();
+        cpu.init_rom(PROGRAM.to_vec());
+        GdbStub::new(&mut cpu).serve(&listener).unwrap();
+        cpu
+    });
+    let stream = TcpStream::connect(addr).unwrap();
+    (Client { stream }, server)
+}
+
+#[test]
+fn test_registers_and_single_step() {
+    let (mut client, server) = start_stub();
+
+    assert!(client.request("qSupported:swbreak+").contains("swbreak+"));
+    assert_eq!(client.request("?"), "S05");
+    assert_eq!(client.request("g"), "000000000000000000000000");
+
+    assert_eq!(client.request("s"), "S05");
+    assert_eq!(client.request("s"), "S05");
+    // A=42, SP=2400, PC=0005
+    assert_eq!(client.request("p0"), "42");
+    assert_eq!(client.request("p5"), "0024");
+    assert_eq!(client.request("p6"), "0500");
+
+    assert_eq!(client.request("P4=3412"), "OK");
+    assert_eq!(client.request("p4"), "3412");
+    // Values must be the register's size
+    assert_eq!(client.request("P0="), "E01");
+    assert_eq!(client.request("P6="), "E01");
+    assert_eq!(client.request("P6=05"), "E01");
+    assert_eq!(client.request("P1=0000"), "E01");
+    assert_eq!(client.request("p6"), "0500");
+
+    // Addresses past 64K wrap rather than overflow
+    assert_eq!(client.request("mffffffffffffffff,2"), "0031");
+
+    assert_eq!(client.request("D"), "OK");
+    let cpu = server.join().unwrap();
+    assert_eq!((cpu.state.h, cpu.state.l), (0x12, 0x34));
+}
+
+#[test]
+fn test_memory_breakpoints_and_watchpoints() {
+    let (mut client, server) = start_stub();
+    client.request("qSupported:swbreak+");
+
+    assert_eq!(client.request("m0,3"), "310024");
+    assert_eq!(client.request("M3000,2:beef"), "OK");
+    assert_eq!(client.request("m3000,2"), "beef");
+
+    assert_eq!(client.request("Z2,2000,1"), "OK");
+    assert_eq!(client.request("c"), "T05watch:2000;");
+    assert_eq!(client.request("p6"), "0800");
+    assert_eq!(client.request("z2,2000,1"), "OK");
+
+    assert_eq!(client.request("Z0,9,1"), "OK");
+    assert_eq!(client.request("c"), "T05swbreak:;");
+    assert_eq!(client.request("p6"), "0900");
+    assert_eq!(client.request("p0"), "43");
+
+    // Resuming from a breakpoint runs around the loop back to it
+    assert_eq!(client.request("c"), "T05swbreak:;");
+    assert_eq!(client.request("p0"), "44");
+
+    assert_eq!(client.request("D"), "OK");
+    server.join().unwrap();
+}
+
+#[test]
+fn test_target_description_and_monitor() {
+    let (mut client, server) = start_stub();
+
+    let xml = client.request("qXfer:features:read:target.xml:0,fff");
+    assert!(xml.starts_with('l'));
+    assert!(xml.contains("name=\"pc\""));
+
+    let decode = |output: String| {
+        let bytes: Vec<u8> = (0..output.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&output[i..i + 2], 16).unwrap())
+            .collect();
+        String::from_utf8(bytes).unwrap()
+    };
+    // "monitor regs"
+    let output = decode(client.request("qRcmd,72656773"));
+    assert!(output.starts_with("A=00 F=00"));
+    // "monitor game"
+    let output = decode(client.request("qRcmd,67616d65"));
+    assert_eq!(output, "Game state is not available for this game\n");
+    // "monitor cheat"
+    let output = decode(client.request("qRcmd,6368656174"));
+    assert_eq!(output, "This target has no cheat support\n");
+    // "monitor search new"
+    let output = decode(client.request("qRcmd,736561726368206e6577"));
+    assert_eq!(output, "1024 candidates\n");
+
+    assert_eq!(client.request("D"), "OK");
+    server.join().unwrap();
+}
@@ -0,0 +1,54 @@
+use crate::emulator::hiscore::{Hiscore, SPACE_INVADERS};
+use crate::emulator::machine::SpaceInvadersMachine;
+use std::fs;
+use std::io::ErrorKind;
+
+#[test]
+fn test_restores_once_game_is_ready() {
+    let mut memory = vec![0u8; 0x10000];
+    let mut hiscore = Hiscore::new(SPACE_INVADERS);
+    assert!(!hiscore.set_saved(&[1, 2, 3]));
+    assert!(hiscore.set_saved(&[0x50, 0x12]));
+
+    assert!(!hiscore.update(&mut memory));
+    assert_eq!(hiscore.bytes(&memory), None);
+    assert_eq!(memory[0x20F4], 0);
+
+    memory[0x20F6..0x20F8].copy_from_slice(&[0x1C, 0x2F]);
+    assert!(hiscore.update(&mut memory));
+    assert_eq!(memory[0x20F4..0x20F6], [0x50, 0x12]);
+    assert!(!hiscore.update(&mut memory));
+
+    memory[0x20F5] = 0x20;
+    assert_eq!(hiscore.bytes(&memory), Some(vec![0x50, 0x20]));
+}
+
+#[test]
+fn test_machine_keeps_high_score_across_restarts() {
+    let path = std::env::temp_dir().join(format!("hiscore_test_{}.hi", std::process::id()));
+
+    let mut machine = SpaceInvadersMachine::new();
+    machine.save_hiscore(&path).unwrap();
+    assert!(!path.exists(), "saved before the game set up its RAM");
+    machine.do_cpu();
+    machine.get_state_mut().memory[0x20F4..0x20F6].copy_from_slice(&[0x70, 0x34]);
+    machine.save_hiscore(&path).unwrap();
+
+    let mut machine = SpaceInvadersMachine::new();
+    machine.hiscore_mut().unwrap().load(&path).unwrap();
+    for _ in 0..10 {
+        machine.run_frame();
+    }
+    assert_eq!(machine.inspector().high_score(), 3470);
+
+    machine.reset();
+    assert_eq!(machine.inspector().high_score(), 0);
+    machine.run_frame();
+    assert_eq!(machine.inspector().high_score(), 3470);
+
+    fs::write(&path, [1, 2, 3]).unwrap();
+    let error = machine.hiscore_mut().unwrap().load(&path).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    fs::remove_file(&path).unwrap();
+    assert!(machine.hiscore_mut().unwrap().load(&path).is_ok());
+}
@@ -0,0 +1,57 @@
+use crate::assembler::assemble;
+use crate::emulator::cpu::{emulate_8080_op, CPU};
+use crate::emulator::history::InstructionHistory;
+use crate::symbols::{SymbolFormat, SymbolTable};
+
+#[test]
+fn test_ring_buffer_keeps_latest_entries() {
+    let mut history = InstructionHistory::new(3);
+    for address in 0..5u16 {
+        history.record(address, [0x00, 0, 0]);
+    }
+    let addresses: Vec<u16> = history.entries().map(|entry| entry.address).collect();
+    assert_eq!(addresses, [2, 3, 4]);
+
+    history.set_capacity(0);
+    history.record(0x10, [0x00, 0, 0]);
+    assert!(!history.is_enabled());
+    assert!(history.is_empty());
+}
+
+#[test]
+fn test_history_formats_when_read() {
+    let program = assemble(
+        "
+        LXI     SP,2400H
+        LXI     H,1234H
+        CALL    sub
+sub:    MOV     A,M
+",
+    )
+    .unwrap();
+    let mut cpu = CPU::new();
+    cpu.state.history.set_capacity(8);
+    program.load_into(&mut cpu.state.memory);
+    for _ in 0..4 {
+        emulate_8080_op(&mut cpu.state);
+    }
+    // Overwriting the code afterwards does not change what was recorded
+    cpu.state.memory[..10].fill(0);
+
+    let mnemonics: Vec<String> = cpu
+        .state
+        .get_instructions_in_order()
+        .into_iter()
+        .map(|info| info.mnemonic)
+        .collect();
+    assert_eq!(
+        mnemonics,
+        ["LXI SP,$2400", "LXI H,$1234", "CALL $0009", "MOV A,M"]
+    );
+
+    let symbols = SymbolTable::parse("0009 sub\n", SymbolFormat::Simple);
+    let named = cpu.state.history.instructions(Some(&symbols));
+    assert_eq!(named[2].mnemonic, "CALL sub");
+    assert_eq!(named[2].address, 0x0006);
+    assert_eq!(named[2].opcode, 0xCD);
+}
@@ -0,0 +1,63 @@
+use crate::debugger::DebugTarget;
+use crate::emulator::input::{COIN, PLAYER1_START};
+use crate::emulator::inspector::GameInspector;
+use crate::emulator::machine::SpaceInvadersMachine;
+
+#[test]
+fn test_decodes_hand_written_ram() {
+    let mut memory = vec![0u8; 0x10000];
+    memory[0x20EF] = 1;
+    memory[0x2067] = 0x21;
+    memory[0x20F8..0x20FE].copy_from_slice(&[0x50, 0x12, 0x1C, 0x27, 0x05, 0x00]);
+    memory[0x20F4..0x20F6].copy_from_slice(&[0x90, 0x99]);
+    memory[0x20EB] = 0x12;
+    memory[0x21FF] = 2;
+    memory[0x22FF] = 3;
+    memory[0x21FE] = 1;
+    memory[0x2100] = 1;
+    memory[0x210A] = 1;
+    memory[0x2136] = 1;
+    memory[0x2082] = 3;
+    memory[0x2084] = 1;
+    memory[0x208A] = 0x40;
+    memory[0x208C] = 0xFE;
+
+    let state = GameInspector::new(&memory).snapshot();
+    assert!(state.in_game);
+    assert_eq!(state.current_player, 1);
+    assert_eq!(state.scores, [1250, 5]);
+    assert_eq!(state.high_score, 9990);
+    assert_eq!(state.credits, 12);
+    assert_eq!(state.lives, [2, 3]);
+    assert_eq!(state.wave, 1);
+    assert_eq!(state.aliens.remaining, 3);
+    assert_eq!(state.aliens.alive_rows, [0x401, 0, 0, 0, 0x400]);
+    assert!(state.ufo.active);
+    assert_eq!(state.ufo.x, 0x40);
+    assert_eq!(state.ufo.direction, -1);
+
+    let json = serde_json::to_value(&state).unwrap();
+    assert_eq!(json["scores"][0], 1250);
+    assert_eq!(json["ufo"]["direction"], -1);
+}
+
+#[test]
+fn test_new_game_state() {
+    let mut machine = SpaceInvadersMachine::new();
+    for (input, frames) in [(0, 60), (COIN, 5), (0, 30), (PLAYER1_START, 5), (0, 250)] {
+        machine.board_mut().set_pressed(1, input);
+        for _ in 0..frames {
+            machine.run_frame();
+        }
+    }
+    let state = machine.inspector().snapshot();
+    assert!(state.in_game);
+    assert_eq!(state.credits, 0);
+    assert_eq!(state.scores, [0, 0]);
+    assert_eq!(state.lives[0], 2);
+    assert_eq!(state.aliens.remaining, 55);
+    assert_eq!(state.aliens.alive_rows, [0x7FF; 5]);
+    assert!(state.player_alive);
+    assert!(state.to_string().contains("Playing, player 1  score 0 / 0"));
+    assert_eq!(DebugTarget::inspector(&machine).unwrap().snapshot(), state);
+}
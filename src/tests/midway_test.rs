@@ -0,0 +1,165 @@
+use crate::assembler::assemble;
+use crate::emulator::cpu::emulate_8080_op;
+use crate::emulator::data_types::State8080;
+use crate::emulator::io::IoBus;
+use crate::emulator::machine::{SpaceInvadersMachine, ROM_PATH};
+use crate::emulator::midway::{self, BoardProfile, MidwayBoard, ShiftPorts, GUNFIGHT, INVADERS};
+
+fn press(machine: &mut SpaceInvadersMachine, input: &str, frames: usize) {
+    assert!(machine.board_mut().set_input(input, true));
+    for _ in 0..frames {
+        machine.run_frame();
+    }
+    machine.board_mut().set_input(input, false);
+    for _ in 0..30 {
+        machine.run_frame();
+    }
+}
+
+fn lit_pixels(machine: &SpaceInvadersMachine) -> u32 {
+    machine.get_memory()[0x2400..0x4000]
+        .iter()
+        .map(|byte| byte.count_ones())
+        .sum()
+}
+
+#[test]
+fn test_board_ports_follow_inputs_and_dips() {
+    let mut board = MidwayBoard::new(&INVADERS);
+    assert_eq!(board.port_value(0), 0x0F);
+    assert_eq!(board.port_value(2), 0x00);
+    assert_eq!(board.dip("Lives"), Some("3"));
+
+    assert!(board.set_input("coin", true));
+    assert!(board.set_input("P2 Right", true));
+    assert_eq!(board.input(1), 0x01);
+    assert_eq!(board.input(2), 0x40);
+    assert!(board.set_dip("Lives", "6"));
+    assert!(board.set_dip("Bonus life", "1000"));
+    assert_eq!(board.input(2), 0x4B);
+    assert_eq!(board.dip("lives"), Some("6"));
+    assert!(!board.set_input("P1 Up", true));
+    assert!(!board.set_dip("Lives", "7"));
+
+    board.output(4, 0xAB);
+    board.output(4, 0xCD);
+    board.output(2, 4);
+    assert_eq!(board.input(3), 0xDA);
+
+    let mut gunfight = MidwayBoard::new(&GUNFIGHT);
+    assert!(gunfight.set_input("P2 Fire", true));
+    assert_eq!(gunfight.input(1), 0x80);
+    assert!(gunfight.set_dip("Game time", "90"));
+    assert_eq!(gunfight.input(2), 0x30);
+    assert_eq!(midway::find("gunfight"), Some(&GUNFIGHT));
+}
+
+#[test]
+fn test_profiles_disassemble_their_own_rom() {
+    for profile in midway::PROFILES {
+        let rom = &profile.disassembly.rom;
+        assert_eq!(rom.start, 0, "{}", profile.name());
+        assert!(rom.end >= profile.rom.size(), "{}", profile.name());
+    }
+    assert_eq!(GUNFIGHT.disassembly.name, "Gun Fight");
+}
+
+static REVERSING: BoardProfile = BoardProfile {
+    shift: ShiftPorts {
+        reversible: true,
+        ..INVADERS.shift
+    },
+    ..INVADERS
+};
+
+#[test]
+fn test_reversing_shifter() {
+    let mut board = MidwayBoard::new(&REVERSING);
+    board.output(4, 0xAB);
+    board.output(4, 0xCD);
+    board.output(2, 4);
+    assert_eq!(board.input(3), 0xDA);
+    // Bit 3 of the count reverses the result
+    board.output(2, 0x0C);
+    assert_eq!(board.input(3), 0x5B);
+
+    // Boards without the reversing shifter ignore it
+    let mut board = MidwayBoard::new(&INVADERS);
+    board.output(4, 0xAB);
+    board.output(4, 0xCD);
+    board.output(2, 0x0C);
+    assert_eq!(board.input(3), 0xDA);
+}
+
+#[test]
+fn test_invaders_lives_dip() {
+    let mut reserve = Vec::new();
+    for lives in ["3", "4"] {
+        let mut machine = SpaceInvadersMachine::from_profile(&INVADERS, ROM_PATH).unwrap();
+        assert!(machine.board_mut().set_dip("Lives", lives));
+        for _ in 0..120 {
+            machine.run_frame();
+        }
+        press(&mut machine, "Coin", 5);
+        press(&mut machine, "P1 Start", 5);
+        reserve.push(machine.get_memory()[0x21FF]);
+    }
+    // One ship is in play, the rest wait at the bottom of the screen
+    assert_eq!(reserve, [2, 3]);
+}
+
+#[test]
+fn test_gunfight_runs_on_the_board() {
+    let mut machine = SpaceInvadersMachine::from_profile(&GUNFIGHT, &["roms"]).unwrap();
+    assert_eq!(machine.profile().name(), "gunfight");
+    assert!(machine.hiscore().is_none());
+    for _ in 0..300 {
+        machine.run_frame();
+    }
+    let attract = lit_pixels(&machine);
+    assert!(attract > 0);
+
+    press(&mut machine, "Coin", 5);
+    press(&mut machine, "P1 Start", 5);
+    for _ in 0..120 {
+        machine.run_frame();
+    }
+    // Both cowboys and the cactus are drawn once play starts
+    assert!(lit_pixels(&machine) > attract);
+}
+
+#[test]
+fn test_sign_conditional_jumps_and_calls() {
+    let program = assemble(
+        "
+        LXI     SP,2400H
+        MVI     B,0
+        MVI     A,1
+        ORA     A
+        JP      plus
+        INR     B
+plus:   CP      count
+        MVI     A,80H
+        ORA     A
+        JP      wrong
+        CP      wrong
+        JM      minus
+        INR     B
+minus:  CM      count
+done:   JMP     done
+count:  INR     B
+        INR     B
+        RET
+wrong:  MVI     B,0FFH
+        JMP     done
+",
+    )
+    .unwrap();
+    let done = program.symbols.address_of("done").unwrap();
+    let mut state = State8080::default();
+    program.load_into(&mut state.memory);
+    while state.pc != done {
+        emulate_8080_op(&mut state);
+    }
+    assert_eq!(state.b, 4);
+}
@@ -1 +1,24 @@
 mod cpu_diag_test;
+mod cpu_test;
+mod disassembler_test;
+mod gdb_stub_test;
+mod recursive_disassembler_test;
+mod symbols_test;
+mod assembler_test;
+mod trace_test;
+mod history_test;
+mod recompiler_test;
+mod batch_test;
+mod env_test;
+mod inspector_test;
+mod hiscore_test;
+mod cheat_test;
+mod romset_test;
+mod midway_test;
+mod colour_test;
+mod cpm_test;
+mod disk_test;
+mod altair_test;
+mod dcdd_test;
+mod board_test;
+mod pic_test;
@@ -0,0 +1,288 @@
+use crate::assembler::assemble;
+use crate::emulator::board::Board;
+use crate::emulator::console::ScriptConsole;
+use crate::emulator::cpm::Exit;
+use crate::emulator::cpu::{acknowledge_interrupt, InterruptSource};
+use crate::emulator::data_types::State8080;
+use crate::emulator::io::{Device, PortMap};
+use crate::emulator::pic::{self, Pic8259};
+use crate::emulator::pit::Pit8253;
+use crate::emulator::usart::{self, Usart8251};
+
+/// Puts the same instruction on the bus for every acknowledge.
+struct Bus(Vec<u8>);
+
+impl InterruptSource for Bus {
+    fn requesting(&mut self) -> bool {
+        true
+    }
+
+    fn acknowledge(&mut self, cycle: usize) -> u8 {
+        self.0[cycle]
+    }
+}
+
+fn interrupted_state() -> State8080 {
+    let mut state = State8080 {
+        pc: 0x0200,
+        sp: 0x1000,
+        int_enable: true,
+        ..Default::default()
+    };
+    state.memory[0x0200..0x0203].copy_from_slice(&[0x3E, 0x42, 0x76]);
+    state
+}
+
+fn return_address(state: &State8080) -> u16 {
+    u16::from_le_bytes([
+        state.memory[state.sp as usize],
+        state.memory[state.sp as usize + 1],
+    ])
+}
+
+#[test]
+fn test_acknowledge_calls_through_bus() {
+    // A CALL returns to the interrupted instruction
+    let mut state = interrupted_state();
+    let cycles = acknowledge_interrupt(&mut state, &mut Bus(vec![0xCD, 0x34, 0x12]));
+    assert_eq!(cycles, Some(17));
+    assert_eq!(state.pc, 0x1234);
+    assert_eq!(state.sp, 0x0FFE);
+    assert_eq!(return_address(&state), 0x0200);
+    assert!(!state.int_enable);
+    assert_eq!(&state.memory[0x0200..0x0203], &[0x3E, 0x42, 0x76]);
+
+    // So does an RST
+    let mut state = interrupted_state();
+    acknowledge_interrupt(&mut state, &mut Bus(vec![0xEF]));
+    assert_eq!(state.pc, 0x0028);
+    assert_eq!(return_address(&state), 0x0200);
+
+    // Even with the stack just above the interrupted instruction
+    let mut state = interrupted_state();
+    state.sp = 0x0203;
+    acknowledge_interrupt(&mut state, &mut Bus(vec![0xCD, 0x34, 0x12]));
+    assert_eq!((state.pc, state.sp), (0x1234, 0x0201));
+    assert_eq!(return_address(&state), 0x0200);
+    assert_eq!(state.memory[0x0200], 0x3E);
+
+    // A NOP leaves the CPU and memory alone
+    let mut state = interrupted_state();
+    let memory = state.memory;
+    assert_eq!(
+        acknowledge_interrupt(&mut state, &mut Bus(vec![0x00])),
+        Some(4)
+    );
+    assert_eq!((state.pc, state.sp), (0x0200, 0x1000));
+    assert!(!state.int_enable);
+    assert!(state.memory == memory);
+
+    // Nothing is taken with interrupts disabled
+    let mut state = interrupted_state();
+    state.int_enable = false;
+    assert_eq!(
+        acknowledge_interrupt(&mut state, &mut Bus(vec![0xFF])),
+        None
+    );
+    assert_eq!(state.pc, 0x0200);
+}
+
+#[test]
+fn test_acknowledge_takes_conditional_calls() {
+    // CNZ is taken with the zero flag clear
+    let mut state = interrupted_state();
+    let cycles = acknowledge_interrupt(&mut state, &mut Bus(vec![0xC4, 0x34, 0x12]));
+    assert_eq!(cycles, Some(17));
+    assert_eq!((state.pc, state.sp), (0x1234, 0x0FFE));
+    assert_eq!(return_address(&state), 0x0200);
+
+    // and skipped with it set, leaving PC on the interrupted instruction
+    let mut state = interrupted_state();
+    state.cc.z = true;
+    let cycles = acknowledge_interrupt(&mut state, &mut Bus(vec![0xC4, 0x34, 0x12]));
+    assert_eq!(cycles, Some(11));
+    assert_eq!((state.pc, state.sp), (0x0200, 0x1000));
+    assert!(!state.int_enable);
+
+    // CM is taken on the sign flag
+    let mut state = interrupted_state();
+    state.cc.s = true;
+    acknowledge_interrupt(&mut state, &mut Bus(vec![0xFC, 0x34, 0x12]));
+    assert_eq!(state.pc, 0x1234);
+}
+
+#[test]
+#[should_panic(expected = "Instruction 0x06 cannot be taken during an interrupt acknowledge")]
+fn test_acknowledge_refuses_other_instructions() {
+    let mut state = interrupted_state();
+    acknowledge_interrupt(&mut state, &mut Bus(vec![0x06, 0x99]));
+}
+
+/// Single, edge triggered, vectors every 4 bytes from 0x2040, auto EOI off.
+fn pic() -> Pic8259 {
+    let mut pic = Pic8259::new();
+    pic.write(0, 0x56);
+    pic.write(1, 0x20);
+    pic
+}
+
+/// The three bytes the PIC answers INTA with.
+fn inta(pic: &mut Pic8259) -> [u8; 3] {
+    assert!(pic.requesting());
+    [pic.acknowledge(0), pic.acknowledge(1), pic.acknowledge(2)]
+}
+
+#[test]
+fn test_pic_priority_and_eoi() {
+    let mut pic = pic();
+    assert!(!pic.requesting());
+    pic.set_line(3, true);
+    pic.set_line(1, true);
+    // There is no IR8
+    pic.set_line(8, true);
+    assert_eq!(pic.request_register(), 0x0A);
+
+    assert_eq!(inta(&mut pic), [0xCD, 0x44, 0x20]);
+    assert_eq!(pic.in_service_register(), 0x02);
+    // IR3 waits for IR1's EOI
+    assert!(!pic.requesting());
+    pic.write(0, pic::EOI);
+    assert_eq!(inta(&mut pic), [0xCD, 0x4C, 0x20]);
+
+    // But a higher priority gets in
+    pic.set_line(0, true);
+    assert_eq!(inta(&mut pic), [0xCD, 0x40, 0x20]);
+    assert_eq!(pic.in_service_register(), 0x09);
+    pic.write(0, pic::SPECIFIC_EOI | 3);
+    assert_eq!(pic.in_service_register(), 0x01);
+
+    // Reading the registers back
+    pic.write(0, pic::READ_ISR);
+    assert_eq!(pic.read(0), 0x01);
+    pic.write(0, pic::READ_IRR);
+    assert_eq!(pic.read(0), 0x00);
+}
+
+#[test]
+fn test_pic_masking_and_rotation() {
+    let mut pic = pic();
+    pic.write(1, 0x04);
+    assert_eq!(pic.read(1), 0x04);
+    pic.set_line(2, true);
+    assert!(!pic.requesting());
+    pic.write(1, 0x00);
+    assert_eq!(inta(&mut pic), [0xCD, 0x48, 0x20]);
+
+    // Rotating on EOI puts IR2 last, so IR5 beats IR0
+    pic.write(0, pic::ROTATE_EOI);
+    pic.set_line(0, true);
+    pic.set_line(5, true);
+    assert_eq!(inta(&mut pic)[1], 0x54);
+    pic.write(0, pic::EOI);
+    assert_eq!(inta(&mut pic)[1], 0x40);
+    pic.write(0, pic::EOI);
+
+    // Set priority makes IR6 the lowest, so IR7 is now the highest
+    pic.write(0, pic::SET_PRIORITY | 6);
+    pic.set_line(5, false);
+    pic.set_line(5, true);
+    pic.set_line(7, true);
+    for expected in [0x87, 0x85, 0x00] {
+        pic.write(0, pic::POLL);
+        assert_eq!(pic.read(0), expected);
+        pic.write(0, pic::EOI);
+    }
+}
+
+#[test]
+fn test_pic_auto_eoi_and_level_triggering() {
+    // Level triggered, interval 8 from 0x3000, with ICW4 asking for auto
+    // EOI
+    let mut pic = Pic8259::new();
+    pic.write(0, 0x1B);
+    pic.write(1, 0x30);
+    pic.write(1, pic::ICW4_AUTO_EOI);
+
+    pic.set_line(4, true);
+    assert_eq!(inta(&mut pic), [0xCD, 0x20, 0x30]);
+    assert_eq!(pic.in_service_register(), 0x00);
+    // Still held high, so it asks again
+    assert_eq!(inta(&mut pic), [0xCD, 0x20, 0x30]);
+    pic.set_line(4, false);
+    assert!(!pic.requesting());
+}
+
+// Echoes the USART from IR1 and counts timer interrupts on IR0 at 0100H,
+// through the PIC's vectors at 0040H. The main program halts between
+// interrupts until the timer has fired 3 times.
+const BOARD: &str = "
+        ORG     0
+        LXI     SP,1000H
+        MVI     A,56H
+        OUT     0C0H
+        XRA     A
+        OUT     0C1H
+        OUT     0C1H
+        MVI     A,4EH
+        OUT     0EDH
+        MVI     A,15H
+        OUT     0EDH
+        MVI     A,34H
+        OUT     0DFH
+        MVI     A,0D0H
+        OUT     0DCH
+        MVI     A,07H
+        OUT     0DCH
+        EI
+WAIT:   HLT
+        LDA     100H
+        CPI     3
+        JNZ     WAIT
+        DI
+DONE:   HLT
+
+        ORG     40H
+        JMP     TIMER
+        NOP
+        JMP     SERIAL
+
+TIMER:  PUSH    PSW
+        LDA     100H
+        INR     A
+        STA     100H
+        JMP     EXIT
+
+SERIAL: PUSH    PSW
+        IN      0ECH
+        OUT     0ECH
+EXIT:   MVI     A,20H
+        OUT     0C0H
+        POP     PSW
+        EI
+        RET
+";
+
+#[test]
+fn test_pic_vectors_board_interrupts() {
+    let mut ports = PortMap::new();
+    ports
+        .attach(0xC0, Pic8259::new())
+        .attach(0xDC, Pit8253::new(2_000_000, [2_000_000, 0, 0]))
+        .attach(0xEC, Usart8251::new(ScriptConsole::new("abc")))
+        .connect(0xDC, 0, 0xC0, 0)
+        .connect(0xEC, usart::RX_READY_PIN, 0xC0, 1);
+    let mut board = Board::new(ports);
+    let program = assemble(BOARD).unwrap();
+    board.load(0, &program.to_bytes());
+
+    let done = program.symbols.address_of("DONE").unwrap();
+    assert_eq!(board.run(), Exit::Halted { pc: done });
+    assert_eq!(board.get_state().memory[0x100], 3);
+    assert_eq!(board.get_state().sp, 0x1000);
+
+    let ports = board.ports();
+    let usart = ports.get::<Usart8251<ScriptConsole>>(0xEC).unwrap();
+    assert_eq!(usart.console().output_text(), "abc");
+    let pic = ports.get::<Pic8259>(0xC0).unwrap();
+    assert_eq!(pic.in_service_register(), 0);
+}
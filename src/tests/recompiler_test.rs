@@ -0,0 +1,71 @@
+use crate::assembler::assemble;
+use crate::emulator::cpu::generate_interrupt;
+use crate::emulator::data_types::State8080;
+use crate::emulator::midway::{MidwayBoard, INVADERS};
+use crate::emulator::recompiler::{run_lockstep, Recompiler};
+
+const SELF_MODIFYING: &str = "
+        LXI     SP,2400H
+        MVI     C,3
+loop:   LDA     patch+1
+        INR     A
+        STA     patch+1
+patch:  MVI     B,0
+        DCR     C
+        JNZ     loop
+done:   JMP     done
+";
+
+fn load_invaders(state: &mut State8080) {
+    state.io = Some(Box::new(MidwayBoard::new(&INVADERS)));
+    let mut offset = 0;
+    for name in ["h", "g", "f", "e"] {
+        let rom = std::fs::read(format!("roms/space_invaders/invaders.{}", name)).unwrap();
+        state.memory[offset..offset + rom.len()].copy_from_slice(&rom);
+        offset += rom.len();
+    }
+}
+
+#[test]
+fn test_recompiler_matches_interpreter_on_space_invaders() {
+    let mut reference = State8080::default();
+    let mut recompiled = State8080::default();
+    load_invaders(&mut reference);
+    load_invaders(&mut recompiled);
+    let mut recompiler = Recompiler::new();
+
+    let mut which = 1;
+    for _ in 0..120 {
+        let mut cycles = 0;
+        while cycles < 16_666 {
+            match run_lockstep(&mut recompiler, &mut reference, &mut recompiled) {
+                Ok(executed) => cycles += executed.cycles,
+                Err(mismatch) => panic!("{}", mismatch),
+            }
+        }
+        if recompiled.int_enable {
+            generate_interrupt(&mut reference, which);
+            generate_interrupt(&mut recompiled, which);
+            which = 3 - which;
+        }
+    }
+    let stats = recompiler.stats();
+    assert!(stats.blocks_executed > stats.blocks_compiled * 10);
+}
+
+#[test]
+fn test_recompiler_rebuilds_modified_code() {
+    let program = assemble(SELF_MODIFYING).unwrap();
+    let done = program.symbols.address_of("done").unwrap();
+    let mut reference = State8080::default();
+    let mut recompiled = State8080::default();
+    program.load_into(&mut reference.memory);
+    program.load_into(&mut recompiled.memory);
+
+    let mut recompiler = Recompiler::new();
+    while recompiled.pc != done {
+        run_lockstep(&mut recompiler, &mut reference, &mut recompiled).unwrap();
+    }
+    assert_eq!(recompiled.b, 3);
+    assert_eq!(recompiler.stats().invalidations, 2);
+}
@@ -0,0 +1,55 @@
+use crate::disassembler::{DisassemblyProfile, RecursiveDisassembler};
+use crate::utils::read_bin_file;
+
+// 0000: JMP $0006 / 0003: data 'H','I',0 / 0006: CALL $000C / 0009: JMP $0006
+// 000C: LDA $0003 / 000F: RET
+const PROGRAM: [u8; 16] = [
+    0xC3, 0x06, 0x00, 0x48, 0x49, 0x00, 0xCD, 0x0C, 0x00, 0xC3, 0x06, 0x00, 0x3A, 0x03, 0x00, 0xC9,
+];
+
+#[test]
+fn test_follows_flow_and_marks_data() {
+    let mut disassembler = RecursiveDisassembler::new(&PROGRAM, 0..PROGRAM.len());
+    disassembler.trace(&[0x0000]);
+
+    for addr in [0x0000, 0x0006, 0x0009, 0x000C, 0x000F] {
+        assert!(disassembler.is_code(addr), "{:04X} should be code", addr);
+    }
+    for addr in 0x0003..0x0006 {
+        assert!(!disassembler.is_code(addr), "{:04X} should be data", addr);
+    }
+
+    let labels = disassembler.labels();
+    assert_eq!(
+        labels.keys().copied().collect::<Vec<_>>(),
+        [0x00, 0x03, 0x06, 0x0C]
+    );
+
+    disassembler.set_label(0x000C, "PrintMessage");
+    let listing = disassembler.listing("test");
+    assert!(listing.contains("ORG     0000H"));
+    assert!(listing.contains("JMP     L0006"));
+    assert!(listing.contains("CALL    PrintMessage"));
+    assert!(listing.contains("LDA     L0003"));
+    assert!(listing.contains("DB      48H,49H,00H"));
+}
+
+#[test]
+fn test_space_invaders_profile() {
+    let mut memory = vec![0u8; 0x10000];
+    let mut offset = 0;
+    for file in ["h", "g", "f", "e"] {
+        let rom = read_bin_file(&format!("roms/space_invaders/invaders.{}", file));
+        memory[offset..offset + rom.len()].copy_from_slice(&rom);
+        offset += rom.len();
+    }
+
+    let profile = DisassemblyProfile::SPACE_INVADERS;
+    let disassembler = RecursiveDisassembler::from_profile(&memory, &profile);
+    for &entry in profile.entry_points {
+        assert!(disassembler.is_code(entry));
+    }
+    // The message and sprite tables at the top of the ROM are never executed
+    assert!(!disassembler.is_code(0x1D00));
+    assert!(disassembler.listing(profile.name).contains("DB      "));
+}
@@ -0,0 +1,175 @@
+use crate::emulator::machine::{SpaceInvadersMachine, ROM_PATH};
+use crate::emulator::midway;
+use crate::emulator::romset::{self, RomError, GUNFIGHT, INVADERS};
+use std::fs;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+#[test]
+fn test_known_sets_load_and_identify() {
+    let image = INVADERS.load("roms/space_invaders").unwrap().program;
+    assert_eq!(image.len(), 0x2000);
+    assert_eq!(fs::read("roms/space_invaders/invaders").unwrap(), image);
+    assert_eq!(
+        GUNFIGHT.load("roms/gunfight").unwrap().program.len(),
+        0x1000
+    );
+
+    let (set, file) = romset::identify(&image).unwrap();
+    assert_eq!((set.name, file), ("invaders", None));
+    let (set, file) = romset::identify(&image[0x1000..0x1800]).unwrap();
+    assert_eq!((set.name, file.unwrap().name), ("invaders", "invaders.f"));
+    let data = fs::read("roms/gunfight/7609e.bin").unwrap();
+    assert_eq!(romset::identify(&data).unwrap().0.name, "gunfight");
+
+    let error = romset::identify(&[0; 16]).unwrap_err();
+    assert!(matches!(error, RomError::Unknown { size: 16, .. }));
+    assert_eq!(romset::find("gunfight"), Some(&GUNFIGHT));
+}
+
+#[test]
+fn test_bad_rom_sets_name_the_file() {
+    let dir = std::env::temp_dir().join(format!("romset_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let image = INVADERS.load("roms/space_invaders").unwrap().program;
+    for (file, data) in INVADERS.files.iter().zip(image.chunks(0x800)) {
+        fs::write(dir.join(file.name), data).unwrap();
+    }
+    assert!(SpaceInvadersMachine::from_rom_dir(&dir).is_ok());
+
+    let mut corrupt = image[0x800..0x1000].to_vec();
+    corrupt[0x123] ^= 0x40;
+    fs::write(dir.join("invaders.g"), &corrupt).unwrap();
+    let error = INVADERS.load(&dir).unwrap_err();
+    assert!(matches!(
+        error,
+        RomError::BadChecksum {
+            expected: 0x6bfaca4a,
+            ..
+        }
+    ));
+    assert!(error.to_string().contains("invaders.g"));
+
+    fs::write(dir.join("invaders.g"), &corrupt[..0x400]).unwrap();
+    let error = INVADERS.load(&dir).unwrap_err();
+    assert!(matches!(
+        error,
+        RomError::WrongSize {
+            expected: 0x800,
+            actual: 0x400,
+            ..
+        }
+    ));
+
+    fs::remove_file(dir.join("invaders.g")).unwrap();
+    let error = SpaceInvadersMachine::from_rom_dir(&dir).err().unwrap();
+    assert!(matches!(
+        error,
+        RomError::Missing {
+            set: "invaders",
+            ..
+        }
+    ));
+    assert!(error.to_string().ends_with("invaders.g"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+fn zip_of(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, data) in files {
+        writer.start_file(*name, options).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_zip_sets_match_by_name_or_crc() {
+    let image = INVADERS.load("roms/space_invaders").unwrap().program;
+    let parts: Vec<&[u8]> = image.chunks(0x800).collect();
+    // Upper case, a directory prefix and a renamed file are all found
+    let zip = zip_of(&[
+        ("readme.txt", b"not a rom"),
+        ("invaders/INVADERS.E", parts[3]),
+        ("invaders.f", parts[2]),
+        ("renamed.bin", parts[1]),
+        ("invaders.h", parts[0]),
+    ]);
+    assert_eq!(INVADERS.load_zip_bytes(&zip).unwrap().program, image);
+    let machine = SpaceInvadersMachine::from_zip_bytes(&zip).unwrap();
+    assert_eq!(machine.get_memory()[..0x2000], image[..]);
+
+    let zip = zip_of(&[("invaders.h", parts[0]), ("invaders.g", parts[1])]);
+    let error = INVADERS.load_zip_bytes(&zip).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invaders: missing invaders.zip/invaders.f"
+    );
+    let error = INVADERS.load_zip_bytes(b"PK not really").unwrap_err();
+    assert!(matches!(error, RomError::Zip { .. }));
+}
+
+#[test]
+fn test_rom_path_search() {
+    let dir = std::env::temp_dir().join(format!("rompath_test_{}", std::process::id()));
+    let empty = dir.join("empty");
+    fs::create_dir_all(&empty).unwrap();
+    let image = INVADERS.load("roms/space_invaders").unwrap().program;
+    let files: Vec<(&str, &[u8])> = INVADERS
+        .files
+        .iter()
+        .map(|file| file.name)
+        .zip(image.chunks(0x800))
+        .collect();
+    fs::write(dir.join("invaders.zip"), zip_of(&files)).unwrap();
+
+    let rom_path = romset::split_rom_path(&format!("{}:{}", empty.display(), dir.display()));
+    assert_eq!(INVADERS.locate(&rom_path).unwrap().program, image);
+    assert_eq!(INVADERS.locate(ROM_PATH).unwrap().program, image);
+
+    let error = GUNFIGHT.locate(&rom_path).unwrap_err();
+    assert!(matches!(
+        error,
+        RomError::NotFound {
+            set: "gunfight",
+            ..
+        }
+    ));
+    assert!(GUNFIGHT.locate(&["roms"]).is_ok());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_every_profile_locates_its_set() {
+    let dir = std::env::temp_dir().join(format!("profile_test_{}", std::process::id()));
+    for profile in midway::PROFILES {
+        let set = profile.rom;
+        assert_eq!(midway::find(set.name), Some(profile));
+        assert_eq!(romset::find(set.name), Some(set));
+        if set.verified() {
+            assert!(set.locate(ROM_PATH).is_ok(), "{}", set.name);
+            continue;
+        }
+
+        // No dump of the other sets is checked in, so stand-ins of the right
+        // sizes take their place
+        let set_dir = dir.join(set.name);
+        fs::create_dir_all(&set_dir).unwrap();
+        for file in set.all_files() {
+            fs::write(set_dir.join(file.name), vec![0xA5; file.size]).unwrap();
+        }
+        let image = set.locate(&[&dir]).unwrap();
+        assert_eq!(image.program.len(), set.size(), "{}", set.name);
+        assert_eq!(image.program[set.files[0].address], 0xA5);
+        assert!(SpaceInvadersMachine::from_profile(profile, &[&dir]).is_ok());
+
+        // Sizes are still checked, and identify never guesses
+        assert!(romset::identify(&image.program).is_err());
+        fs::write(set_dir.join(set.files[0].name), [0xA5; 16]).unwrap();
+        let error = set.locate(&[&dir]).unwrap_err();
+        assert!(matches!(error, RomError::WrongSize { actual: 16, .. }));
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,68 @@
+use crate::disassembler::{decode, FormatOptions, RecursiveDisassembler};
+use crate::symbols::{SymbolFormat, SymbolTable};
+
+#[test]
+fn test_parses_symbol_formats() {
+    let simple = SymbolTable::parse(
+        "; routines\n15D3 DrawSprite\n$0000 Reset\nSHIFTX 20F0\nbogus line here\n",
+        SymbolFormat::Simple,
+    );
+    assert_eq!(simple.name_of(0x15D3), Some("DrawSprite"));
+    assert_eq!(simple.name_of(0x0000), Some("Reset"));
+    assert_eq!(simple.address_of("ShiftX"), Some(0x20F0));
+    assert_eq!(simple.len(), 3);
+
+    let sym = SymbolTable::parse(
+        "P1Score EQU 20F8H\nCredits: equ $20EB\nShips = 0x21FF\n",
+        SymbolFormat::Simple,
+    );
+    assert_eq!(sym.address_of("p1score"), Some(0x20F8));
+    assert_eq!(sym.address_of("Credits"), Some(0x20EB));
+    assert_eq!(sym.address_of("Ships"), Some(0x21FF));
+
+    let listing = SymbolTable::parse(
+        "0000  C3 D4 18     Reset:  JMP Init\n\
+         18D4  31 00 24     Init:   LXI SP,2400H  ; stack\n\
+         0003  21 00 20             LXI H,Score\n\
+         20F8               Score   EQU 20F8H\n",
+        SymbolFormat::Listing,
+    );
+    assert_eq!(listing.name_of(0x0000), Some("Reset"));
+    assert_eq!(listing.name_of(0x18D4), Some("Init"));
+    assert_eq!(listing.address_of("Score"), Some(0x20F8));
+    assert_eq!(listing.len(), 3);
+}
+
+#[test]
+fn test_resolves_names_and_numbers() {
+    let mut symbols = SymbolTable::new();
+    symbols.insert(0x15D3, "DrawSprite");
+    assert_eq!(symbols.resolve("DrawSprite"), Some(0x15D3));
+    assert_eq!(symbols.resolve("drawsprite+3"), Some(0x15D6));
+    assert_eq!(symbols.resolve("$1A00"), Some(0x1A00));
+    assert_eq!(symbols.resolve("1A00h"), Some(0x1A00));
+    assert_eq!(symbols.resolve("0x1A00"), Some(0x1A00));
+    assert_eq!(symbols.resolve("NoSuchName"), None);
+}
+
+#[test]
+fn test_disassembly_uses_symbols() {
+    let symbols = SymbolTable::parse("15D3 DrawSprite\n20F8 P1Score\n", SymbolFormat::Simple);
+    let call = decode(&[0xCD, 0xD3, 0x15], 0);
+    let text = call.format_with(&FormatOptions::default(), |addr| {
+        symbols.name_of(addr).map(str::to_string)
+    });
+    assert_eq!(text, "CALL DrawSprite");
+
+    // 0000: LDA $20F8 / 0003: CALL $0007 / 0006: HLT / 0007: RET
+    let program = [0x3A, 0xF8, 0x20, 0xCD, 0x07, 0x00, 0x76, 0xC9];
+    let symbols = SymbolTable::parse("0007 Done\n20F8 P1Score\n", SymbolFormat::Simple);
+    let mut disassembler = RecursiveDisassembler::new(&program, 0..program.len());
+    disassembler.trace(&[0x0000]);
+    disassembler.apply_symbols(&symbols);
+    let listing = disassembler.listing("test");
+    assert!(listing.contains("P1Score EQU     20F8H"));
+    assert!(listing.contains("LDA     P1Score"));
+    assert!(listing.contains("CALL    Done"));
+    assert!(listing.contains("\nDone:"));
+}
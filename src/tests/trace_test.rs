@@ -0,0 +1,131 @@
+use crate::assembler::assemble;
+use crate::emulator::cpu::{emulate_8080_op, CPU};
+use crate::emulator::trace::{diff, DiffOptions, Tracer};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SharedBuffer {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.lock().unwrap().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+const PROGRAM: &str = "
+        MVI     A,5
+        MVI     B,7
+loop:   DCR     A
+        JNZ     loop
+        LXI     SP,2400H
+done:   JMP     done
+";
+
+/// Runs `PROGRAM` for `steps` instructions with `tracer` attached.
+fn run(tracer: Tracer, steps: usize) {
+    let mut cpu = CPU::new();
+    assemble(PROGRAM).unwrap().load_into(&mut cpu.state.memory);
+    cpu.state.tracer = Some(Box::new(tracer));
+    for _ in 0..steps {
+        emulate_8080_op(&mut cpu.state);
+    }
+}
+
+#[test]
+fn test_trace_lines() {
+    let buffer = SharedBuffer::default();
+    run(Tracer::new(buffer.clone()), 4);
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(
+        lines[0],
+        "PC: 0000, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 0\t(3E 05 06 07)  MVI A,$05"
+    );
+    assert_eq!(
+        lines[3],
+        "PC: 0005, AF: 0412, BC: 0700, DE: 0000, HL: 0000, SP: 0000, CYC: 19\t(C2 04 00 31)  JNZ $0004"
+    );
+}
+
+#[test]
+fn test_trace_filters() {
+    let buffer = SharedBuffer::default();
+    run(Tracer::new(buffer.clone()).with_range(0x0004..=0x0004), 20);
+    assert!(buffer
+        .lines()
+        .iter()
+        .all(|line| line.starts_with("PC: 0004")));
+    assert_eq!(buffer.lines().len(), 5);
+
+    let buffer = SharedBuffer::default();
+    run(
+        Tracer::new(buffer.clone())
+            .with_trigger(0x0008)
+            .with_limit(2),
+        20,
+    );
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("PC: 0008"));
+    assert!(lines[1].starts_with("PC: 000B"));
+}
+
+#[test]
+fn test_diff_reports_first_divergence() {
+    let reference = "\
+PC: 0000, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 0 (3E 05 06 07)
+PC: 0002, AF: 0502, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 7 (06 07 3D C2)
+PC: 0004, AF: 0502, BC: 0700, DE: 0000, HL: 0000, SP: 0000, CYC: 14 (3D C2 04 00)
+";
+    let ours = "\
+PC: 0000, AF: 0002, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 0\t(3E 05 06 07)  MVI A,$05
+PC: 0002, AF: 0502, BC: 0000, DE: 0000, HL: 0000, SP: 0000, CYC: 7\t(06 07 3D C2)  MVI B,$07
+PC: 0004, AF: 0546, BC: 0700, DE: 0000, HL: 0000, SP: 0000, CYC: 15\t(3D C2 04 00)  DCR A
+";
+    let divergence = diff(
+        reference.as_bytes(),
+        ours.as_bytes(),
+        DiffOptions::default(),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(divergence.line, 3);
+    assert_eq!(divergence.fields, ["AF", "CYC"]);
+
+    let options = DiffOptions {
+        ignore_cycles: true,
+        compare_bytes: true,
+    };
+    let matching = &ours.replace("0546", "0502");
+    assert_eq!(
+        diff(reference.as_bytes(), matching.as_bytes(), options).unwrap(),
+        None
+    );
+
+    let truncated: String = ours
+        .lines()
+        .take(2)
+        .map(|line| format!("{}\n", line))
+        .collect();
+    let divergence = diff(reference.as_bytes(), truncated.as_bytes(), options)
+        .unwrap()
+        .unwrap();
+    assert_eq!(divergence.line, 3);
+    assert_eq!(divergence.actual, None);
+}
@@ -41,19 +41,5 @@ pub fn get_file_path() -> String {
     } else {
         file_path = args[1].clone();
     }
-    return file_path;
-}
-
-#[cfg(not(target_arch = "wasm32"))]
-pub fn load_icon() -> sdl2::surface::Surface<'static> {
-    // Initialize SDL2_image
-    let _image_context = sdl2::image::init(sdl2::image::InitFlag::JPG).unwrap();
-
-    // Load the icon image
-    let icon_path = "src/assets/invaders_mascot.bmp";
-    let icon = match sdl2::surface::Surface::load_bmp(icon_path) {
-        Ok(icon) => icon,
-        Err(error) => panic!("Problem loading icon: {:?}", error),
-    };
-    icon
+    file_path
 }